@@ -0,0 +1,56 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use {
+    criterion::{criterion_group, criterion_main, Criterion, Throughput},
+    pyoxidizerlib::py_packaging::distribution::{
+        set_hash_chunk_size, verify_location, PythonDistributionLocation, DEFAULT_HASH_CHUNK_SIZE,
+    },
+    sha2::{Digest, Sha256},
+};
+
+/// Size of the archive to verify. Roughly that of a full distribution.
+const ARCHIVE_SIZE: usize = 128 * 1024 * 1024;
+
+pub fn bench_distribution_hash(c: &mut Criterion) {
+    let temp_dir = tempfile::Builder::new()
+        .prefix("pyoxidizer-bench-")
+        .tempdir()
+        .expect("failed to create temp directory");
+
+    let data = (0..ARCHIVE_SIZE)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let path = temp_dir.path().join("dist.tar.zst");
+    std::fs::write(&path, &data).expect("failed to write archive");
+
+    let location = PythonDistributionLocation::Local {
+        local_path: path.display().to_string(),
+        sha256: hex::encode(Sha256::digest(&data)),
+    };
+
+    let mut group = c.benchmark_group("distribution.verify_location");
+    group.throughput(Throughput::Bytes(ARCHIVE_SIZE as u64));
+    group.sample_size(10);
+
+    for chunk_size in [
+        8192,
+        DEFAULT_HASH_CHUNK_SIZE,
+        256 * 1024,
+        1024 * 1024,
+        4 * 1024 * 1024,
+    ] {
+        group.bench_function(format!("chunk_{}", chunk_size), |b| {
+            set_hash_chunk_size(chunk_size).expect("failed to set chunk size");
+            b.iter(|| verify_location(&location).expect("failed to verify archive"))
+        });
+    }
+
+    group.finish();
+
+    set_hash_chunk_size(DEFAULT_HASH_CHUNK_SIZE).expect("failed to reset chunk size");
+}
+
+criterion_group!(benches, bench_distribution_hash);
+criterion_main!(benches);
@@ -495,20 +495,25 @@ pub fn run_cli() -> Result<()> {
                     "must only specify one of --download-default or --archive-path"
                 ))
             } else {
-                projectmgmt::python_distribution_extract(download_default, archive_path, dest_path)
+                projectmgmt::python_distribution_extract(
+                    &logger_context.logger,
+                    download_default,
+                    archive_path,
+                    dest_path,
+                )
             }
         }
 
         ("python-distribution-info", Some(args)) => {
             let dist_path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_info(dist_path)
+            projectmgmt::python_distribution_info(&logger_context.logger, dist_path)
         }
 
         ("python-distribution-licenses", Some(args)) => {
             let path = args.value_of("path").unwrap();
 
-            projectmgmt::python_distribution_licenses(path)
+            projectmgmt::python_distribution_licenses(&logger_context.logger, path)
         }
 
         ("run-build-script", Some(args)) => {
@@ -235,6 +235,11 @@ pub struct Environment {
     /// Directory to use for caching things.
     cache_dir: PathBuf,
 
+    /// Directory to download and extract Python distributions into.
+    ///
+    /// If not set, a directory under `cache_dir` is used.
+    python_distributions_dir: Option<PathBuf>,
+
     /// Whether we should use a Rust installation we manage ourselves.
     managed_rust: bool,
 
@@ -259,12 +264,20 @@ impl Environment {
 
         let managed_rust = std::env::var("PYOXIDIZER_SYSTEM_RUST").is_err();
 
-        Ok(Self {
+        let mut env = Self {
             pyoxidizer_source,
             cache_dir,
+            python_distributions_dir: None,
             managed_rust,
             rust_environment: Arc::new(RwLock::new(None)),
-        })
+        };
+
+        if let Ok(p) = std::env::var("PYOXIDIZER_DISTRIBUTIONS_DIR") {
+            env.set_extraction_root(&PathBuf::from(p))
+                .context("resolving PYOXIDIZER_DISTRIBUTIONS_DIR")?;
+        }
+
+        Ok(env)
     }
 
     /// Cache directory for PyOxidizer to use.
@@ -275,8 +288,30 @@ impl Environment {
     }
 
     /// Directory to use for storing Python distributions.
+    ///
+    /// Distribution archives are downloaded to and extracted in this directory.
     pub fn python_distributions_dir(&self) -> PathBuf {
-        self.cache_dir.join("python_distributions")
+        if let Some(p) = &self.python_distributions_dir {
+            p.clone()
+        } else {
+            self.cache_dir.join("python_distributions")
+        }
+    }
+
+    /// Download and extract Python distributions under the specified directory.
+    ///
+    /// The directory is created if it doesn't exist. An error is returned if
+    /// files can't be written to it.
+    pub fn set_extraction_root(&mut self, path: &Path) -> Result<()> {
+        std::fs::create_dir_all(path)
+            .with_context(|| format!("creating extraction root {}", path.display()))?;
+
+        tempfile::tempfile_in(path)
+            .map_err(|e| anyhow!("extraction root {} is not writable: {}", path.display(), e))?;
+
+        self.python_distributions_dir = Some(path.to_path_buf());
+
+        Ok(())
     }
 
     /// Directory to hold Rust toolchains.
@@ -184,8 +184,12 @@ pub fn find_resources(
     classify_files: bool,
     emit_files: bool,
 ) -> Result<()> {
-    let distribution_location =
-        default_distribution_location(&DistributionFlavor::Standalone, target_triple, None)?;
+    let distribution_location = default_distribution_location(
+        logger,
+        &DistributionFlavor::Standalone,
+        target_triple,
+        None,
+    )?;
 
     let mut temp_dir = None;
 
@@ -381,6 +385,7 @@ pub fn init_rust_project(
 }
 
 pub fn python_distribution_extract(
+    logger: &slog::Logger,
     download_default: bool,
     archive_path: Option<&str>,
     dest_path: &str,
@@ -389,12 +394,13 @@ pub fn python_distribution_extract(
         PathBuf::from(path)
     } else if download_default {
         let location = default_distribution_location(
+            logger,
             &DistributionFlavor::Standalone,
             default_target_triple(),
             None,
         )?;
 
-        resolve_python_distribution_archive(&location, Path::new(dest_path))?
+        resolve_python_distribution_archive(logger, &location, Path::new(dest_path))?
     } else {
         return Err(anyhow!("do not know what distribution to operate on"));
     };
@@ -412,7 +418,7 @@ pub fn python_distribution_extract(
     Ok(())
 }
 
-pub fn python_distribution_info(dist_path: &str) -> Result<()> {
+pub fn python_distribution_info(logger: &slog::Logger, dist_path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(dist_path))?;
     let reader = std::io::BufReader::new(fh);
 
@@ -421,7 +427,7 @@ pub fn python_distribution_info(dist_path: &str) -> Result<()> {
         .tempdir()?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst(logger, reader, temp_dir_path)?;
 
     println!("High-Level Metadata");
     println!("===================");
@@ -493,7 +499,7 @@ pub fn python_distribution_info(dist_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn python_distribution_licenses(path: &str) -> Result<()> {
+pub fn python_distribution_licenses(logger: &slog::Logger, path: &str) -> Result<()> {
     let fh = std::fs::File::open(Path::new(path))?;
     let reader = std::io::BufReader::new(fh);
 
@@ -502,7 +508,7 @@ pub fn python_distribution_licenses(path: &str) -> Result<()> {
         .tempdir()?;
     let temp_dir_path = temp_dir.path();
 
-    let dist = StandaloneDistribution::from_tar_zst(reader, temp_dir_path)?;
+    let dist = StandaloneDistribution::from_tar_zst(logger, reader, temp_dir_path)?;
 
     println!(
         "Python Distribution Licenses: {}",
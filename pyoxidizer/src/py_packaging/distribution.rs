@@ -12,26 +12,38 @@ use {
         config::PyembedPythonInterpreterConfig,
         standalone_distribution::StandaloneDistribution,
     },
-    crate::python_distributions::PYTHON_DISTRIBUTIONS,
+    crate::{
+        environment::PYOXIDIZER_VERSION,
+        python_distributions::{PythonVersion, SelectionPolicy, PYTHON_DISTRIBUTIONS},
+    },
     anyhow::{anyhow, Context, Result},
     fs2::FileExt,
+    once_cell::sync::Lazy,
     python_packaging::{
         bytecode::PythonBytecodeCompiler, module_util::PythonModuleSuffixes,
         policy::PythonPackagingPolicy, resource::PythonResource,
     },
+    serde::Deserialize,
     sha2::{Digest, Sha256},
     slog::warn,
     std::{
-        collections::HashMap,
+        borrow::Cow,
+        cell::RefCell,
+        collections::{BTreeMap, BTreeSet, HashMap},
         convert::TryFrom,
-        fs,
+        fmt, fs,
         fs::{create_dir_all, File},
         io::Read,
         ops::DerefMut,
         path::{Path, PathBuf},
-        sync::{Arc, Mutex},
+        str::FromStr,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Condvar, Mutex, RwLock,
+        },
+        time::Duration,
     },
-    tugger_common::http::get_http_client,
+    tugger_common::http::get_http_client_builder,
     tugger_file_manifest::FileEntry,
     url::Url,
     uuid::Uuid,
@@ -50,8 +62,109 @@ pub enum BinaryLibpythonLinkMode {
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum PythonDistributionLocation {
-    Local { local_path: String, sha256: String },
-    Url { url: String, sha256: String },
+    Local {
+        local_path: String,
+        sha256: String,
+    },
+    Url {
+        url: String,
+        sha256: String,
+    },
+    /// An archive stored as a layer of an OCI artifact in a container registry.
+    ///
+    /// `reference` is like `registry.example.com/python/cpython:3.9` or
+    /// `registry.example.com/python/cpython@sha256:<hex>`. When pinned by
+    /// digest, the fetched manifest is verified against it. `digest` is the
+    /// hex SHA-256 of the archive layer, which the manifest must reference.
+    /// Registries requiring authentication are authenticated to with
+    /// credentials from `set_credential_provider()`.
+    Oci {
+        reference: String,
+        digest: String,
+    },
+    /// An archive committed to a git repository, optionally stored with Git LFS.
+    ///
+    /// `repo` is anything `git fetch` accepts. e.g.
+    /// `https://git.example.com/python-dists.git`. `rev` is the branch, tag,
+    /// or commit to read `path` from. Only `rev` is fetched, without history.
+    /// Requires `git` and, for LFS files, `git-lfs` to be installed.
+    Git {
+        repo: String,
+        rev: String,
+        path: String,
+        sha256: String,
+    },
+    /// An archive split into parts at several URLs.
+    ///
+    /// The parts are downloaded in order and concatenated. `sha256` is the
+    /// digest of the concatenated archive.
+    Parts {
+        urls: Vec<String>,
+        sha256: String,
+    },
+}
+
+impl PythonDistributionLocation {
+    /// The file name of the distribution archive.
+    ///
+    /// OCI references don't name files. So `<digest>.tar.zst` is used for them.
+    pub fn filename(&self) -> Cow<'_, str> {
+        let path = match self {
+            Self::Local { local_path, .. } => local_path.as_str(),
+            Self::Url { url, .. } => url.split(['?', '#']).next().unwrap_or(url),
+            Self::Oci { digest, .. } => return Cow::Owned(format!("{}.tar.zst", digest)),
+            Self::Git { path, .. } => path.as_str(),
+            Self::Parts { urls, sha256 } => {
+                let name = urls
+                    .first()
+                    .map(|url| url.split(['?', '#']).next().unwrap_or(url))
+                    .and_then(|url| url.rsplit('/').next())
+                    .unwrap_or_default();
+
+                // Parts are conventionally named like `<archive>.part1` or `<archive>.001`.
+                return match name.rsplit_once('.') {
+                    Some((archive, suffix))
+                        if suffix.starts_with("part")
+                            || (!suffix.is_empty()
+                                && suffix.chars().all(|c| c.is_ascii_digit())) =>
+                    {
+                        Cow::Owned(archive.to_string())
+                    }
+                    _ if name.is_empty() => Cow::Owned(format!("{}.tar.zst", sha256)),
+                    _ => Cow::Owned(name.to_string()),
+                };
+            }
+        };
+
+        Cow::Borrowed(path.rsplit(['/', '\\']).next().unwrap_or(path))
+    }
+
+    /// Describe where the archive is obtained from, for humans.
+    ///
+    /// This is the local path, URL, OCI reference, `<repo>@<rev>:<path>` git
+    /// file, or space separated part URLs.
+    pub fn source(&self) -> String {
+        match self {
+            Self::Local { local_path, .. } => local_path.clone(),
+            Self::Url { url, .. } => url.clone(),
+            Self::Oci { reference, .. } => reference.clone(),
+            Self::Git {
+                repo, rev, path, ..
+            } => format!("{}@{}:{}", repo, rev, path),
+            Self::Parts { urls, .. } => urls.join(" "),
+        }
+    }
+
+    /// The hex encoded SHA-256 digest of the distribution archive.
+    pub fn sha256(&self) -> &str {
+        match self {
+            Self::Local { sha256, .. } => sha256,
+            Self::Url { sha256, .. } => sha256,
+            Self::Oci { digest, .. } => digest,
+            Self::Git { sha256, .. } => sha256,
+            Self::Parts { sha256, .. } => sha256,
+        }
+    }
 }
 
 /// Describes an obtainable Python distribution.
@@ -60,6 +173,9 @@ pub struct PythonDistributionRecord {
     /// X.Y major.minor version of Python.
     pub python_major_minor_version: String,
 
+    /// Full version of Python. e.g. `3.9.6` or `3.10.0rc1`.
+    pub python_version: PythonVersion,
+
     /// Where the distribution can be obtained from.
     pub location: PythonDistributionLocation,
 
@@ -68,6 +184,329 @@ pub struct PythonDistributionRecord {
 
     /// Whether the distribution can load prebuilt extension modules.
     pub supports_prebuilt_extension_modules: bool,
+
+    /// Build optimizations applied to the distribution.
+    ///
+    /// `None` if not known.
+    pub optimizations: Option<BuildOptimizations>,
+
+    /// Minimum operating system version the distribution runs on.
+    ///
+    /// For Apple targets, this is the deployment target the distribution was
+    /// built with. e.g. `10.9`. `None` if not known or not applicable.
+    pub min_os_version: Option<String>,
+
+    /// Leading archive path to strip when extracting with `PythonDistributionRecord::extract()`.
+    ///
+    /// `None` uses `DEFAULT_STRIP_PREFIX`.
+    pub strip_prefix: Option<String>,
+
+    /// Archive of packages to overlay onto the extracted distribution.
+    ///
+    /// This is a zstd compressed tar archive whose member paths are relative to
+    /// the extraction directory. e.g. `python/install/lib/python3.9/site-packages/foo/__init__.py`.
+    /// Its digest is verified like the distribution's. Seeded extractions are
+    /// kept apart from the distribution's plain extraction.
+    pub seed_packages: Option<PythonDistributionLocation>,
+
+    /// Arbitrary key-value metadata. e.g. `approval=granted`.
+    ///
+    /// Selection can require or exclude label values. See
+    /// `SelectionPolicy::required_labels`. Built-in records have no labels.
+    pub labels: BTreeMap<String, String>,
+
+    /// Directory convention of the extracted distribution.
+    ///
+    /// Once extracted, the distribution's `PYTHON.json` is authoritative. See
+    /// `StandaloneDistribution::layout()`.
+    pub layout: DistributionLayout,
+
+    /// Size in bytes of the distribution archive, if known.
+    ///
+    /// Consulted by `SelectionPolicy::max_footprint`. Built-in records don't
+    /// record sizes.
+    pub archive_size: Option<u64>,
+
+    /// Whether the distribution's target isn't production ready yet.
+    ///
+    /// Experimental distributions are still selected unless
+    /// `SelectionPolicy::reject_experimental` is set.
+    /// `PythonDistributionCollection::find_distribution_logged()` logs a
+    /// warning when one is.
+    pub experimental: bool,
+
+    /// Build modes of bundled libraries, keyed by library. e.g. `sqlite3` => `threadsafe`.
+    ///
+    /// Libraries missing from the map have an unknown build mode. Built-in
+    /// records have none. They are filled in from the `library_build_modes`
+    /// key of archives' `PYTHON.json` by
+    /// `PythonDistributionCollection::inspect_library_build_modes()`. See
+    /// `SelectionPolicy::required_library_modes`.
+    pub library_build_modes: BTreeMap<String, String>,
+
+    /// Names of extension modules built into the distribution. e.g. `_ssl`.
+    ///
+    /// `None` if not known. Built-in records don't know them. They are filled
+    /// in from archives by
+    /// `PythonDistributionCollection::inspect_extension_modules()`. See
+    /// `SelectionPolicy::required_modules`.
+    pub extension_modules: Option<BTreeSet<String>>,
+}
+
+/// Directory conventions of distributions.
+///
+/// Paths within distributions, e.g. of the interpreter, should be derived
+/// with this rather than assuming a convention.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DistributionLayout {
+    /// Installed files are under an `install/` directory, next to build
+    /// artifacts. This is the convention of `python-build-standalone`.
+    Install,
+    /// Installed files are at the root of the distribution.
+    #[allow(unused)]
+    Flat,
+}
+
+// `#[default]` on enum variants requires a newer Rust than we support.
+#[allow(clippy::derivable_impls)]
+impl Default for DistributionLayout {
+    fn default() -> Self {
+        Self::Install
+    }
+}
+
+impl DistributionLayout {
+    /// Derive the layout from a `PYTHON.json` path, e.g. of `python_exe`.
+    ///
+    /// Paths in `PYTHON.json` are relative to the distribution root. So paths
+    /// under `install/` denote `Install` and others `Flat`.
+    pub fn from_python_json_path(path: &str) -> Self {
+        if Path::new(path).starts_with("install") {
+            Self::Install
+        } else {
+            Self::Flat
+        }
+    }
+
+    /// Directory containing installed files, relative to the distribution root.
+    ///
+    /// Empty for `Flat`.
+    pub fn install_dir(&self) -> &'static Path {
+        match self {
+            Self::Install => Path::new("install"),
+            Self::Flat => Path::new(""),
+        }
+    }
+}
+
+impl PythonDistributionRecord {
+    /// Extract the distribution with `strip_prefix` stripped from member paths.
+    ///
+    /// See `extract_distribution()`.
+    #[allow(unused)]
+    pub fn extract(&self, logger: &slog::Logger, dest_dir: &Path) -> Result<Vec<PathBuf>> {
+        extract_distribution(
+            logger,
+            &self.location,
+            self.strip_prefix.as_deref(),
+            dest_dir,
+        )
+    }
+
+    /// The PEP 425 ABI tag of the distribution. e.g. `cp39`.
+    ///
+    /// This is derived from the record and doesn't require obtaining the
+    /// distribution, making it suitable for quick checks. Once obtained, the
+    /// distribution's `PythonDistribution::python_abi_tag()` is authoritative.
+    pub fn python_abi_tag(&self) -> String {
+        format!(
+            "cp{}{}",
+            self.python_major_minor_version.replace('.', ""),
+            if self.optimizations == Some(BuildOptimizations::Debug) {
+                "d"
+            } else {
+                ""
+            }
+        )
+    }
+
+    /// The distribution flavors this distribution satisfies.
+    pub fn supported_flavors(&self) -> FlavorSet {
+        let mut flavors = FlavorSet::default();
+        flavors.insert(&DistributionFlavor::Standalone);
+
+        if self.supports_prebuilt_extension_modules {
+            flavors.insert(&DistributionFlavor::StandaloneDynamic);
+        } else {
+            flavors.insert(&DistributionFlavor::StandaloneStatic);
+        }
+
+        flavors
+    }
+
+    /// The `python-build-standalone` release this distribution belongs to. e.g. `20210724`.
+    ///
+    /// This is derived from the GitHub release download URL of the
+    /// distribution. `None` for local distributions and other URLs.
+    pub fn release_tag(&self) -> Option<&str> {
+        match &self.location {
+            PythonDistributionLocation::Url { url, .. } => {
+                let (_, rest) = url.split_once("/releases/download/")?;
+                let tag = rest.split('/').next()?;
+
+                if tag.is_empty() {
+                    None
+                } else {
+                    Some(tag)
+                }
+            }
+            PythonDistributionLocation::Local { .. }
+            | PythonDistributionLocation::Oci { .. }
+            | PythonDistributionLocation::Git { .. }
+            | PythonDistributionLocation::Parts { .. } => None,
+        }
+    }
+
+    /// When this distribution was built, as `YYYYMMDDTHHMM` in UTC. e.g. `20210724T1424`.
+    ///
+    /// This is derived from the archive file name, which `python-build-standalone`
+    /// suffixes with the build time. e.g.
+    /// `cpython-3.9.6-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst`.
+    /// `None` if the file name has no timestamp. Unlike `release_tag()`, this
+    /// distinguishes rebuilds published under one release.
+    pub fn build_timestamp(&self) -> Option<String> {
+        let filename = self.location.filename();
+        let stem = filename.rsplit('/').next()?.split(".tar").next()?;
+        let timestamp = stem.rsplit('-').next()?;
+
+        let (date, time) = timestamp.split_once('T')?;
+        if date.len() == 8
+            && time.len() == 4
+            && date.chars().chain(time.chars()).all(|c| c.is_ascii_digit())
+        {
+            Some(timestamp.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Whether a wheel's PEP 425 ABI tag can be loaded by this distribution.
+    ///
+    /// See `is_abi_compatible()`.
+    #[allow(unused)]
+    pub fn is_abi_compatible_with(&self, wheel_tag: &str) -> bool {
+        is_abi_compatible(&self.python_abi_tag(), wheel_tag)
+    }
+
+    /// Render a Starlark `PythonDistribution()` call obtaining this distribution.
+    ///
+    /// This allows pinning a distribution in a configuration file. Only the
+    /// location is expressible in Starlark, so other fields aren't reproduced.
+    /// Errors if the location can't be expressed, i.e. isn't a local path or URL.
+    #[allow(unused)]
+    pub fn to_starlark(&self) -> Result<String> {
+        let (key, value, sha256) = match &self.location {
+            PythonDistributionLocation::Local { local_path, sha256 } => {
+                ("local_path", local_path, sha256)
+            }
+            PythonDistributionLocation::Url { url, sha256 } => ("url", url, sha256),
+            PythonDistributionLocation::Oci { .. }
+            | PythonDistributionLocation::Git { .. }
+            | PythonDistributionLocation::Parts { .. } => {
+                return Err(anyhow!(
+                    "{} cannot be expressed in Starlark",
+                    self.location.source()
+                ));
+            }
+        };
+
+        Ok(format!(
+            "PythonDistribution(\n    sha256 = {},\n    {} = {},\n    flavor = \"standalone\",\n)\n",
+            starlark_string(sha256),
+            key,
+            starlark_string(value)
+        ))
+    }
+}
+
+/// Quote a string as a Starlark string literal.
+fn starlark_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// Whether a wheel's PEP 425 ABI tag is compatible with an interpreter's ABI tag.
+///
+/// `wheel_tag` may be a compressed tag set like `cp39.abi3`. A wheel is
+/// compatible if one of its tags is `none`, matches `interpreter_tag` exactly,
+/// or is `abi3` and the interpreter is a non-debug CPython 3.
+pub fn is_abi_compatible(interpreter_tag: &str, wheel_tag: &str) -> bool {
+    let stable_abi = interpreter_tag.starts_with("cp3") && !interpreter_tag.ends_with('d');
+
+    wheel_tag
+        .split('.')
+        .any(|tag| tag == "none" || tag == interpreter_tag || (tag == "abi3" && stable_abi))
+}
+
+/// Describes the build optimizations applied to a Python distribution.
+///
+/// Variants are ordered from least to most optimized, so comparisons can be
+/// used to express a minimum optimization level.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BuildOptimizations {
+    /// A debug build.
+    Debug,
+    /// No optimizations beyond the compiler defaults.
+    Noopt,
+    /// Link-time optimization.
+    Lto,
+    /// Profile-guided optimization.
+    Pgo,
+    /// Profile-guided optimization and link-time optimization.
+    PgoLto,
+}
+
+impl TryFrom<&str> for BuildOptimizations {
+    type Error = String;
+
+    /// Parse the value used by `python-build-standalone` (e.g. in `PYTHON.json`).
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "debug" => Ok(Self::Debug),
+            "noopt" => Ok(Self::Noopt),
+            "lto" => Ok(Self::Lto),
+            "pgo" => Ok(Self::Pgo),
+            "pgo+lto" => Ok(Self::PgoLto),
+            _ => Err(format!("build optimizations {} not recognized", value)),
+        }
+    }
+}
+
+impl fmt::Display for BuildOptimizations {
+    /// Formats the value used by `python-build-standalone`. e.g. `pgo+lto`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Debug => "debug",
+            Self::Noopt => "noopt",
+            Self::Lto => "lto",
+            Self::Pgo => "pgo",
+            Self::PgoLto => "pgo+lto",
+        })
+    }
 }
 
 /// Describes Apple SDK build/targeting.
@@ -120,6 +559,17 @@ pub trait PythonDistribution {
     /// Obtain the PEP 425 Python ABI tag. e.g. `cp38d`.
     fn python_abi_tag(&self) -> Option<&str>;
 
+    /// Whether a wheel's PEP 425 ABI tag can be loaded by this distribution.
+    ///
+    /// See `is_abi_compatible()`. Returns false if the distribution's ABI tag
+    /// isn't known and the wheel isn't ABI independent.
+    fn is_abi_compatible_with(&self, wheel_tag: &str) -> bool {
+        match self.python_abi_tag() {
+            Some(tag) => is_abi_compatible(tag, wheel_tag),
+            None => wheel_tag.split('.').any(|tag| tag == "none"),
+        }
+    }
+
     /// Obtain the Python platform tag.
     fn python_platform_tag(&self) -> &str;
 
@@ -261,338 +711,6000 @@ impl Drop for DistributionExtractLock {
     }
 }
 
-fn sha256_path(path: &Path) -> Vec<u8> {
-    let mut hasher = Sha256::new();
-    let fh = File::open(&path).unwrap();
-    let mut reader = std::io::BufReader::new(fh);
+/// Describes when directories managed by a `CleanupGuard` are removed.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CleanupPolicy {
+    /// Never remove directories.
+    KeepAll,
 
-    let mut buffer = [0; 32768];
+    /// Remove directories when the guard is dropped.
+    DeleteOnDrop,
 
-    loop {
-        let count = reader.read(&mut buffer).expect("error reading");
-        if count == 0 {
-            break;
+    /// Remove directories when `CleanupGuard::build_finished()` is called.
+    ///
+    /// Directories of a build that doesn't finish, e.g. due to an error, are
+    /// retained for inspection.
+    DeleteAfterBuild,
+}
+
+/// Removes extraction and temporary directories according to a `CleanupPolicy`.
+#[derive(Debug)]
+pub struct CleanupGuard {
+    policy: CleanupPolicy,
+    paths: Vec<PathBuf>,
+}
+
+impl CleanupGuard {
+    #[allow(unused)]
+    pub fn new(policy: CleanupPolicy) -> Self {
+        Self {
+            policy,
+            paths: vec![],
         }
-        hasher.update(&buffer[..count]);
     }
 
-    hasher.finalize().to_vec()
-}
+    /// Register a directory to be removed according to this guard's policy.
+    #[allow(unused)]
+    pub fn track(&mut self, path: impl AsRef<Path>) {
+        self.paths.push(path.as_ref().to_path_buf());
+    }
 
-/// Ensure a Python distribution at a URL is available in a local directory.
-///
-/// The path to the downloaded and validated file is returned.
-pub fn download_distribution(url: &str, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
-    let expected_hash = hex::decode(sha256)?;
-    let u = Url::parse(url)?;
+    /// Denote that the build using tracked directories has finished.
+    ///
+    /// Tracked directories are removed if the policy is
+    /// `CleanupPolicy::DeleteAfterBuild`.
+    #[allow(unused)]
+    pub fn build_finished(mut self) -> Result<()> {
+        if self.policy == CleanupPolicy::DeleteAfterBuild {
+            self.remove_paths()?;
+        }
 
-    let basename = u
-        .path_segments()
-        .expect("cannot be base path")
-        .last()
-        .unwrap()
-        .to_string();
+        Ok(())
+    }
 
-    let cache_path = cache_dir.join(basename);
+    fn remove_paths(&mut self) -> Result<()> {
+        for path in self.paths.drain(..) {
+            if path.exists() {
+                remove_dir_all::remove_dir_all(&path)
+                    .with_context(|| format!("removing {}", path.display()))?;
+            }
+        }
 
-    if cache_path.exists() {
-        let file_hash = sha256_path(&cache_path);
+        Ok(())
+    }
+}
 
-        // We don't care about timing side-channels from the string compare.
-        if file_hash == expected_hash {
-            return Ok(cache_path);
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        if self.policy == CleanupPolicy::DeleteOnDrop {
+            // Errors can't be propagated from drop. Leaving a directory behind
+            // is harmless.
+            self.remove_paths().ok();
         }
     }
+}
 
-    let mut data: Vec<u8> = Vec::new();
+/// User-Agent sent with HTTP requests for distributions.
+static USER_AGENT: Lazy<RwLock<String>> =
+    Lazy::new(|| RwLock::new(format!("pyoxidizer/{}", PYOXIDIZER_VERSION)));
+
+/// Set the User-Agent sent with HTTP requests for distributions.
+///
+/// Defaults to `pyoxidizer/<version>`.
+#[allow(unused)]
+pub fn set_user_agent(user_agent: &str) {
+    *USER_AGENT.write().unwrap() = user_agent.to_string();
+}
 
-    println!("downloading {}", u);
-    let client = get_http_client()?;
-    let mut response = client.get(u.as_str()).send()?;
-    response.read_to_end(&mut data)?;
+/// Obtain the User-Agent sent with HTTP requests for distributions.
+pub fn user_agent() -> String {
+    USER_AGENT.read().unwrap().clone()
+}
 
-    let mut hasher = Sha256::new();
-    hasher.update(&data);
+/// Proxy through which all HTTP requests for distributions are sent.
+static PROXY: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
 
-    let url_hash = hasher.finalize().to_vec();
-    if url_hash != expected_hash {
-        return Err(anyhow!("sha256 of Python distribution does not validate"));
+/// Set a proxy through which all HTTP requests for distributions are sent.
+///
+/// `url` can be an HTTP proxy (`http://host:port`) or a SOCKS5 proxy
+/// (`socks5://host:port` or `socks5h://host:port` to also resolve host names
+/// through the proxy). If not set, proxies defined by the `HTTP_PROXY`,
+/// `HTTPS_PROXY`, and `ALL_PROXY` environment variables are used. A proxy set
+/// with this function takes precedence over environment variables.
+#[allow(unused)]
+pub fn set_proxy(url: Option<&str>) -> Result<()> {
+    if let Some(url) = url {
+        reqwest::Proxy::all(url).with_context(|| format!("parsing proxy URL {}", url))?;
     }
 
-    let mut temp_cache_path = cache_path.clone();
-    temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
+    *PROXY.write().unwrap() = url.map(|x| x.to_string());
 
-    fs::write(&temp_cache_path, data).context("unable to write distribution file")?;
+    Ok(())
+}
 
-    fs::rename(&temp_cache_path, &cache_path)
-        .or_else(|e| -> Result<()> {
-            fs::remove_file(&temp_cache_path)
-                .context("unable to remove temporary distribution file")?;
+/// Hosts distributions may be downloaded from. Empty allows all hosts.
+static ALLOWED_HOSTS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(vec![]));
 
-            if cache_path.exists() {
-                download_distribution(url, sha256, cache_dir)?;
-                return Ok(());
-            }
+/// Restrict the hosts distributions may be downloaded from.
+///
+/// Requests for distribution URLs whose host isn't in `hosts` fail with
+/// `DistributionError::HostNotAllowed` before any request is made. Host names
+/// are compared case-insensitively. An empty list allows all hosts, which is
+/// the default.
+#[allow(unused)]
+pub fn set_allowed_hosts(hosts: &[&str]) {
+    *ALLOWED_HOSTS.write().unwrap() = normalize_allowed_hosts(hosts);
+}
 
-            Err(e.into())
-        })
-        .context("unable to rename downloaded distribution file")?;
+/// Normalize host names for comparison with distribution URL hosts.
+fn normalize_allowed_hosts(hosts: &[&str]) -> Vec<String> {
+    hosts.iter().map(|h| h.to_lowercase()).collect()
+}
 
-    Ok(cache_path)
+/// Parse the URL of a distribution, enforcing the host allowlist.
+fn parse_distribution_url(url: &str) -> Result<Url> {
+    parse_distribution_url_allowing(url, &ALLOWED_HOSTS.read().unwrap())
 }
 
-pub fn copy_local_distribution(path: &Path, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
-    let expected_hash = hex::decode(sha256)?;
-    let basename = path.file_name().unwrap().to_str().unwrap().to_string();
-    let cache_path = cache_dir.join(basename);
+/// Parse the URL of a distribution, enforcing a normalized host allowlist.
+///
+/// An empty `allowed` allows all hosts. See `set_allowed_hosts()`.
+fn parse_distribution_url_allowing(url: &str, allowed: &[String]) -> Result<Url> {
+    let u = Url::parse(url)?;
 
-    if cache_path.exists() {
-        let file_hash = sha256_path(&cache_path);
+    if !allowed.is_empty() {
+        let host = u.host_str().unwrap_or_default().to_lowercase();
 
-        if file_hash == expected_hash {
-            println!(
-                "existing {} passes SHA-256 integrity check",
-                cache_path.display()
-            );
-            return Ok(cache_path);
+        if !allowed.contains(&host) {
+            return Err(DistributionError::HostNotAllowed {
+                url: url.to_string(),
+                host,
+            }
+            .into());
         }
     }
 
-    let source_hash = sha256_path(path);
+    Ok(u)
+}
 
-    if source_hash != expected_hash {
-        return Err(anyhow!("sha256 of Python distribution does not validate"));
-    }
+/// Obtain the HTTP client used for all distribution requests.
+fn distribution_http_client() -> Result<reqwest::blocking::Client> {
+    let mut builder = get_http_client_builder().user_agent(user_agent());
 
-    println!("copying {}", path.display());
-    std::fs::copy(path, &cache_path)?;
+    if let Some(url) = PROXY.read().unwrap().as_ref() {
+        // An explicitly configured proxy replaces proxies defined by the
+        // environment.
+        builder = reqwest::blocking::ClientBuilder::new()
+            .user_agent(user_agent())
+            .proxy(reqwest::Proxy::all(url)?);
+    }
 
-    Ok(cache_path)
+    Ok(builder.build()?)
 }
 
-/// Obtain a local Path for a Python distribution tar archive.
+/// Base URLs of mirrors raced against distribution URLs before downloading.
+static DOWNLOAD_MIRRORS: Lazy<RwLock<Vec<Url>>> = Lazy::new(|| RwLock::new(vec![]));
+
+/// How long to wait for mirror probes before falling back to the primary URL.
+const MIRROR_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Configure mirrors to probe before downloading a distribution.
 ///
-/// Takes a parsed config and a cache directory as input. Usually the cache
-/// directory is the OUT_DIR for the invocation of a Cargo build script.
-/// A Python distribution will be fetched according to the configuration and a
-/// copy of the archive placed in ``cache_dir``. If the archive already exists
-/// in ``cache_dir``, it will be verified and returned.
+/// Mirrors are base URLs like `https://mirror.example.com/python`. A
+/// distribution's URL on a mirror is the mirror URL followed by the path of
+/// its primary URL. Before downloading, `HEAD` requests for the primary URL and
+/// every mirror are raced and the first to succeed is downloaded from. If none
+/// succeeds within `MIRROR_PROBE_TIMEOUT`, the primary URL is used. Mirrors
+/// are subject to `set_allowed_hosts()` and archive digests are verified
+/// regardless of where archives come from. An empty list, the default,
+/// disables probing.
 ///
-/// Local filesystem paths are preferred over remote URLs if both are defined.
-pub fn resolve_python_distribution_archive(
-    dist: &PythonDistributionLocation,
-    cache_dir: &Path,
-) -> Result<PathBuf> {
-    if !cache_dir.exists() {
-        create_dir_all(cache_dir).unwrap();
-    }
+/// If a download fails or its digest doesn't match, the remaining URLs are
+/// tried in turn: the primary URL, then mirrors in the order given. If all
+/// fail, `DistributionError::MirrorsExhausted` reports every attempt. Failures
+/// of the installed `DigestVerifier` other than a digest mismatch aren't
+/// retried.
+#[allow(unused)]
+pub fn set_download_mirrors(mirrors: &[&str]) -> Result<()> {
+    let mirrors = mirrors
+        .iter()
+        .map(|mirror| Url::parse(mirror).with_context(|| format!("parsing mirror {}", mirror)))
+        .collect::<Result<Vec<_>>>()?;
 
-    match dist {
-        PythonDistributionLocation::Local { local_path, sha256 } => {
-            let p = PathBuf::from(local_path);
-            copy_local_distribution(&p, sha256, cache_dir)
-        }
-        PythonDistributionLocation::Url { url, sha256 } => {
-            download_distribution(url, sha256, cache_dir)
-        }
-    }
+    *DOWNLOAD_MIRRORS.write().unwrap() = mirrors;
+
+    Ok(())
 }
 
-/// Resolve a Python distribution archive.
+/// Content of a file listing download mirrors.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MirrorsFile {
+    mirrors: Vec<String>,
+}
+
+/// Find the 1-based line of `content` containing `needle` as a quoted string.
+fn line_of(content: &str, needle: &str) -> Option<usize> {
+    let quoted = [format!("\"{}\"", needle), format!("'{}'", needle)];
+
+    content
+        .lines()
+        .position(|line| quoted.iter().any(|q| line.contains(q.as_str())))
+        .map(|i| i + 1)
+}
+
+/// Parse and validate the content of a mirrors file.
 ///
-/// Returns a tuple of (archive path, extract directory).
-pub fn resolve_python_distribution_from_location(
-    logger: &slog::Logger,
-    location: &PythonDistributionLocation,
-    distributions_dir: &Path,
-) -> Result<(PathBuf, PathBuf)> {
-    warn!(logger, "resolving Python distribution {:?}", location);
-    let path = resolve_python_distribution_archive(location, distributions_dir)?;
-    warn!(
-        logger,
-        "Python distribution available at {}",
-        path.display()
-    );
-
-    let distribution_hash = match location {
-        PythonDistributionLocation::Local { sha256, .. } => sha256,
-        PythonDistributionLocation::Url { sha256, .. } => sha256,
+/// `path` determines the format, JSON for a `.json` extension and TOML
+/// otherwise, and is named in error messages.
+fn parse_mirrors_file(path: &Path, content: &str) -> Result<Vec<Url>> {
+    let file: MirrorsFile = if path.extension().and_then(|x| x.to_str()) == Some("json") {
+        serde_json::from_str(content)
+            .map_err(|e| anyhow!("{}:{}: {}", path.display(), e.line(), e))?
+    } else {
+        toml::from_str(content).map_err(|e| match e.line_col() {
+            Some((line, _)) => anyhow!("{}:{}: {}", path.display(), line + 1, e),
+            None => anyhow!("{}: {}", path.display(), e),
+        })?
     };
 
-    let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
+    let mut mirrors: Vec<Url> = vec![];
 
-    Ok((path, distribution_path))
+    for mirror in &file.mirrors {
+        let location = match line_of(content, mirror) {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+
+        let url = Url::parse(mirror)
+            .map_err(|e| anyhow!("{}: invalid mirror {}: {}", location, mirror, e))?;
+
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(anyhow!(
+                "{}: mirror {} must be an http or https URL",
+                location,
+                mirror
+            ));
+        }
+        if url.host_str().is_none() {
+            return Err(anyhow!("{}: mirror {} has no host", location, mirror));
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(anyhow!(
+                "{}: mirror {} must not have a query or fragment",
+                location,
+                mirror
+            ));
+        }
+        if mirrors.contains(&url) {
+            return Err(anyhow!("{}: mirror {} is listed twice", location, mirror));
+        }
+
+        mirrors.push(url);
+    }
+
+    Ok(mirrors)
 }
 
-/// Describes the flavor of a distribution.
-#[derive(Debug, PartialEq)]
-pub enum DistributionFlavor {
-    /// Distributions coming from the `python-build-standalone` project.
-    Standalone,
+/// Configure download mirrors from a file.
+///
+/// The file is TOML, or JSON if its extension is `.json`, and has a
+/// `mirrors` key holding base URLs of mirrors in the order they are tried.
+/// e.g. `mirrors = ["https://mirror.example.com/python"]`. Mirrors behave as
+/// if passed to `set_download_mirrors()`. Every entry must be an `http` or
+/// `https` URL without query or fragment and may only be listed once. Errors
+/// name the file and line of the offending entry. If the file is invalid, the
+/// configured mirrors are left unchanged.
+#[allow(unused)]
+pub fn load_download_mirrors(path: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
 
-    /// Statically linked distributions coming from the `python-build-standalone` project.
-    StandaloneStatic,
+    *DOWNLOAD_MIRRORS.write().unwrap() = parse_mirrors_file(path, &content)?;
 
-    /// Dynamically linked distributions coming from the `python-build-standalone` project.
-    StandaloneDynamic,
+    Ok(())
 }
 
-impl Default for DistributionFlavor {
-    fn default() -> Self {
-        DistributionFlavor::Standalone
-    }
+/// Resolve the URL of a distribution on a mirror.
+fn mirror_url(mirror: &Url, primary: &Url) -> Url {
+    let mut url = mirror.clone();
+    url.set_path(&format!(
+        "{}{}",
+        mirror.path().trim_end_matches('/'),
+        primary.path()
+    ));
+    url.set_query(primary.query());
+
+    url
 }
 
-impl TryFrom<&str> for DistributionFlavor {
-    type Error = String;
+/// Choose the URL to download a distribution from by racing probes against mirrors.
+fn fastest_url(primary: &Url, mirrors: &[Url], timeout: Duration) -> Url {
+    if mirrors.is_empty() {
+        return primary.clone();
+    }
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value {
-            "standalone" => Ok(Self::Standalone),
-            "standalone_static" | "standalone-static" => Ok(Self::StandaloneStatic),
-            "standalone_dynamic" | "standalone-dynamic" => Ok(Self::StandaloneDynamic),
-            _ => Err(format!("distribution flavor {} not recognized", value)),
+    let client = match distribution_http_client() {
+        Ok(client) => client,
+        Err(_) => return primary.clone(),
+    };
+
+    let candidates = std::iter::once(primary.clone())
+        .chain(mirrors.iter().map(|mirror| mirror_url(mirror, primary)))
+        .filter(|url| parse_distribution_url(url.as_str()).is_ok())
+        .collect::<Vec<_>>();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    for (i, url) in candidates.iter().enumerate() {
+        let tx = tx.clone();
+        let client = client.clone();
+        let url = url.clone();
+
+        std::thread::spawn(move || {
+            let ok = client
+                .head(url.as_str())
+                .timeout(timeout)
+                .send()
+                .map(|response| response.status().is_success())
+                .unwrap_or(false);
+
+            tx.send((i, ok)).ok();
+        });
+    }
+
+    drop(tx);
+
+    let deadline = std::time::Instant::now() + timeout;
+    while let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) {
+        match rx.recv_timeout(remaining) {
+            Ok((i, true)) => return candidates[i].clone(),
+            Ok((_, false)) => {}
+            Err(_) => break,
         }
     }
+
+    primary.clone()
 }
 
-type DistributionCacheKey = (PathBuf, PythonDistributionLocation);
-type DistributionCacheValue = Arc<Mutex<Option<Arc<StandaloneDistribution>>>>;
+/// Resolve the URLs to attempt downloading a distribution from, in order.
+///
+/// The fastest URL as determined by `fastest_url()` comes first, followed by
+/// the primary URL and then mirrors.
+fn download_candidates(primary: &Url, mirrors: &[Url], timeout: Duration) -> Vec<Url> {
+    let mut candidates = vec![fastest_url(primary, mirrors, timeout)];
 
-/// Holds references to resolved PythonDistribution instances.
-#[derive(Debug)]
-pub struct DistributionCache {
-    cache: Mutex<HashMap<DistributionCacheKey, DistributionCacheValue>>,
-    default_dest_dir: Option<PathBuf>,
+    for url in std::iter::once(primary.clone())
+        .chain(mirrors.iter().map(|mirror| mirror_url(mirror, primary)))
+        .filter(|url| parse_distribution_url(url.as_str()).is_ok())
+    {
+        if !candidates.contains(&url) {
+            candidates.push(url);
+        }
+    }
+
+    candidates
 }
 
-impl DistributionCache {
-    pub fn new(default_dest_dir: Option<&Path>) -> Self {
+/// Bounds how many operations of a kind run at once.
+///
+/// This is a counting semaphore whose limit can be changed while permits are
+/// held. Lowering the limit doesn't affect held permits.
+struct ConcurrencyLimit {
+    /// The limit and the number of permits held.
+    state: Mutex<(usize, usize)>,
+    released: Condvar,
+}
+
+impl ConcurrencyLimit {
+    fn new(limit: usize) -> Self {
         Self {
-            cache: Mutex::new(HashMap::new()),
-            default_dest_dir: default_dest_dir.map(|x| x.to_path_buf()),
+            state: Mutex::new((limit, 0)),
+            released: Condvar::new(),
         }
     }
 
-    /// Resolve a `PythonDistribution` given its source and storage locations.
-    pub fn resolve_distribution(
-        &self,
-        logger: &slog::Logger,
-        location: &PythonDistributionLocation,
-        dest_dir: Option<&Path>,
-    ) -> Result<Arc<StandaloneDistribution>> {
-        let dest_dir = if let Some(p) = dest_dir {
-            p
-        } else if let Some(p) = &self.default_dest_dir {
-            p
-        } else {
-            return Err(anyhow!("no destination directory available"));
-        };
+    fn set_limit(&self, limit: usize) {
+        self.state.lock().unwrap().0 = limit;
+        self.released.notify_all();
+    }
 
-        let key = (dest_dir.to_path_buf(), location.clone());
+    /// Wait until fewer than the limit permits are held, then take one.
+    fn acquire(&self) -> ConcurrencyPermit<'_> {
+        let mut state = self.state.lock().unwrap();
+        while state.1 >= state.0 {
+            state = self.released.wait(state).unwrap();
+        }
+        state.1 += 1;
 
-        // This logic is whack. Surely there's a cleaner way to do this...
-        //
-        // The general problem is instances of this type are Send + Sync. And
-        // we do rely on multiple threads simultaneously accessing it. This
-        // occurs in tests for example, which use a global/static instance to
-        // cache resolved distributions to drastically reduce CPU overhead.
-        //
-        // We need a Mutex of some kind around the HashMap to allow
-        // multi-threaded access. But if that was the only Mutex that existed, we'd
-        // need to hold the Mutex while any thread was resolving a distribution
-        // and this would prevent multi-threaded distribution resolving.
-        //
-        // Or we could release that Mutex after a missing lookup and then each
-        // thread would race to resolve the distribution and insert. That's fine,
-        // but it results in redundancy and wasted CPU (several minutes worth for
-        // debug builds in the test harness).
-        //
-        // What we do instead is have HashMap values be Arc<Mutex<Option<T>>>.
-        // We then perform a 2 phase lookup.
-        //
-        // In the 1st lock, we lock the entire HashMap and do the key lookup.
-        // If it exists, we clone the Arc<T>. Else if it is missing, we insert
-        // a new key with `None` and return a clone of its Arc<T>. Either way,
-        // we have a handle on the Arc<Mutex<Option<T>>> in a populated. We then
-        // release the outer HashMap lock.
-        //
-        // We then lock the inner entry. With that lock hold, we return a clone
-        // of its `Some(T)` entry immediately or proceed to populate it. Only 1
-        // thread can hold this lock, ensuring only 1 thread performs the
-        // value resolution. Multiple threads can resolve different keys in
-        // parallel. By other threads will be blocked resolving a single key.
+        ConcurrencyPermit { limit: self }
+    }
+}
 
-        let entry = {
-            let mut lock = self
-                .cache
-                .lock()
-                .map_err(|e| anyhow!("cannot obtain distribution cache lock: {}", e))?;
+/// A permit to run an operation bounded by a `ConcurrencyLimit`. Released on drop.
+pub(crate) struct ConcurrencyPermit<'a> {
+    limit: &'a ConcurrencyLimit,
+}
 
-            if let Some(value) = lock.get(&key) {
-                value.clone()
-            } else {
-                let value = Arc::new(Mutex::new(None));
-                lock.insert(key.clone(), value.clone());
+impl<'a> Drop for ConcurrencyPermit<'a> {
+    fn drop(&mut self) {
+        self.limit.state.lock().unwrap().1 -= 1;
+        self.limit.released.notify_all();
+    }
+}
 
-                value
-            }
-        };
+/// Default maximum number of distributions downloaded at once.
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 4;
 
-        let mut lock = entry
-            .lock()
-            .map_err(|e| anyhow!("cannot obtain distribution lock: {}", e))?;
+static DOWNLOAD_CONCURRENCY: Lazy<ConcurrencyLimit> =
+    Lazy::new(|| ConcurrencyLimit::new(DEFAULT_MAX_CONCURRENT_DOWNLOADS));
 
-        let value = lock.deref_mut();
+/// Extraction is mostly decompression, so by default one per CPU runs at once.
+static EXTRACTION_CONCURRENCY: Lazy<ConcurrencyLimit> =
+    Lazy::new(|| ConcurrencyLimit::new(num_cpus::get().max(1)));
 
-        if let Some(dist) = value {
-            Ok(dist.clone())
-        } else {
-            let dist = Arc::new(StandaloneDistribution::from_location(
-                logger, location, dest_dir,
-            )?);
+/// Set the maximum number of distribution downloads and extractions running at once.
+///
+/// The limit applies separately to downloads and to extractions, across all
+/// threads of the process. Operations exceeding it wait for others to finish.
+/// By default, `DEFAULT_MAX_CONCURRENT_DOWNLOADS` downloads and one extraction
+/// per CPU run at once.
+///
+/// `n` must be at least `1`.
+#[allow(unused)]
+pub fn set_max_concurrency(n: usize) -> Result<()> {
+    if n == 0 {
+        return Err(anyhow!("maximum concurrency must be at least 1"));
+    }
 
-            lock.replace(dist.clone());
+    DOWNLOAD_CONCURRENCY.set_limit(n);
+    EXTRACTION_CONCURRENCY.set_limit(n);
 
-            Ok(dist)
+    Ok(())
+}
+
+/// Wait for permission to extract a distribution, per `set_max_concurrency()`.
+pub(crate) fn acquire_extraction_permit() -> ConcurrencyPermit<'static> {
+    EXTRACTION_CONCURRENCY.acquire()
+}
+
+/// Validate an archive member path, returning it without leading `.` components.
+///
+/// Archives created like `tar -C dir -cf x.tar .` have members like
+/// `./python/PYTHON.json`. Members with any other component that isn't a
+/// normal name, like `..` or a root, are rejected.
+fn archive_member_path(path: &Path) -> Result<PathBuf> {
+    let mut result = PathBuf::new();
+
+    for component in path
+        .components()
+        .skip_while(|c| *c == std::path::Component::CurDir)
+    {
+        match component {
+            std::path::Component::Normal(c) => result.push(c),
+            _ => return Err(anyhow!("archive member {} has unsafe path", path.display())),
         }
     }
+
+    Ok(result)
 }
 
-/// Obtain a `PythonDistribution` implementation of a flavor and from a location.
+/// Verify no parent directory of `path` below `root` is a symlink.
 ///
-/// The distribution will be written to `dest_dir`.
+/// Writing to `root.join(path)` would otherwise follow the symlink, possibly
+/// to outside of `root`. `path` is an archive member path relative to `root`.
+fn ensure_no_symlinked_parents(root: &Path, path: &Path) -> Result<()> {
+    let mut current = root.to_path_buf();
+
+    for component in path
+        .parent()
+        .into_iter()
+        .flat_map(|parent| parent.components())
+    {
+        current.push(component);
+
+        if matches!(current.symlink_metadata(), Ok(metadata) if metadata.file_type().is_symlink()) {
+            return Err(anyhow!(
+                "archive member path {} passes through symlink {}",
+                path.display(),
+                current.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// A handle for cancelling distribution resolution from another thread.
+///
+/// Clones share state, so a clone can be handed to e.g. a signal handler
+/// while the original is passed to `resolve_distribution_cancelable()`.
 #[allow(unused)]
-pub fn resolve_distribution(
-    logger: &slog::Logger,
-    location: &PythonDistributionLocation,
-    dest_dir: &Path,
-) -> Result<Box<dyn PythonDistribution>> {
-    Ok(Box::new(StandaloneDistribution::from_location(
-        logger, location, dest_dir,
-    )?) as Box<dyn PythonDistribution>)
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+#[allow(unused)]
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of operations using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
 }
 
-/// Resolve the location of the default Python distribution of a given flavor and build target.
-pub fn default_distribution_location(
-    flavor: &DistributionFlavor,
-    target: &str,
-    python_major_minor_version: Option<&str>,
-) -> Result<PythonDistributionLocation> {
-    let dist = PYTHON_DISTRIBUTIONS
-        .find_distribution(target, flavor, python_major_minor_version)
-        .ok_or_else(|| anyhow!("could not find default Python distribution for {}", target))?;
+thread_local! {
+    /// The token of the cancelable operation running on this thread, if any.
+    // `const` initializers need a newer Rust than we support.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static ACTIVE_CANCELLATION: RefCell<Option<CancellationToken>> = RefCell::new(None);
+}
 
-    Ok(dist.location)
+/// Restores the previously active `CancellationToken` when dropped.
+struct ActiveCancellationGuard(Option<CancellationToken>);
+
+impl Drop for ActiveCancellationGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        ACTIVE_CANCELLATION.with(|active| *active.borrow_mut() = previous);
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use {super::*, crate::testutil::*};
+/// Run `f` such that downloads and extractions it performs on this thread stop once `token` is cancelled.
+///
+/// Downloads are held in memory until complete and extractions are renamed
+/// into place once complete, so cancellation leaves neither a partial archive
+/// in the cache nor a partial extraction behind. If `f` fails after `token`
+/// is cancelled, `DistributionError::Cancelled` is returned instead of its
+/// error.
+#[allow(unused)]
+pub fn with_cancellation<T>(token: &CancellationToken, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let _guard = ActiveCancellationGuard(
+        ACTIVE_CANCELLATION.with(|active| active.replace(Some(token.clone()))),
+    );
 
-    #[test]
-    fn test_all_standalone_distributions() -> Result<()> {
-        assert!(!get_all_standalone_distributions()?.is_empty());
+    match f() {
+        Err(_) if token.is_cancelled() => Err(DistributionError::Cancelled.into()),
+        result => result,
+    }
+}
+
+/// Fail if the operation running on this thread was cancelled. See `with_cancellation()`.
+pub(crate) fn check_cancelled() -> Result<(), DistributionError> {
+    let cancelled = ACTIVE_CANCELLATION.with(|active| {
+        active
+            .borrow()
+            .as_ref()
+            .map(|token| token.is_cancelled())
+            .unwrap_or(false)
+    });
+
+    if cancelled {
+        Err(DistributionError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// A `Read` adapter failing reads once the operation running on this thread is cancelled.
+pub(crate) struct CancelableReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> CancelableReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R: Read> Read for CancelableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if check_cancelled().is_err() {
+            // The error is replaced by `with_cancellation()`, so its content
+            // doesn't matter.
+            return Err(std::io::ErrorKind::Other.into());
+        }
+
+        self.inner.read(buf)
+    }
+}
+
+/// Whether cached downloads are revalidated against their origin.
+static REVALIDATE_CACHED_DOWNLOADS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether cached downloads are revalidated against their origin.
+///
+/// When enabled, using a previously downloaded distribution issues a
+/// conditional `HEAD` request with the `ETag` recorded at download time. The
+/// cached archive is used if the server confirms it is unchanged. An error is
+/// raised if the server reports different content under the same URL. Cached
+/// archives are always verified against their SHA-256 regardless. Disabled
+/// by default to avoid a request per distribution use.
+#[allow(unused)]
+pub fn set_revalidate_cached_downloads(enabled: bool) {
+    REVALIDATE_CACHED_DOWNLOADS.store(enabled, Ordering::SeqCst);
+}
+
+/// Path of the file holding the `ETag` of a downloaded distribution.
+fn etag_path(cache_path: &Path) -> PathBuf {
+    let mut name = cache_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".etag");
+
+    cache_path.with_file_name(name)
+}
+
+/// Confirm a cached download matches the content currently served at its URL.
+///
+/// Does nothing if no `ETag` was recorded for the download.
+fn revalidate_cached_download(url: &Url, cache_path: &Path) -> Result<()> {
+    let etag = match fs::read_to_string(etag_path(cache_path)) {
+        Ok(etag) => etag.trim().to_string(),
+        Err(_) => return Ok(()),
+    };
+
+    let response = distribution_http_client()?
+        .head(url.as_str())
+        .header(reqwest::header::IF_NONE_MATCH, &etag)
+        .send()?;
+
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(());
+    }
+
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "HTTP {} revalidating cached distribution {}",
+            response.status(),
+            url
+        ));
+    }
+
+    // Servers may ignore the conditional and respond normally.
+    match response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(current) if current != etag => Err(anyhow!(
+            "{} changed upstream since it was downloaded (ETag {} is now {})",
+            url,
+            etag,
+            current
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Default size in bytes of reads when hashing distribution archives.
+pub const DEFAULT_HASH_CHUNK_SIZE: usize = 32768;
+
+/// Size in bytes of reads when hashing distribution archives.
+static HASH_CHUNK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_HASH_CHUNK_SIZE);
+
+/// Set the size in bytes of reads when hashing distribution archives.
+///
+/// Larger reads can speed up verifying large archives on fast storage at the
+/// cost of memory. Defaults to `DEFAULT_HASH_CHUNK_SIZE`. Errors if `size` is 0.
+#[allow(unused)]
+pub fn set_hash_chunk_size(size: usize) -> Result<()> {
+    if size == 0 {
+        return Err(anyhow!("hash chunk size must be at least 1"));
+    }
+
+    HASH_CHUNK_SIZE.store(size, Ordering::SeqCst);
+
+    Ok(())
+}
+
+/// Read a stream to its end in chunks of a given size, feeding data to a function.
+fn read_chunked(
+    reader: &mut impl Read,
+    chunk_size: usize,
+    mut f: impl FnMut(&[u8]),
+) -> std::io::Result<()> {
+    let mut buffer = vec![0; chunk_size];
+
+    loop {
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            return Ok(());
+        }
+        f(&buffer[..count]);
+    }
+}
+
+fn sha256_path(path: &Path) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    let mut fh = File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+
+    read_chunked(&mut fh, HASH_CHUNK_SIZE.load(Ordering::SeqCst), |data| {
+        hasher.update(data)
+    })
+    .with_context(|| format!("reading {}", path.display()))?;
+
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Errors that can occur when selecting a Python distribution.
+#[derive(Debug)]
+pub enum DistributionError {
+    /// No known distribution satisfies the requested criteria.
+    NotFound {
+        target_triple: String,
+        flavor: String,
+        python_version: String,
+    },
+
+    /// The requested Python version or version specifier is malformed.
+    #[allow(unused)]
+    InvalidVersion { version: String, message: String },
+
+    /// A distribution was selected but its Python version is end-of-life.
+    EndOfLife { version: String, eol_date: String },
+
+    /// A distribution URL's host isn't in the allowlist set by `set_allowed_hosts()`.
+    HostNotAllowed { url: String, host: String },
+
+    /// A distribution was selected but its archive isn't in the cache.
+    NotCached {
+        filename: String,
+        cache_dir: PathBuf,
+    },
+
+    /// The SHA-256 of a distribution archive doesn't match the expected digest.
+    DigestMismatch { name: String },
+
+    /// A distribution archive matches its record's digest but not the integrity map.
+    ///
+    /// See `set_integrity_map()`. Digests are hex SHA-256.
+    IntegrityMismatch {
+        name: String,
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// A distribution archive was rejected by the installed `DigestVerifier`.
+    #[allow(unused)]
+    AttestationFailed { name: String, reason: String },
+
+    /// A distribution archive could not be downloaded.
+    Network { url: String, message: String },
+
+    /// A container registry or its token service refused access to a distribution.
+    ///
+    /// See `set_credential_provider()`.
+    Unauthorized { url: String, message: String },
+
+    /// No distribution satisfying a request fits `SelectionPolicy::max_footprint`.
+    ///
+    /// `smallest` is the smallest known archive size of distributions that
+    /// would otherwise have been selected.
+    BudgetExceeded { budget: u64, smallest: u64 },
+
+    /// Every configured mirror failed to provide a valid distribution archive.
+    ///
+    /// `attempts` holds each URL tried and why it failed.
+    MirrorsExhausted {
+        name: String,
+        attempts: Vec<(String, String)>,
+    },
+
+    /// The operation was cancelled through a `CancellationToken`.
+    Cancelled,
+
+    /// A distribution archive uses a compression this build can't decompress.
+    ///
+    /// `detected` names the compression. e.g. `gzip`.
+    UnsupportedCompression { detected: String },
+
+    /// A distribution archive's provenance attestation was rejected by a `ProvenanceVerifier`.
+    ProvenanceFailed { name: String, reason: String },
+
+    /// Distributions of a host's Python version exist, but none has its ABI.
+    ///
+    /// `distribution_abi` is the ABI of the distribution that would otherwise
+    /// have been selected.
+    AbiMismatch {
+        host_abi: String,
+        python_version: String,
+        distribution_abi: String,
+    },
+}
+
+impl DistributionError {
+    /// A stable machine-readable identifier of the kind of error. e.g. `CHECKSUM`.
+    ///
+    /// Codes are upper case and never change meaning, so automation can branch
+    /// on them. e.g. retrying on `NETWORK`. The `Display` message is for humans
+    /// and may change.
+    #[allow(unused)]
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "NOT_FOUND",
+            Self::InvalidVersion { .. } => "INVALID_VERSION",
+            Self::EndOfLife { .. } => "END_OF_LIFE",
+            Self::HostNotAllowed { .. } => "HOST_NOT_ALLOWED",
+            Self::NotCached { .. } => "NOT_CACHED",
+            Self::DigestMismatch { .. } => "CHECKSUM",
+            Self::IntegrityMismatch { .. } => "INTEGRITY",
+            Self::AttestationFailed { .. } => "ATTESTATION",
+            Self::Network { .. } => "NETWORK",
+            Self::Unauthorized { .. } => "UNAUTHORIZED",
+            Self::BudgetExceeded { .. } => "BUDGET_EXCEEDED",
+            Self::MirrorsExhausted { .. } => "MIRRORS_EXHAUSTED",
+            Self::Cancelled => "CANCELLED",
+            Self::AbiMismatch { .. } => "ABI_MISMATCH",
+            Self::ProvenanceFailed { .. } => "PROVENANCE",
+            Self::UnsupportedCompression { .. } => "UNSUPPORTED_COMPRESSION",
+        }
+    }
+
+    /// Obtain the code of the `DistributionError` causing an error, if any.
+    ///
+    /// The error's chain of causes is searched.
+    #[allow(unused)]
+    pub fn code_of(error: &anyhow::Error) -> Option<&'static str> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Self>())
+            .map(|e| e.code())
+    }
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound {
+                target_triple,
+                flavor,
+                python_version,
+            } => write!(
+                f,
+                "could not find Python distribution for {} matching flavor {} and version {}",
+                target_triple, flavor, python_version
+            ),
+            Self::InvalidVersion { message, .. } => f.write_str(message),
+            Self::EndOfLife { version, eol_date } => write!(
+                f,
+                "Python {} reached end-of-life on {} and end-of-life versions are rejected",
+                version, eol_date
+            ),
+            Self::HostNotAllowed { url, host } => write!(
+                f,
+                "host {} of {} is not an allowed distribution download host",
+                host, url
+            ),
+            Self::NotCached {
+                filename,
+                cache_dir,
+            } => write!(
+                f,
+                "Python distribution {} is not present in cache directory {}",
+                filename,
+                cache_dir.display()
+            ),
+            Self::DigestMismatch { name } => write!(
+                f,
+                "sha256 of Python distribution {} does not validate",
+                name
+            ),
+            Self::IntegrityMismatch {
+                name,
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "sha256 {} of Python distribution {} matches its record but not the integrity map, which expects {} for {}",
+                actual, name, expected, url
+            ),
+            Self::AttestationFailed { name, reason } => write!(
+                f,
+                "attestation of Python distribution {} failed: {}",
+                name, reason
+            ),
+            Self::Network { url, message } => write!(f, "error downloading {}: {}", url, message),
+            Self::Unauthorized { url, message } => {
+                write!(f, "not authorized to access {}: {}", url, message)
+            }
+            Self::BudgetExceeded { budget, smallest } => write!(
+                f,
+                "no Python distribution fits the size budget of {} bytes; the smallest is {} bytes",
+                budget, smallest
+            ),
+            Self::MirrorsExhausted { name, attempts } => {
+                write!(f, "no mirror provided a valid Python distribution {}", name)?;
+                for (url, result) in attempts {
+                    write!(f, "\n  {}: {}", url, result)?;
+                }
+
+                Ok(())
+            }
+            Self::Cancelled => f.write_str("resolving Python distribution was cancelled"),
+            Self::UnsupportedCompression { detected } => write!(
+                f,
+                "Python distribution archive is {} compressed, which this build of PyOxidizer cannot decompress; supported compressions are {}",
+                detected,
+                SUPPORTED_COMPRESSIONS.join(" and ")
+            ),
+            Self::ProvenanceFailed { name, reason } => write!(
+                f,
+                "provenance of Python distribution {} could not be verified: {}",
+                name, reason
+            ),
+            Self::AbiMismatch {
+                host_abi,
+                python_version,
+                distribution_abi,
+            } => write!(
+                f,
+                "no Python {} distribution has host ABI {}; the distribution that would be used has ABI {}",
+                python_version, host_abi, distribution_abi
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DistributionError {}
+
+/// Verifies that a distribution archive is the expected one.
+///
+/// The installed verifier is consulted whenever an archive is obtained or
+/// reused from a cache. See `set_digest_verifier()`.
+pub trait DigestVerifier: Send + Sync {
+    /// Verify an archive given its expected hex SHA-256 and its actual SHA-256.
+    ///
+    /// `name` identifies the archive, typically by file name.
+    fn verify(
+        &self,
+        name: &str,
+        expected_sha256: &str,
+        actual_sha256: &[u8],
+    ) -> Result<(), DistributionError>;
+}
+
+/// The default `DigestVerifier`, which checks the actual digest matches the expected one.
+pub struct Sha256Verifier;
+
+impl DigestVerifier for Sha256Verifier {
+    fn verify(
+        &self,
+        name: &str,
+        expected_sha256: &str,
+        actual_sha256: &[u8],
+    ) -> Result<(), DistributionError> {
+        if hex::encode(actual_sha256) == expected_sha256.to_lowercase() {
+            Ok(())
+        } else {
+            Err(DistributionError::DigestMismatch {
+                name: name.to_string(),
+            })
+        }
+    }
+}
+
+static DIGEST_VERIFIER: Lazy<RwLock<Arc<dyn DigestVerifier>>> =
+    Lazy::new(|| RwLock::new(Arc::new(Sha256Verifier)));
+
+/// Install the `DigestVerifier` consulted for distribution archives.
+///
+/// This replaces `Sha256Verifier`. Stricter verifiers, e.g. ones consulting a
+/// transparency log, should usually call `Sha256Verifier` first and report their
+/// own failures as `DistributionError::AttestationFailed`.
+#[allow(unused)]
+pub fn set_digest_verifier(verifier: Arc<dyn DigestVerifier>) {
+    *DIGEST_VERIFIER.write().unwrap() = verifier;
+}
+
+/// Verify a distribution archive with the installed `DigestVerifier`.
+fn verify_digest(name: &str, expected_sha256: &str, actual_sha256: &[u8]) -> Result<()> {
+    let verifier = DIGEST_VERIFIER.read().unwrap().clone();
+
+    Ok(verifier.verify(name, expected_sha256, actual_sha256)?)
+}
+
+/// Digests distribution archives must have, keyed by distribution URL.
+static INTEGRITY_MAP: Lazy<RwLock<HashMap<String, String>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Normalize a hex SHA-256 from an integrity map.
+fn normalize_integrity_digest(url: &str, sha256: &str) -> Result<String> {
+    if sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(sha256.to_lowercase())
+    } else {
+        Err(anyhow!("invalid sha256 {} for {}", sha256, url))
+    }
+}
+
+/// Pin distribution archives to digests maintained outside of their records.
+///
+/// `map` maps distribution URLs to hex SHA-256 digests. Archives of
+/// distributions whose URL is in the map must match both their record's
+/// digest and the one in the map, whether they are downloaded, obtained from
+/// a mirror, or reused from the download cache. A mismatch with the record is
+/// reported as `DistributionError::DigestMismatch` and one with the map as
+/// `DistributionError::IntegrityMismatch`. URLs are compared exactly. OCI
+/// distributions are looked up by the URL of their archive blob, like
+/// `https://<registry>/v2/<repository>/blobs/sha256:<digest>`. Local
+/// distributions aren't affected. An empty map, the default, disables the
+/// check.
+#[allow(unused)]
+pub fn set_integrity_map(map: &HashMap<String, String>) -> Result<()> {
+    let map = map
+        .iter()
+        .map(|(url, sha256)| Ok((url.clone(), normalize_integrity_digest(url, sha256)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    *INTEGRITY_MAP.write().unwrap() = map;
+
+    Ok(())
+}
+
+/// Pin distribution archives to digests from a JSON file.
+///
+/// The file holds an object mapping distribution URLs to hex SHA-256
+/// digests, e.g. `{"https://example.com/dist.tar.zst": "<sha256>"}`. See
+/// `set_integrity_map()`. Errors name the file and line of the offending
+/// entry. If the file is invalid, the installed map is left unchanged.
+#[allow(unused)]
+pub fn load_integrity_map(path: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+    let map: HashMap<String, String> = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("{}:{}: {}", path.display(), e.line(), e))?;
+
+    for (url, sha256) in &map {
+        if let Err(e) = normalize_integrity_digest(url, sha256) {
+            return Err(match line_of(&content, url) {
+                Some(line) => anyhow!("{}:{}: {}", path.display(), line, e),
+                None => anyhow!("{}: {}", path.display(), e),
+            });
+        }
+    }
+
+    set_integrity_map(&map)
+}
+
+/// Verify a distribution archive against the integrity map, if it has an entry for `url`.
+fn verify_integrity(url: &str, name: &str, actual_sha256: &[u8]) -> Result<()> {
+    if let Some(expected) = INTEGRITY_MAP.read().unwrap().get(url) {
+        let actual = hex::encode(actual_sha256);
+
+        if &actual != expected {
+            return Err(DistributionError::IntegrityMismatch {
+                name: name.to_string(),
+                url: url.to_string(),
+                expected: expected.clone(),
+                actual,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies a provenance attestation ties a distribution archive to its build.
+///
+/// See `verify_provenance()`.
+pub trait ProvenanceVerifier: Send + Sync {
+    /// Verify an attestation bundle for an archive given the archive's actual SHA-256.
+    ///
+    /// `name` identifies the archive, typically by file name. Failures should
+    /// be reported as `DistributionError::ProvenanceFailed`.
+    fn verify(
+        &self,
+        name: &str,
+        attestation: &[u8],
+        actual_sha256: &[u8],
+    ) -> Result<(), DistributionError>;
+}
+
+/// A `ProvenanceVerifier` checking SLSA provenance in in-toto statements against a policy.
+///
+/// The attestation may be an in-toto statement, a DSSE envelope holding
+/// one, or a Sigstore bundle's `dsseEnvelope`. Its subject must include the
+/// archive's digest. SLSA v0.2 and v1 provenance predicates are understood.
+///
+/// DSSE signatures are not checked. Verifiers needing signature verification
+/// should check signatures, then delegate to this.
+#[allow(unused)]
+#[derive(Clone, Debug, Default)]
+pub struct SlsaProvenanceVerifier {
+    /// Builder IDs to accept. e.g. a GitHub Actions workflow URI. Empty accepts any.
+    pub builder_ids: Vec<String>,
+
+    /// Source repositories to accept. e.g. `https://github.com/indygreg/python-build-standalone`.
+    ///
+    /// Compared ignoring `git+` prefixes, `@` revisions, and `.git` suffixes.
+    /// Empty accepts any.
+    pub source_repos: Vec<String>,
+}
+
+/// Obtain the in-toto statement from an attestation bundle.
+fn provenance_statement(attestation: &[u8]) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value = serde_json::from_slice(attestation)
+        .map_err(|e| format!("attestation is not valid JSON: {}", e))?;
+
+    let envelope = if value.get("dsseEnvelope").is_some() {
+        &value["dsseEnvelope"]
+    } else {
+        &value
+    };
+
+    match envelope.get("payload").and_then(|payload| payload.as_str()) {
+        Some(payload) => {
+            if envelope["payloadType"] != "application/vnd.in-toto+json" {
+                return Err(format!(
+                    "unsupported DSSE payload type {}",
+                    envelope["payloadType"]
+                ));
+            }
+
+            let payload = base64::decode(payload)
+                .map_err(|e| format!("DSSE payload is not valid base64: {}", e))?;
+
+            serde_json::from_slice(&payload)
+                .map_err(|e| format!("DSSE payload is not valid JSON: {}", e))
+        }
+        None => Ok(value),
+    }
+}
+
+/// Normalize a source repository URI for comparison.
+fn normalize_source_repo(uri: &str) -> &str {
+    let uri = uri.strip_prefix("git+").unwrap_or(uri);
+
+    // A revision follows an `@` in the path. An `@` in the authority is a user.
+    let path_start = uri
+        .find("://")
+        .and_then(|i| uri[i + 3..].find('/').map(|j| i + 3 + j))
+        .unwrap_or(0);
+    let uri = match uri[path_start..].find('@') {
+        Some(i) => &uri[..path_start + i],
+        None => uri,
+    };
+    let uri = uri.trim_end_matches('/');
+
+    uri.strip_suffix(".git").unwrap_or(uri)
+}
+
+impl SlsaProvenanceVerifier {
+    fn check(&self, statement: &serde_json::Value, actual_sha256: &[u8]) -> Result<(), String> {
+        let actual = hex::encode(actual_sha256);
+
+        let subject_matches = statement["subject"]
+            .as_array()
+            .map(|subjects| {
+                subjects.iter().any(|subject| {
+                    subject["digest"]["sha256"]
+                        .as_str()
+                        .map(|digest| digest.to_lowercase() == actual)
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false);
+        if !subject_matches {
+            return Err(format!("no attestation subject has SHA-256 {}", actual));
+        }
+
+        let predicate = &statement["predicate"];
+
+        if !self.builder_ids.is_empty() {
+            let builder_id = predicate["builder"]["id"]
+                .as_str()
+                .or_else(|| predicate["runDetails"]["builder"]["id"].as_str())
+                .ok_or_else(|| "provenance does not identify its builder".to_string())?;
+
+            if !self.builder_ids.iter().any(|id| id == builder_id) {
+                return Err(format!("builder {} is not trusted", builder_id));
+            }
+        }
+
+        if !self.source_repos.is_empty() {
+            let uris = [
+                &predicate["invocation"]["configSource"]["uri"],
+                &predicate["buildDefinition"]["externalParameters"]["workflow"]["repository"],
+            ]
+            .iter()
+            .filter_map(|v| v.as_str())
+            .chain(
+                [
+                    &predicate["materials"],
+                    &predicate["buildDefinition"]["resolvedDependencies"],
+                ]
+                .iter()
+                .filter_map(|v| v.as_array())
+                .flatten()
+                .filter_map(|material| material["uri"].as_str()),
+            )
+            .collect::<Vec<_>>();
+
+            let trusted = uris.iter().any(|uri| {
+                self.source_repos
+                    .iter()
+                    .any(|repo| normalize_source_repo(repo) == normalize_source_repo(uri))
+            });
+
+            if !trusted {
+                return Err(if uris.is_empty() {
+                    "provenance does not identify its source repository".to_string()
+                } else {
+                    format!("source {} is not trusted", uris.join(", "))
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ProvenanceVerifier for SlsaProvenanceVerifier {
+    fn verify(
+        &self,
+        name: &str,
+        attestation: &[u8],
+        actual_sha256: &[u8],
+    ) -> Result<(), DistributionError> {
+        provenance_statement(attestation)
+            .and_then(|statement| self.check(&statement, actual_sha256))
+            .map_err(|reason| DistributionError::ProvenanceFailed {
+                name: name.to_string(),
+                reason,
+            })
+    }
+}
+
+/// Verify a provenance attestation for a downloaded distribution archive.
+///
+/// The archive is hashed and `verifier` decides whether `attestation`
+/// vouches for it. e.g. with `SlsaProvenanceVerifier`. This complements the
+/// `DigestVerifier`, which only confirms an archive is the expected one.
+#[allow(unused)]
+pub fn verify_provenance(
+    archive_path: &Path,
+    attestation: &[u8],
+    verifier: &dyn ProvenanceVerifier,
+) -> Result<()> {
+    let name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| archive_path.display().to_string());
+
+    let actual = sha256_path(archive_path)?;
+
+    Ok(verifier.verify(&name, attestation, &actual)?)
+}
+
+/// Resolve the path to a verified copy of a distribution archive in a cache directory.
+///
+/// Returns `None` if the archive isn't present or its content doesn't match the
+/// expected digest. No network or source file access is performed.
+pub fn cached_distribution_path(
+    location: &PythonDistributionLocation,
+    cache_dir: &Path,
+) -> Option<PathBuf> {
+    let path = cache_dir.join(location.filename().as_ref());
+    let expected_hash = hex::decode(location.sha256()).ok()?;
+
+    if path.is_file() && matches!(sha256_path(&path), Ok(hash) if hash == expected_hash) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Ensure a Python distribution at a URL is available in a local directory.
+///
+/// The path to the downloaded and validated file is returned. Mirrors that
+/// are skipped are logged to `logger`.
+pub fn download_distribution(
+    logger: &slog::Logger,
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+) -> Result<PathBuf> {
+    let u = parse_distribution_url(url)?;
+
+    let basename = u
+        .path_segments()
+        .expect("cannot be base path")
+        .last()
+        .unwrap()
+        .to_string();
+
+    download_distribution_as(logger, url, sha256, cache_dir, &basename)
+}
+
+/// Ensure a Python distribution at a URL is available in a local directory under a given name.
+fn download_distribution_as(
+    logger: &slog::Logger,
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+    basename: &str,
+) -> Result<PathBuf> {
+    let mirrors = DOWNLOAD_MIRRORS.read().unwrap().clone();
+
+    download_distribution_from(logger, url, sha256, cache_dir, basename, &mirrors)
+}
+
+/// Download the content of a distribution URL, returning it and its ETag.
+///
+/// `authorization` is sent as the `Authorization` header if given.
+fn fetch_distribution(
+    source: &Url,
+    authorization: Option<&str>,
+) -> Result<(Vec<u8>, Option<String>)> {
+    let _permit = DOWNLOAD_CONCURRENCY.acquire();
+
+    let network = |message: String| DistributionError::Network {
+        url: source.to_string(),
+        message,
+    };
+
+    println!("downloading {}", source);
+    let client = distribution_http_client()?;
+    let mut request = client.get(source.as_str());
+    if let Some(value) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, value);
+    }
+    let mut response = request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| network(e.to_string()))?;
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+
+    let mut data: Vec<u8> = Vec::new();
+    CancelableReader::new(&mut response)
+        .read_to_end(&mut data)
+        .map_err(|e| network(e.to_string()))?;
+
+    Ok((data, etag))
+}
+
+/// Ensure a Python distribution is available in a local directory, trying mirrors.
+fn download_distribution_from(
+    logger: &slog::Logger,
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+    basename: &str,
+    mirrors: &[Url],
+) -> Result<PathBuf> {
+    download_distribution_with(logger, url, sha256, cache_dir, basename, mirrors, None)
+}
+
+/// Ensure a Python distribution is available in a local directory, authorizing its download.
+///
+/// `authorization` is sent as the `Authorization` header when downloading
+/// from `url`, but never to mirrors.
+fn download_distribution_with(
+    logger: &slog::Logger,
+    url: &str,
+    sha256: &str,
+    cache_dir: &Path,
+    basename: &str,
+    mirrors: &[Url],
+    authorization: Option<&str>,
+) -> Result<PathBuf> {
+    let expected_hash = hex::decode(sha256)?;
+    let u = parse_distribution_url(url)?;
+
+    let cache_path = cache_dir.join(basename);
+
+    if cache_path.exists() {
+        let file_hash = sha256_path(&cache_path)?;
+
+        // We don't care about timing side-channels from the string compare.
+        if file_hash == expected_hash {
+            verify_digest(basename, sha256, &file_hash)?;
+            verify_integrity(url, basename, &file_hash)?;
+
+            if REVALIDATE_CACHED_DOWNLOADS.load(Ordering::SeqCst) {
+                revalidate_cached_download(&u, &cache_path)?;
+            }
+
+            return Ok(cache_path);
+        }
+    }
+
+    let candidates = download_candidates(&u, mirrors, MIRROR_PROBE_TIMEOUT);
+
+    let mut attempts = vec![];
+    let mut fetched = None;
+
+    for source in &candidates {
+        check_cancelled()?;
+
+        let primary = source == &u;
+        let result = fetch_distribution(source, authorization.filter(|_| primary)).and_then(
+            |(data, etag)| {
+                let mut hasher = Sha256::new();
+                hasher.update(&data);
+                let digest = hasher.finalize();
+                verify_digest(basename, sha256, &digest)?;
+                verify_integrity(url, basename, &digest)?;
+
+                Ok((data, etag))
+            },
+        );
+
+        match result {
+            Ok(v) => {
+                fetched = Some(v);
+                break;
+            }
+            // A mismatch when there are alternatives more likely means a
+            // stale mirror than tampering, so fall through to the next one.
+            Err(e)
+                if candidates.len() > 1
+                    && matches!(
+                        DistributionError::code_of(&e),
+                        Some("CHECKSUM") | Some("NETWORK")
+                    ) =>
+            {
+                warn!(logger, "{}; trying next mirror", e);
+                attempts.push((source.to_string(), e.to_string()));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    let (data, etag) = fetched.ok_or_else(|| DistributionError::MirrorsExhausted {
+        name: basename.to_string(),
+        attempts,
+    })?;
+
+    check_cancelled()?;
+
+    let mut temp_cache_path = cache_path.clone();
+    temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
+
+    fs::write(&temp_cache_path, data).context("unable to write distribution file")?;
+
+    fs::rename(&temp_cache_path, &cache_path)
+        .or_else(|e| -> Result<()> {
+            fs::remove_file(&temp_cache_path)
+                .context("unable to remove temporary distribution file")?;
+
+            if cache_path.exists() {
+                download_distribution_with(
+                    logger,
+                    url,
+                    sha256,
+                    cache_dir,
+                    basename,
+                    mirrors,
+                    authorization,
+                )?;
+                return Ok(());
+            }
+
+            Err(e.into())
+        })
+        .context("unable to rename downloaded distribution file")?;
+
+    // The ETag is only needed for optional revalidation. So failure to record
+    // it isn't fatal.
+    if let Some(etag) = etag {
+        fs::write(etag_path(&cache_path), etag).ok();
+    }
+
+    Ok(cache_path)
+}
+
+pub fn copy_local_distribution(path: &Path, sha256: &str, cache_dir: &Path) -> Result<PathBuf> {
+    let expected_hash = hex::decode(sha256)?;
+    let basename = path.file_name().unwrap().to_str().unwrap().to_string();
+    let cache_path = cache_dir.join(&basename);
+
+    if cache_path.exists() {
+        let file_hash = sha256_path(&cache_path)?;
+
+        if file_hash == expected_hash {
+            verify_digest(&basename, sha256, &file_hash)?;
+
+            println!(
+                "existing {} passes SHA-256 integrity check",
+                cache_path.display()
+            );
+            return Ok(cache_path);
+        }
+    }
+
+    let source_hash = sha256_path(path)?;
+    verify_digest(&basename, sha256, &source_hash)?;
+
+    println!("copying {}", path.display());
+    std::fs::copy(path, &cache_path)?;
+
+    Ok(cache_path)
+}
+
+/// Obtain a local Path for a Python distribution tar archive.
+///
+/// Takes a parsed config and a cache directory as input. Usually the cache
+/// directory is the OUT_DIR for the invocation of a Cargo build script.
+/// A Python distribution will be fetched according to the configuration and a
+/// copy of the archive placed in ``cache_dir``. If the archive already exists
+/// in ``cache_dir``, it will be verified and returned.
+///
+/// Local filesystem paths are preferred over remote URLs if both are defined.
+pub fn resolve_python_distribution_archive(
+    logger: &slog::Logger,
+    dist: &PythonDistributionLocation,
+    cache_dir: &Path,
+) -> Result<PathBuf> {
+    if !cache_dir.exists() {
+        create_dir_all(cache_dir).unwrap();
+    }
+
+    match dist {
+        PythonDistributionLocation::Local { local_path, sha256 } => {
+            let p = PathBuf::from(local_path);
+            copy_local_distribution(&p, sha256, cache_dir)
+        }
+        PythonDistributionLocation::Url { url, sha256 } => {
+            download_distribution(logger, url, sha256, cache_dir)
+        }
+        PythonDistributionLocation::Oci { reference, digest } => {
+            let cache_path = cache_dir.join(dist.filename().as_ref());
+
+            // Avoid querying the registry if we already have the blob.
+            if cache_path.exists() {
+                let file_hash = sha256_path(&cache_path)?;
+
+                if file_hash == hex::decode(digest)? {
+                    verify_digest(&dist.filename(), digest, &file_hash)?;
+                    verify_integrity(
+                        &OciReference::parse(reference)?.blob_url(digest),
+                        &dist.filename(),
+                        &file_hash,
+                    )?;
+                    return Ok(cache_path);
+                }
+            }
+
+            let blob = resolve_oci_blob(reference, digest)?;
+            let mirrors = DOWNLOAD_MIRRORS.read().unwrap().clone();
+            download_distribution_with(
+                logger,
+                blob.url.as_str(),
+                digest,
+                cache_dir,
+                &dist.filename(),
+                &mirrors,
+                blob.authorization.as_deref(),
+            )
+        }
+        PythonDistributionLocation::Git {
+            repo,
+            rev,
+            path,
+            sha256,
+        } => {
+            let basename = dist.filename();
+            let cache_path = cache_dir.join(basename.as_ref());
+
+            if cache_path.exists() {
+                let file_hash = sha256_path(&cache_path)?;
+
+                if file_hash == hex::decode(sha256)? {
+                    verify_digest(&basename, sha256, &file_hash)?;
+                    return Ok(cache_path);
+                }
+            }
+
+            warn!(logger, "fetching {} from {}", rev, repo);
+            let data = read_git_file(repo, rev, path)?;
+            verify_digest(&basename, sha256, &Sha256::digest(&data))?;
+
+            write_cached_archive(&cache_path, &data)?;
+
+            Ok(cache_path)
+        }
+        PythonDistributionLocation::Parts { urls, sha256 } => {
+            let basename = dist.filename();
+            let cache_path = cache_dir.join(basename.as_ref());
+
+            if cache_path.exists() {
+                let file_hash = sha256_path(&cache_path)?;
+
+                if file_hash == hex::decode(sha256)? {
+                    verify_digest(&basename, sha256, &file_hash)?;
+                    return Ok(cache_path);
+                }
+            }
+
+            let data = download_parts(urls)?;
+            verify_digest(&basename, sha256, &Sha256::digest(&data))?;
+
+            write_cached_archive(&cache_path, &data)?;
+
+            Ok(cache_path)
+        }
+    }
+}
+
+/// Atomically write archive content to a path in a cache directory.
+fn write_cached_archive(cache_path: &Path, data: &[u8]) -> Result<()> {
+    let mut temp_cache_path = cache_path.to_path_buf();
+    temp_cache_path.set_file_name(format!("{}.tmp", Uuid::new_v4()));
+    fs::write(&temp_cache_path, data).context("unable to write distribution file")?;
+    fs::rename(&temp_cache_path, cache_path).context("unable to rename distribution file")?;
+
+    Ok(())
+}
+
+/// Download the parts of a split archive and concatenate them.
+///
+/// Errors identify the part that failed.
+fn download_parts(urls: &[String]) -> Result<Vec<u8>> {
+    if urls.is_empty() {
+        return Err(anyhow!("split archive has no parts"));
+    }
+
+    let client = distribution_http_client()?;
+    let mut data = vec![];
+
+    for (i, url) in urls.iter().enumerate() {
+        let u = parse_distribution_url(url)?;
+        let network = |message: String| DistributionError::Network {
+            url: url.clone(),
+            message: format!("part {} of {}: {}", i + 1, urls.len(), message),
+        };
+
+        check_cancelled()?;
+        let _permit = DOWNLOAD_CONCURRENCY.acquire();
+
+        println!("downloading {}", u);
+        let mut response = client
+            .get(u.as_str())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| network(e.to_string()))?;
+        CancelableReader::new(&mut response)
+            .read_to_end(&mut data)
+            .map_err(|e| network(e.to_string()))?;
+    }
+
+    Ok(data)
+}
+
+/// Run `git` with arguments, returning its standard output.
+///
+/// `stdin` is written to the process's standard input.
+fn run_git(args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut child = std::process::Command::new("git")
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .context("running git; is it installed?")?;
+
+    {
+        let mut child_stdin = child.stdin.take().unwrap();
+        if let Some(stdin) = stdin {
+            child_stdin.write_all(stdin)?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// The first line of Git LFS pointer files.
+const GIT_LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1";
+
+/// Ensure a repository or revision of a git location can't be mistaken for an option.
+///
+/// Values come from distribution records. A value like `--upload-pack=<cmd>`
+/// would otherwise make git run arbitrary commands.
+fn check_git_argument(what: &str, value: &str) -> Result<()> {
+    if value.is_empty() || value.starts_with('-') {
+        Err(anyhow!("invalid git {} {:?}", what, value))
+    } else {
+        Ok(())
+    }
+}
+
+/// Read a file from a git repository at a revision.
+///
+/// Only `rev` is fetched, into a temporary repository. Git LFS pointers are
+/// resolved to the content they point to.
+fn read_git_file(repo: &str, rev: &str, path: &str) -> Result<Vec<u8>> {
+    check_git_argument("repository", repo)?;
+    check_git_argument("revision", rev)?;
+
+    let td = tempfile::Builder::new()
+        .prefix("pyoxidizer-git-")
+        .tempdir()?;
+    let git_dir = td.path().to_string_lossy().to_string();
+
+    run_git(&["init", "-q", "--", &git_dir], None)?;
+    run_git(
+        &["-C", &git_dir, "remote", "add", "--", "origin", repo],
+        None,
+    )?;
+    run_git(
+        &[
+            "-C", &git_dir, "fetch", "-q", "--depth", "1", "--", "origin", rev,
+        ],
+        None,
+    )?;
+
+    let data = run_git(
+        &["-C", &git_dir, "show", &format!("FETCH_HEAD:{}", path)],
+        None,
+    )?;
+
+    if data.starts_with(GIT_LFS_POINTER_PREFIX) {
+        run_git(&["-C", &git_dir, "lfs", "smudge", "--", path], Some(&data))
+    } else {
+        Ok(data)
+    }
+}
+
+/// Media types of OCI and Docker image manifests we accept.
+const OCI_MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Supplies credentials for container registries that require authentication.
+///
+/// See `set_credential_provider()`.
+pub trait CredentialProvider: Send + Sync {
+    /// Obtain the username and password to use for a registry host, if any.
+    ///
+    /// `host` is the registry as written in OCI references. e.g. `ghcr.io`.
+    fn credentials(&self, host: &str) -> Option<(String, String)>;
+}
+
+static CREDENTIAL_PROVIDER: Lazy<RwLock<Option<Arc<dyn CredentialProvider>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+/// Install the `CredentialProvider` consulted when a registry requires authentication.
+///
+/// Credentials are exchanged for a token at the registry's token service.
+/// Without a provider, the default, tokens are requested anonymously, which
+/// suffices for public repositories.
+#[allow(unused)]
+pub fn set_credential_provider(provider: Option<Arc<dyn CredentialProvider>>) {
+    *CREDENTIAL_PROVIDER.write().unwrap() = provider;
+}
+
+/// A parsed OCI artifact reference.
+#[derive(Debug, Eq, PartialEq)]
+struct OciReference {
+    registry: String,
+    repository: String,
+    /// A tag or a `sha256:<hex>` manifest digest.
+    reference: String,
+}
+
+impl OciReference {
+    fn parse(value: &str) -> Result<Self> {
+        let invalid = || {
+            anyhow!(
+                "invalid OCI reference {}; expected <registry>/<repository>:<tag> or <registry>/<repository>@sha256:<digest>",
+                value
+            )
+        };
+
+        let (registry, rest) = value.split_once('/').ok_or_else(invalid)?;
+
+        let (repository, reference) = if let Some((repository, digest)) = rest.split_once('@') {
+            (repository, digest.to_string())
+        } else {
+            match rest.rsplit_once(':') {
+                Some((repository, tag)) if !tag.contains('/') => (repository, tag.to_string()),
+                _ => (rest, "latest".to_string()),
+            }
+        };
+
+        if registry.is_empty() || repository.is_empty() || reference.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            reference,
+        })
+    }
+
+    /// The base URL of the registry.
+    ///
+    /// Registries on loopback hosts are accessed over plain HTTP, as Docker does.
+    fn registry_url(&self) -> String {
+        let loopback = Url::parse(&format!("http://{}/", self.registry))
+            .ok()
+            .map(|u| matches!(u.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")))
+            .unwrap_or(false);
+
+        format!(
+            "{}://{}",
+            if loopback { "http" } else { "https" },
+            self.registry
+        )
+    }
+
+    fn manifest_url(&self) -> String {
+        format!(
+            "{}/v2/{}/manifests/{}",
+            self.registry_url(),
+            self.repository,
+            self.reference
+        )
+    }
+
+    fn blob_url(&self, digest: &str) -> String {
+        format!(
+            "{}/v2/{}/blobs/sha256:{}",
+            self.registry_url(),
+            self.repository,
+            digest
+        )
+    }
+}
+
+/// Verify an OCI image manifest and that it references a layer with the given digest.
+fn verify_oci_manifest(reference: &OciReference, manifest: &[u8], digest: &str) -> Result<()> {
+    if let Some(pinned) = reference.reference.strip_prefix("sha256:") {
+        if hex::encode(Sha256::digest(manifest)) != pinned.to_lowercase() {
+            return Err(anyhow!(
+                "manifest of {}/{} does not match digest sha256:{}",
+                reference.registry,
+                reference.repository,
+                pinned
+            ));
+        }
+    }
+
+    let manifest: serde_json::Value =
+        serde_json::from_slice(manifest).context("parsing OCI manifest")?;
+
+    let wanted = format!("sha256:{}", digest.to_lowercase());
+    let referenced = manifest["layers"]
+        .as_array()
+        .map(|layers| {
+            layers
+                .iter()
+                .any(|layer| layer["digest"] == wanted.as_str())
+        })
+        .unwrap_or(false);
+
+    if referenced {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "manifest of {}/{}:{} does not reference layer {}",
+            reference.registry,
+            reference.repository,
+            reference.reference,
+            wanted
+        ))
+    }
+}
+
+/// The URL of the archive blob of an OCI distribution and how to request it.
+struct OciBlob {
+    url: Url,
+    /// The `Authorization` header value to send, if the registry requires one.
+    authorization: Option<String>,
+}
+
+impl OciBlob {
+    fn request(
+        &self,
+        client: &reqwest::blocking::Client,
+        method: reqwest::Method,
+    ) -> reqwest::blocking::RequestBuilder {
+        let request = client.request(method, self.url.as_str());
+
+        match &self.authorization {
+            Some(value) => request.header(reqwest::header::AUTHORIZATION, value),
+            None => request,
+        }
+    }
+}
+
+/// Parse the parameters of a `WWW-Authenticate` header with a Bearer challenge.
+///
+/// Returns `None` if the header isn't a well-formed Bearer challenge.
+fn parse_bearer_challenge(value: &str) -> Option<BTreeMap<String, String>> {
+    let (scheme, mut rest) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut params = BTreeMap::new();
+
+    loop {
+        rest = rest.trim_start().trim_start_matches(',').trim_start();
+        if rest.is_empty() {
+            break;
+        }
+
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+
+        // Values like scopes may contain commas, so they are usually quoted.
+        let (value, after) = if let Some(quoted) = after.strip_prefix('"') {
+            quoted.split_once('"')?
+        } else {
+            after.split_once(',').unwrap_or((after, ""))
+        };
+
+        params.insert(key.trim().to_lowercase(), value.trim().to_string());
+        rest = after;
+    }
+
+    Some(params)
+}
+
+/// Obtain a token from the token service named by a registry's Bearer challenge.
+///
+/// `url` is the URL the registry challenged a request for.
+fn request_registry_token(
+    client: &reqwest::blocking::Client,
+    url: &Url,
+    challenge: &BTreeMap<String, String>,
+    credentials: Option<(String, String)>,
+) -> Result<String> {
+    let unauthorized = |message: String| DistributionError::Unauthorized {
+        url: url.to_string(),
+        message,
+    };
+
+    let realm = challenge.get("realm").ok_or_else(|| {
+        unauthorized("Bearer challenge does not name a token service".to_string())
+    })?;
+    let mut token_url = parse_distribution_url(realm)?;
+    for key in ["service", "scope"] {
+        if let Some(value) = challenge.get(key) {
+            token_url.query_pairs_mut().append_pair(key, value);
+        }
+    }
+
+    let mut request = client.get(token_url.as_str());
+    if let Some((username, password)) = credentials {
+        request = request.basic_auth(username, Some(password));
+    }
+
+    let response = request.send()?;
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(unauthorized(format!(
+            "token service {} responded with HTTP {}",
+            realm, status
+        ))
+        .into());
+    }
+
+    let body: serde_json::Value = serde_json::from_slice(&response.error_for_status()?.bytes()?)
+        .context("parsing registry token response")?;
+
+    // Docker's token specification allows either key.
+    body["token"]
+        .as_str()
+        .or_else(|| body["access_token"].as_str())
+        .map(|token| token.to_string())
+        .ok_or_else(|| {
+            unauthorized(format!("token service {} did not provide a token", realm)).into()
+        })
+}
+
+/// Resolve the archive blob of an OCI distribution.
+///
+/// The artifact manifest is fetched and verified to reference the blob. See
+/// `resolve_oci_blob_with()` for how registries are authenticated to.
+fn resolve_oci_blob(reference: &str, digest: &str) -> Result<OciBlob> {
+    let provider = CREDENTIAL_PROVIDER.read().unwrap().clone();
+
+    resolve_oci_blob_with(
+        &distribution_http_client()?,
+        reference,
+        digest,
+        provider.as_deref(),
+    )
+}
+
+/// Resolve the archive blob of an OCI distribution, authenticating with a `CredentialProvider`.
+///
+/// If the registry challenges the manifest request with `WWW-Authenticate:
+/// Bearer`, a token is requested from the challenge's token service, with the
+/// provider's credentials for the registry or anonymously, and the manifest
+/// and blob are requested with it. Token services are subject to
+/// `set_allowed_hosts()`. A registry or token service refusing access is
+/// reported as `DistributionError::Unauthorized`.
+fn resolve_oci_blob_with(
+    client: &reqwest::blocking::Client,
+    reference: &str,
+    digest: &str,
+    provider: Option<&dyn CredentialProvider>,
+) -> Result<OciBlob> {
+    let reference = OciReference::parse(reference)?;
+
+    let manifest_url = parse_distribution_url(&reference.manifest_url())?;
+    let get_manifest = |authorization: Option<&str>| {
+        let request = client
+            .get(manifest_url.as_str())
+            .header(reqwest::header::ACCEPT, OCI_MANIFEST_MEDIA_TYPES);
+
+        match authorization {
+            Some(value) => request.header(reqwest::header::AUTHORIZATION, value),
+            None => request,
+        }
+        .send()
+    };
+
+    let mut response = get_manifest(None)?;
+    let mut authorization = None;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_bearer_challenge)
+            .ok_or_else(|| DistributionError::Unauthorized {
+                url: manifest_url.to_string(),
+                message: "registry requires authentication but sent no Bearer challenge"
+                    .to_string(),
+            })?;
+
+        let credentials = provider.and_then(|p| p.credentials(&reference.registry));
+        let token = request_registry_token(client, &manifest_url, &challenge, credentials)?;
+        let value = format!("Bearer {}", token);
+
+        response = get_manifest(Some(&value))?;
+        authorization = Some(value);
+    }
+
+    let status = response.status();
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        return Err(DistributionError::Unauthorized {
+            url: manifest_url.to_string(),
+            message: format!("registry responded with HTTP {}", status),
+        }
+        .into());
+    }
+
+    let manifest = response.error_for_status()?.bytes()?;
+
+    verify_oci_manifest(&reference, &manifest, digest)?;
+
+    Ok(OciBlob {
+        url: parse_distribution_url(&reference.blob_url(digest))?,
+        authorization,
+    })
+}
+
+/// Resolve a Python distribution archive.
+///
+/// Returns a tuple of (archive path, extract directory).
+pub fn resolve_python_distribution_from_location(
+    logger: &slog::Logger,
+    location: &PythonDistributionLocation,
+    distributions_dir: &Path,
+) -> Result<(PathBuf, PathBuf)> {
+    warn!(logger, "resolving Python distribution {:?}", location);
+    let path = resolve_python_distribution_archive(logger, location, distributions_dir)?;
+    warn!(
+        logger,
+        "Python distribution available at {}",
+        path.display()
+    );
+
+    let distribution_hash = location.sha256();
+
+    let distribution_path = distributions_dir.join(format!("python.{}", &distribution_hash[0..12]));
+
+    Ok((path, distribution_path))
+}
+
+/// A `Read` adapter that computes the SHA-256 of all data read through it.
+struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Read any remaining data from the source and return the digest of all data.
+    fn finish(mut self) -> Result<Vec<u8>> {
+        read_chunked(&mut self, HASH_CHUNK_SIZE.load(Ordering::SeqCst), |_| {})?;
+
+        Ok(self.hasher.finalize().to_vec())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.hasher.update(&buf[..count]);
+
+        Ok(count)
+    }
+}
+
+/// Open a stream to the raw archive content of a distribution.
+///
+/// Content is read directly from its source and isn't cached.
+fn open_location_stream(location: &PythonDistributionLocation) -> Result<Box<dyn Read>> {
+    match location {
+        PythonDistributionLocation::Local { local_path, .. } => Ok(Box::new(
+            File::open(local_path).with_context(|| format!("opening {}", local_path))?,
+        )),
+        PythonDistributionLocation::Url { url, .. } => {
+            let u = parse_distribution_url(url)?;
+            let client = distribution_http_client()?;
+            let response = client.get(u.as_str()).send()?.error_for_status()?;
+
+            Ok(Box::new(response))
+        }
+        PythonDistributionLocation::Oci { reference, digest } => {
+            let blob = resolve_oci_blob(reference, digest)?;
+            let client = distribution_http_client()?;
+            let response = blob
+                .request(&client, reqwest::Method::GET)
+                .send()?
+                .error_for_status()?;
+
+            Ok(Box::new(response))
+        }
+        PythonDistributionLocation::Git {
+            repo, rev, path, ..
+        } => Ok(Box::new(std::io::Cursor::new(read_git_file(
+            repo, rev, path,
+        )?))),
+        PythonDistributionLocation::Parts { urls, .. } => {
+            Ok(Box::new(std::io::Cursor::new(download_parts(urls)?)))
+        }
+    }
+}
+
+/// Verify the digest of a distribution archive without storing it.
+///
+/// The archive is streamed from its location through a hasher and discarded.
+/// Nothing is written to disk. Returns an error if the digest doesn't match.
+#[allow(unused)]
+pub fn verify_location(location: &PythonDistributionLocation) -> Result<()> {
+    hex::decode(location.sha256())?;
+
+    let reader = HashingReader::new(open_location_stream(location)?);
+
+    verify_digest(&location.filename(), location.sha256(), &reader.finish()?)
+}
+
+/// Open a stream to the raw archive content of a distribution after verifying its digest.
+///
+/// Local archives are hashed in place and then read again. Remote archives
+/// are buffered in memory.
+fn open_verified_location_stream(location: &PythonDistributionLocation) -> Result<Box<dyn Read>> {
+    hex::decode(location.sha256())?;
+
+    match location {
+        PythonDistributionLocation::Local { local_path, .. } => {
+            let path = Path::new(local_path);
+
+            verify_digest(&location.filename(), location.sha256(), &sha256_path(path)?)?;
+
+            Ok(Box::new(
+                File::open(path).with_context(|| format!("opening {}", path.display()))?,
+            ))
+        }
+        PythonDistributionLocation::Url { .. }
+        | PythonDistributionLocation::Oci { .. }
+        | PythonDistributionLocation::Git { .. }
+        | PythonDistributionLocation::Parts { .. } => {
+            let mut data = vec![];
+            open_location_stream(location)?.read_to_end(&mut data)?;
+
+            verify_digest(
+                &location.filename(),
+                location.sha256(),
+                &Sha256::digest(&data),
+            )?;
+
+            Ok(Box::new(std::io::Cursor::new(data)))
+        }
+    }
+}
+
+/// Maximum number of times to retry a request that was rate limited.
+const MAX_RATE_LIMIT_RETRIES: usize = 3;
+
+/// Maximum time we're willing to wait before retrying a rate limited request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Check whether a distribution archive can be obtained, without obtaining it.
+///
+/// For URLs, a `HEAD` request is issued. If the server responds with HTTP 429,
+/// the request is retried after the delay advertised by the `Retry-After`
+/// header. For local paths, the existence of the file is checked.
+///
+/// Returns `Ok(())` if the archive is available or an error describing why it
+/// isn't.
+#[allow(unused)]
+pub fn check_location_availability(location: &PythonDistributionLocation) -> Result<()> {
+    match location {
+        PythonDistributionLocation::Local { local_path, .. } => {
+            if Path::new(local_path).is_file() {
+                Ok(())
+            } else {
+                Err(anyhow!("{} does not exist", local_path))
+            }
+        }
+        PythonDistributionLocation::Url { url, .. } => {
+            let u = parse_distribution_url(url)?;
+            let client = distribution_http_client()?;
+
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+                let response = client.head(u.as_str()).send()?;
+
+                if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS
+                    && attempt <= MAX_RATE_LIMIT_RETRIES
+                {
+                    let delay = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or_else(|| Duration::from_secs(attempt as u64));
+
+                    std::thread::sleep(delay.min(MAX_RETRY_AFTER));
+                    continue;
+                }
+
+                return if response.status().is_success() {
+                    Ok(())
+                } else {
+                    Err(anyhow!("HTTP {} from {}", response.status(), url))
+                };
+            }
+        }
+        PythonDistributionLocation::Oci { reference, digest } => {
+            let blob = resolve_oci_blob(reference, digest)?;
+            let response = blob
+                .request(&distribution_http_client()?, reqwest::Method::HEAD)
+                .send()?;
+
+            if response.status().is_success() {
+                Ok(())
+            } else {
+                Err(anyhow!("HTTP {} from {}", response.status(), blob.url))
+            }
+        }
+        PythonDistributionLocation::Git { repo, rev, .. } => {
+            // Commits can't be looked up remotely. So only the repository is
+            // checked for them.
+            check_git_argument("repository", repo)?;
+            check_git_argument("revision", rev)?;
+
+            let refs = run_git(&["ls-remote", "--", repo, rev], None)?;
+
+            if refs.is_empty() && !(rev.len() == 40 && hex::decode(rev).is_ok()) {
+                Err(anyhow!("{} not found in {}", rev, repo))
+            } else {
+                Ok(())
+            }
+        }
+        PythonDistributionLocation::Parts { urls, .. } => {
+            let client = distribution_http_client()?;
+
+            for (i, url) in urls.iter().enumerate() {
+                let u = parse_distribution_url(url)?;
+                let response = client.head(u.as_str()).send()?;
+
+                if !response.status().is_success() {
+                    return Err(anyhow!(
+                        "HTTP {} from part {} of {} ({})",
+                        response.status(),
+                        i + 1,
+                        urls.len(),
+                        url
+                    ));
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Maximum zstd window, as a power of 2, used to decompress distribution archives.
+///
+/// `None` uses the zstd default, which accepts archives produced by
+/// `python-build-standalone`.
+static MAX_WINDOW_LOG: Lazy<RwLock<Option<u32>>> = Lazy::new(|| RwLock::new(None));
+
+/// Set the maximum zstd window used when decompressing distribution archives.
+///
+/// Decompression memory grows with the window size an archive was compressed
+/// with. Limiting the window to `2^window_log` bytes bounds that memory.
+/// Archives needing a larger window then fail to decompress with an error
+/// rather than consuming the memory. `window_log` must be between 10 and 31.
+/// `None` restores the default, which accepts any window an archive declares
+/// up to `2^ZSTD_MAX_WINDOW_LOG` bytes, including windows beyond zstd's
+/// default limit of `2^27` bytes used by archives compressed with
+/// `zstd --long`.
+#[allow(unused)]
+pub fn set_max_decompression_window_log(window_log: Option<u32>) -> Result<()> {
+    if let Some(window_log) = window_log {
+        if !(10..=ZSTD_MAX_WINDOW_LOG).contains(&window_log) {
+            return Err(anyhow!(
+                "zstd window log {} is outside the supported range 10-{}",
+                window_log,
+                ZSTD_MAX_WINDOW_LOG
+            ));
+        }
+    }
+
+    *MAX_WINDOW_LOG.write().unwrap() = window_log;
+
+    Ok(())
+}
+
+/// Magic number at the start of zstd frames.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Largest window, as a power of 2, zstd decodes without being told to accept more.
+const ZSTD_DEFAULT_WINDOW_LOG_MAX: u32 = 27;
+
+/// Largest window, as a power of 2, the zstd decoder can be told to accept.
+const ZSTD_MAX_WINDOW_LOG: u32 = 31;
+
+/// Determine the window a zstd frame needs, as a power of 2, from its header.
+///
+/// `header` is the start of the frame, including the magic number. Returns
+/// `None` if the header is incomplete or the frame is a single segment,
+/// which is decoded without a window.
+fn zstd_frame_window_log(header: &[u8]) -> Option<u32> {
+    let descriptor = *header.get(4)?;
+
+    // Single_Segment_Flag.
+    if descriptor & 0x20 != 0 {
+        return None;
+    }
+
+    let window_descriptor = *header.get(5)?;
+    let window_log = 10 + u32::from(window_descriptor >> 3);
+
+    // A non-zero mantissa adds fractions of the base window.
+    Some(if window_descriptor & 0x07 == 0 {
+        window_log
+    } else {
+        window_log + 1
+    })
+}
+
+/// Compressions `ArchiveDecoder` can decompress.
+///
+/// No decoder is optional, so there are no features to enable for others.
+const SUPPORTED_COMPRESSIONS: [&str; 2] = ["zstd", "brotli"];
+
+/// Magic numbers of compressions `ArchiveDecoder` recognizes but can't decompress.
+const UNSUPPORTED_COMPRESSION_MAGICS: [(&str, &[u8]); 5] = [
+    ("gzip", &[0x1f, 0x8b]),
+    ("xz", &[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+    ("bzip2", b"BZh"),
+    ("lz4", &[0x04, 0x22, 0x4d, 0x18]),
+    ("zip", b"PK\x03\x04"),
+];
+
+/// Size of the buffer used when decompressing brotli archives.
+const BROTLI_BUFFER_SIZE: usize = 65536;
+
+/// Compressed data already read from an archive's source, followed by the rest of it.
+type SniffedSource<R> = std::io::Chain<std::io::Cursor<Vec<u8>>, R>;
+
+enum ArchiveDecompressor<R: Read> {
+    Zstd(zstd::stream::Decoder<'static, std::io::BufReader<SniffedSource<R>>>),
+    Brotli(Box<brotli_decompressor::Decompressor<SniffedSource<R>>>),
+}
+
+/// A decoder for distribution archives honoring the configured maximum zstd window.
+///
+/// Archives are zstd or brotli compressed, i.e. `.tar.zst` or `.tar.br`. The
+/// compression is detected from the content: brotli streams have no magic
+/// number, so data not starting with the zstd magic number is decoded as
+/// brotli.
+pub struct ArchiveDecoder<R: Read> {
+    decoder: ArchiveDecompressor<R>,
+    window_log: Option<u32>,
+    /// Window the zstd frame needs, as a power of 2, if known.
+    required_window_log: Option<u32>,
+}
+
+impl<R: Read> ArchiveDecoder<R> {
+    /// Construct a decoder reading compressed data from `source`.
+    ///
+    /// The zstd window is limited per `set_max_decompression_window_log()`.
+    pub fn new(source: R) -> Result<Self> {
+        let window_log = *MAX_WINDOW_LOG.read().unwrap();
+
+        Self::with_max_window_log(source, window_log)
+    }
+
+    /// Construct a decoder limiting the zstd window to `2^window_log` bytes.
+    ///
+    /// `None` accepts any window the archive declares. Unlike `new()`, the
+    /// limit set by `set_max_decompression_window_log()` is ignored.
+    pub fn with_max_window_log(mut source: R, window_log: Option<u32>) -> Result<Self> {
+        // The magic number, frame header descriptor, and window descriptor.
+        let mut header = vec![];
+        (&mut source).take(6).read_to_end(&mut header)?;
+        let is_zstd = header.starts_with(&ZSTD_MAGIC);
+
+        // Anything else is assumed to be brotli. So recognize formats we can't
+        // decompress to avoid reporting them as invalid brotli data.
+        if let Some((detected, _)) = UNSUPPORTED_COMPRESSION_MAGICS
+            .iter()
+            .find(|(_, magic)| header.starts_with(magic))
+        {
+            return Err(DistributionError::UnsupportedCompression {
+                detected: detected.to_string(),
+            }
+            .into());
+        }
+
+        let required_window_log = if is_zstd {
+            zstd_frame_window_log(&header)
+        } else {
+            None
+        };
+
+        // No limit makes such frames decodable. So there's no limit to suggest raising.
+        if let Some(required) = required_window_log {
+            if required > ZSTD_MAX_WINDOW_LOG {
+                return Err(anyhow!(
+                    "archive requires a zstd window of 2^{} bytes, larger than the decoder maximum of 2^{} bytes; \
+                     recompress it with a smaller window",
+                    required,
+                    ZSTD_MAX_WINDOW_LOG
+                ));
+            }
+        }
+        let source = std::io::Cursor::new(header).chain(source);
+
+        let decoder = if is_zstd {
+            let mut decoder = zstd::stream::Decoder::new(source)?;
+            match (window_log, required_window_log) {
+                (Some(window_log), _) => decoder.window_log_max(window_log)?,
+                // Accept long windows, as produced by `zstd --long`, by default.
+                (None, Some(required)) if required > ZSTD_DEFAULT_WINDOW_LOG_MAX => {
+                    decoder.window_log_max(required)?
+                }
+                (None, _) => {}
+            }
+
+            ArchiveDecompressor::Zstd(decoder)
+        } else {
+            ArchiveDecompressor::Brotli(Box::new(brotli_decompressor::Decompressor::new(
+                source,
+                BROTLI_BUFFER_SIZE,
+            )))
+        };
+
+        Ok(Self {
+            decoder,
+            window_log,
+            required_window_log,
+        })
+    }
+
+    /// Obtain the source compressed data is read from.
+    pub fn into_inner(self) -> R {
+        match self.decoder {
+            ArchiveDecompressor::Zstd(decoder) => decoder.finish().into_inner().into_inner().1,
+            ArchiveDecompressor::Brotli(decoder) => decoder.into_inner().into_inner().1,
+        }
+    }
+}
+
+impl<R: Read> Read for ArchiveDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match &mut self.decoder {
+            ArchiveDecompressor::Zstd(decoder) => {
+                if let (Some(window_log), Some(required)) =
+                    (self.window_log, self.required_window_log)
+                {
+                    // Construction rejected frames beyond `ZSTD_MAX_WINDOW_LOG`.
+                    // So the suggested limit is one the setter accepts.
+                    if required > window_log {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "archive requires a zstd window of 2^{} bytes, larger than the configured maximum of 2^{} bytes; \
+                                 it was likely compressed with `zstd --long`. Raise the maximum with \
+                                 set_max_decompression_window_log(Some({})) or recompress it with a smaller window",
+                                required, window_log, required
+                            ),
+                        ));
+                    }
+                }
+
+                decoder.read(buf).map_err(|e| match self.window_log {
+                    // zstd reports frames with too large a window this way.
+                    Some(window_log) if e.to_string().contains("too much memory") => {
+                        std::io::Error::new(
+                            e.kind(),
+                            format!(
+                                "archive requires a zstd window larger than the configured maximum of 2^{} bytes",
+                                window_log
+                            ),
+                        )
+                    }
+                    _ => e,
+                })
+            }
+            ArchiveDecompressor::Brotli(decoder) => decoder.read(buf).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("archive is neither zstd nor valid brotli data: {}", e),
+                )
+            }),
+        }
+    }
+}
+
+/// Describes an entry in a distribution archive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path of the entry within the archive.
+    pub path: PathBuf,
+
+    /// Size in bytes of the entry's content.
+    pub size: u64,
+}
+
+/// List the entries of a distribution archive without extracting it.
+///
+/// The archive is streamed from its location and tar headers are read as data
+/// arrives. The SHA-256 of the archive is verified once the stream is
+/// exhausted and an error is returned if it doesn't match, so a returned
+/// listing always describes the expected archive.
+#[allow(unused)]
+pub fn list_archive_contents(location: &PythonDistributionLocation) -> Result<Vec<ArchiveEntry>> {
+    hex::decode(location.sha256())?;
+
+    let reader = HashingReader::new(open_location_stream(location)?);
+    let mut archive = tar::Archive::new(ArchiveDecoder::new(reader)?);
+
+    let mut entries = vec![];
+
+    for entry in archive.entries()? {
+        let entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        entries.push(ArchiveEntry {
+            path: entry.path()?.to_path_buf(),
+            size: entry.header().size()?,
+        });
+    }
+
+    let reader = archive.into_inner().into_inner();
+    verify_digest(&location.filename(), location.sha256(), &reader.finish()?)?;
+
+    Ok(entries)
+}
+
+/// The `PYTHON.json` and member paths of a distribution archive.
+#[derive(Clone, Debug)]
+pub struct ArchiveSummary {
+    /// Parsed content of `PYTHON.json`.
+    pub python_json: serde_json::Value,
+
+    /// Directory containing `PYTHON.json` within the archive. e.g. `python`.
+    ///
+    /// Paths in `PYTHON.json` are relative to it.
+    pub root: PathBuf,
+
+    /// Paths of all members of the archive.
+    pub members: BTreeSet<PathBuf>,
+}
+
+impl ArchiveSummary {
+    /// Whether the archive has a member at a path relative to `root`.
+    pub fn has_file(&self, path: &str) -> bool {
+        self.members.contains(&self.root.join(path))
+    }
+}
+
+/// Read the `PYTHON.json` and member paths of a distribution archive.
+///
+/// The archive is streamed without being extracted and its SHA-256 is verified.
+pub fn summarize_archive(location: &PythonDistributionLocation) -> Result<ArchiveSummary> {
+    hex::decode(location.sha256())?;
+
+    let reader = HashingReader::new(open_location_stream(location)?);
+    let mut archive = tar::Archive::new(ArchiveDecoder::new(reader)?);
+
+    let mut python_json = None;
+    let mut members = BTreeSet::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        let path = entry.path()?.to_path_buf();
+        if path == Path::new("python/PYTHON.json") || path == Path::new("PYTHON.json") {
+            let mut data = vec![];
+            entry.read_to_end(&mut data)?;
+            python_json = Some((path.parent().unwrap().to_path_buf(), data));
+        }
+        members.insert(path);
+    }
+
+    let reader = archive.into_inner().into_inner();
+    verify_digest(&location.filename(), location.sha256(), &reader.finish()?)?;
+
+    let (root, python_json) = python_json
+        .ok_or_else(|| anyhow!("{} does not contain PYTHON.json", location.filename()))?;
+    let python_json = serde_json::from_slice(&python_json)
+        .with_context(|| format!("parsing PYTHON.json of {}", location.filename()))?;
+
+    Ok(ArchiveSummary {
+        python_json,
+        root,
+        members,
+    })
+}
+
+/// Extension module sets of distributions, keyed by lowercase hex SHA-256 of the archive.
+static EXTENSION_MODULES: Lazy<Mutex<HashMap<String, Arc<BTreeSet<String>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Obtain the names of extension modules built into a distribution. e.g. `_ssl`.
+///
+/// Names are the keys of `build_info.extensions` in the archive's `PYTHON.json`.
+/// The archive is streamed without being extracted and its SHA-256 is verified.
+/// Results are cached per archive digest for the lifetime of the process, so
+/// each archive is read at most once.
+#[allow(unused)]
+pub fn distribution_extension_modules(
+    location: &PythonDistributionLocation,
+) -> Result<Arc<BTreeSet<String>>> {
+    let sha256 = location.sha256().to_lowercase();
+    hex::decode(&sha256)?;
+
+    if let Some(modules) = EXTENSION_MODULES.lock().unwrap().get(&sha256) {
+        return Ok(modules.clone());
+    }
+
+    let modules = Arc::new(
+        summarize_archive(location)?
+            .python_json
+            .get("build_info")
+            .and_then(|info| info.get("extensions"))
+            .and_then(|extensions| extensions.as_object())
+            .ok_or_else(|| {
+                anyhow!(
+                    "PYTHON.json of {} does not define build_info.extensions",
+                    location.filename()
+                )
+            })?
+            .keys()
+            .cloned()
+            .collect::<BTreeSet<_>>(),
+    );
+
+    EXTENSION_MODULES
+        .lock()
+        .unwrap()
+        .insert(sha256, modules.clone());
+
+    Ok(modules)
+}
+
+/// Obtain the build modes of libraries bundled with a distribution, keyed by library.
+///
+/// Modes are read from the `library_build_modes` key of the archive's
+/// `PYTHON.json`, as by `StandaloneDistribution::library_build_modes()`,
+/// without extracting the archive. Archives without the key have no known
+/// modes.
+#[allow(unused)]
+pub fn distribution_library_build_modes(
+    location: &PythonDistributionLocation,
+) -> Result<BTreeMap<String, String>> {
+    let summary = summarize_archive(location)?;
+
+    match summary.python_json.get("library_build_modes") {
+        None => Ok(BTreeMap::new()),
+        Some(modes) => serde_json::from_value(modes.clone()).with_context(|| {
+            format!(
+                "parsing library_build_modes of PYTHON.json of {}",
+                location.filename()
+            )
+        }),
+    }
+}
+
+/// Extract archive members matching a predicate from a distribution.
+///
+/// `predicate` receives the path of each member within the archive and
+/// returns whether to extract it. Matching members are extracted relative to
+/// `dest_dir`.
+///
+/// The SHA-256 of the entire archive is verified before anything is
+/// extracted. The digest covers the whole archive, not just the extracted
+/// members, so members are only trusted to the extent the whole archive is.
+///
+/// Returns the archive paths of extracted members.
+#[allow(unused)]
+pub fn extract_members(
+    location: &PythonDistributionLocation,
+    predicate: impl Fn(&Path) -> bool,
+    dest_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let reader = open_verified_location_stream(location)?;
+    let mut archive = tar::Archive::new(ArchiveDecoder::new(reader)?);
+
+    create_dir_all(dest_dir)
+        .with_context(|| format!("creating directory {}", dest_dir.display()))?;
+
+    let mut extracted = vec![];
+
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        let path = entry.path()?.to_path_buf();
+        if !predicate(&path) {
+            continue;
+        }
+
+        // See the comment in `StandaloneDistribution::from_tar()` for why we don't
+        // preserve mtimes.
+        entry.set_preserve_mtime(false);
+        entry
+            .unpack_in(dest_dir)
+            .with_context(|| format!("extracting {}", path.display()))?;
+
+        extracted.push(path);
+    }
+
+    Ok(extracted)
+}
+
+/// Leading directory of all members of `python-build-standalone` archives.
+pub const DEFAULT_STRIP_PREFIX: &str = "python/";
+
+/// Destination of files extracted from a distribution archive.
+///
+/// Paths are relative to the root of the extracted distribution and only
+/// consist of normal components. Parent directories of a path are created
+/// before anything is written under them. Implementations can write to the
+/// filesystem, like `DiskSink`, or to an in-memory filesystem.
+pub trait FileSink {
+    /// Create a directory.
+    fn create_dir(&mut self, path: &Path) -> Result<()>;
+
+    /// Write a regular file with the given Unix permissions mode.
+    fn write_file(&mut self, path: &Path, data: &mut dyn Read, mode: u32) -> Result<()>;
+
+    /// Create a symbolic link to `target`, which is stored verbatim.
+    fn symlink(&mut self, path: &Path, target: &Path) -> Result<()>;
+
+    /// Create a hard link to the previously written file at `target`.
+    ///
+    /// Defaults to a relative symbolic link to `target`.
+    fn hard_link(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let mut relative = PathBuf::new();
+        if let Some(parent) = path.parent() {
+            for _ in parent.components() {
+                relative.push("..");
+            }
+        }
+        relative.push(target);
+
+        self.symlink(path, &relative)
+    }
+}
+
+/// A `FileSink` writing to a directory in the filesystem.
+pub struct DiskSink {
+    root: PathBuf,
+}
+
+impl DiskSink {
+    /// Construct an instance writing under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn prepare(&self, path: &Path) -> Result<PathBuf> {
+        ensure_no_symlinked_parents(&self.root, path)?;
+        let dest_path = self.root.join(path);
+
+        if let Some(parent) = dest_path.parent() {
+            create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+
+        // Replace files from an earlier extraction.
+        if dest_path.symlink_metadata().is_ok() && !dest_path.is_dir() {
+            fs::remove_file(&dest_path)
+                .with_context(|| format!("removing {}", dest_path.display()))?;
+        }
+
+        Ok(dest_path)
+    }
+}
+
+impl FileSink for DiskSink {
+    fn create_dir(&mut self, path: &Path) -> Result<()> {
+        ensure_no_symlinked_parents(&self.root, path)?;
+        let dest_path = self.root.join(path);
+
+        create_dir_all(&dest_path)
+            .with_context(|| format!("creating directory {}", dest_path.display()))
+    }
+
+    #[allow(unused_variables)]
+    fn write_file(&mut self, path: &Path, data: &mut dyn Read, mode: u32) -> Result<()> {
+        let dest_path = self.prepare(path)?;
+
+        let mut fh = File::create(&dest_path)
+            .with_context(|| format!("creating {}", dest_path.display()))?;
+        std::io::copy(data, &mut fh).with_context(|| format!("writing {}", dest_path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dest_path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+
+        Ok(())
+    }
+
+    fn symlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+        let dest_path = self.prepare(path)?;
+
+        #[cfg(unix)]
+        let res = std::os::unix::fs::symlink(target, &dest_path);
+        #[cfg(windows)]
+        let res = std::os::windows::fs::symlink_file(target, &dest_path);
+
+        res.with_context(|| format!("creating symlink {}", dest_path.display()))
+    }
+
+    fn hard_link(&mut self, path: &Path, target: &Path) -> Result<()> {
+        ensure_no_symlinked_parents(&self.root, target)?;
+        let dest_path = self.prepare(path)?;
+
+        fs::hard_link(self.root.join(target), &dest_path)
+            .with_context(|| format!("creating hard link {}", dest_path.display()))
+    }
+}
+
+/// Extract a distribution with a leading directory stripped from member paths.
+///
+/// `strip_prefix` defaults to `DEFAULT_STRIP_PREFIX`, so the interpreter
+/// directory becomes `dest_dir` itself. Use `PythonDistributionRecord::extract()`
+/// to strip a record's own `strip_prefix`. If the default prefix is used and
+/// the archive doesn't have it, the archive is extracted unchanged. An
+/// explicit prefix not matching archive members is an error, as is an
+/// archive with only some members under the prefix.
+///
+/// Like `extract_members()`, the archive digest is verified before anything
+/// is extracted.
+///
+/// Returns the paths of extracted members relative to `dest_dir`. Files
+/// with unexpected permissions are handled per `set_permission_policy()`,
+/// logging warnings to `logger`.
+#[allow(unused)]
+pub fn extract_distribution(
+    logger: &slog::Logger,
+    location: &PythonDistributionLocation,
+    strip_prefix: Option<&str>,
+    dest_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    create_dir_all(dest_dir)
+        .with_context(|| format!("creating directory {}", dest_dir.display()))?;
+
+    let extracted =
+        extract_distribution_into(location, strip_prefix, &mut DiskSink::new(dest_dir))?;
+
+    apply_permission_policy(logger, dest_dir)?;
+
+    Ok(extracted)
+}
+
+/// What to do about unexpected permission bits in extracted distributions.
+///
+/// Unexpected bits are setuid, setgid, and world writable. Distributions
+/// from `python-build-standalone` don't have files with them, so their
+/// presence suggests tampering. Only applies on Unix.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PermissionPolicy {
+    /// Don't check permissions.
+    Ignore,
+    /// Log a warning for each file with unexpected permissions.
+    Warn,
+    /// Clear unexpected permission bits, logging a warning for each file.
+    Strip,
+    /// Fail extraction if any file has unexpected permissions.
+    Reject,
+}
+
+/// A file with unexpected permission bits. See `PermissionPolicy`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PermissionIssue {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// The file's permission bits. e.g. `0o4755`.
+    pub mode: u32,
+    /// The unexpected permission bits of `mode`.
+    pub unexpected: u32,
+}
+
+impl fmt::Display for PermissionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = vec![];
+        if self.unexpected & 0o4000 != 0 {
+            problems.push("setuid");
+        }
+        if self.unexpected & 0o2000 != 0 {
+            problems.push("setgid");
+        }
+        if self.unexpected & 0o002 != 0 {
+            problems.push("world writable");
+        }
+
+        write!(
+            f,
+            "{} is {} (mode {:o})",
+            self.path.display(),
+            problems.join(" and "),
+            self.mode
+        )
+    }
+}
+
+/// Permission bits `PermissionPolicy` considers unexpected.
+const UNEXPECTED_PERMISSION_BITS: u32 = 0o4000 | 0o2000 | 0o002;
+
+static PERMISSION_POLICY: Lazy<RwLock<PermissionPolicy>> =
+    Lazy::new(|| RwLock::new(PermissionPolicy::Warn));
+
+/// Set the policy applied to file permissions after distributions are extracted.
+///
+/// The default is `PermissionPolicy::Warn`.
+#[allow(unused)]
+pub fn set_permission_policy(policy: PermissionPolicy) {
+    *PERMISSION_POLICY.write().unwrap() = policy;
+}
+
+/// Apply the policy set by `set_permission_policy()` to an extracted distribution.
+pub(crate) fn apply_permission_policy(logger: &slog::Logger, extracted_root: &Path) -> Result<()> {
+    let policy = *PERMISSION_POLICY.read().unwrap();
+
+    check_extracted_permissions(logger, extracted_root, policy)?;
+
+    Ok(())
+}
+
+/// Find files with unexpected permission bits in an extracted distribution and act per `policy`.
+///
+/// Symlinks aren't checked, as their permissions are meaningless. Returns the
+/// files found, before any bits are stripped. `PermissionPolicy::Reject`
+/// returns an error describing them instead.
+#[allow(unused)]
+#[allow(unused_variables)]
+pub fn check_extracted_permissions(
+    logger: &slog::Logger,
+    extracted_root: &Path,
+    policy: PermissionPolicy,
+) -> Result<Vec<PermissionIssue>> {
+    let mut issues = vec![];
+
+    if policy == PermissionPolicy::Ignore {
+        return Ok(issues);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        for entry in
+            walkdir::WalkDir::new(extracted_root).sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        {
+            let entry = entry?;
+            if entry.file_type().is_symlink() {
+                continue;
+            }
+
+            let mode = entry.metadata()?.permissions().mode() & 0o7777;
+            let unexpected = mode & UNEXPECTED_PERMISSION_BITS;
+            if unexpected == 0 {
+                continue;
+            }
+
+            let issue = PermissionIssue {
+                path: entry.path().to_path_buf(),
+                mode,
+                unexpected,
+            };
+
+            match policy {
+                PermissionPolicy::Ignore | PermissionPolicy::Reject => {}
+                PermissionPolicy::Warn => warn!(logger, "{}", issue),
+                PermissionPolicy::Strip => {
+                    warn!(logger, "{}; clearing unexpected permissions", issue);
+                    fs::set_permissions(
+                        entry.path(),
+                        fs::Permissions::from_mode(mode & !UNEXPECTED_PERMISSION_BITS),
+                    )?;
+                }
+            }
+
+            issues.push(issue);
+        }
+    }
+
+    if policy == PermissionPolicy::Reject && !issues.is_empty() {
+        return Err(anyhow!(
+            "extracted distribution has files with unexpected permissions: {}",
+            issues
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
+        ));
+    }
+
+    Ok(issues)
+}
+
+/// Extract a distribution into a `FileSink`.
+///
+/// Like `extract_distribution()`, but files are written to `sink` instead of
+/// a directory. The archive digest is verified before anything is written
+/// to `sink`.
+#[allow(unused)]
+pub fn extract_distribution_into(
+    location: &PythonDistributionLocation,
+    strip_prefix: Option<&str>,
+    sink: &mut impl FileSink,
+) -> Result<Vec<PathBuf>> {
+    let prefix = Path::new(strip_prefix.unwrap_or(DEFAULT_STRIP_PREFIX));
+
+    let _permit = acquire_extraction_permit();
+    let reader = open_verified_location_stream(location)?;
+    let mut archive = tar::Archive::new(ArchiveDecoder::new(reader)?);
+
+    // Whether members are stripped. Determined by the first member.
+    let mut stripping = None;
+    let mut extracted = vec![];
+
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        let archive_path = entry.path()?.to_path_buf();
+
+        let member_path = archive_member_path(&archive_path)?;
+
+        // The archive root itself, as archived by e.g. `tar -cf x.tar .`.
+        if member_path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let has_prefix = member_path.starts_with(prefix);
+
+        let strip = match stripping {
+            Some(strip) => strip,
+            None if has_prefix => *stripping.insert(true),
+            None if strip_prefix.is_none() => *stripping.insert(false),
+            None => {
+                return Err(anyhow!(
+                    "archive member {} does not begin with prefix {}",
+                    archive_path.display(),
+                    prefix.display()
+                ));
+            }
+        };
+
+        let relative_path = |archive_path: &Path| -> Result<PathBuf> {
+            let path = archive_member_path(archive_path)?;
+
+            if strip {
+                Ok(path
+                    .strip_prefix(prefix)
+                    .map_err(|_| {
+                        anyhow!(
+                            "archive member {} does not begin with prefix {}",
+                            archive_path.display(),
+                            prefix.display()
+                        )
+                    })?
+                    .to_path_buf())
+            } else {
+                Ok(path)
+            }
+        };
+
+        let path = relative_path(&archive_path)?;
+
+        // The prefix directory itself.
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let entry_type = entry.header().entry_type();
+        let link_name = || -> Result<PathBuf> {
+            Ok(entry
+                .link_name()?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "archive member {} has no link target",
+                        archive_path.display()
+                    )
+                })?
+                .to_path_buf())
+        };
+
+        match entry_type {
+            tar::EntryType::Directory => sink.create_dir(&path),
+            tar::EntryType::Symlink => sink.symlink(&path, &link_name()?),
+            tar::EntryType::Link => {
+                let target = relative_path(&link_name()?)?;
+                sink.hard_link(&path, &target)
+            }
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let mode = entry.header().mode()?;
+                sink.write_file(&path, &mut entry, mode)
+            }
+            _ => Err(anyhow!("unsupported archive member type {:?}", entry_type)),
+        }
+        .with_context(|| format!("extracting {}", archive_path.display()))?;
+
+        extracted.push(path);
+    }
+
+    Ok(extracted)
+}
+
+/// Options for `repackage()`.
+#[derive(Clone, Debug)]
+pub struct RepackageOptions {
+    /// Directory to place archive members under. e.g. `python/`.
+    ///
+    /// Defaults to `DEFAULT_STRIP_PREFIX`, matching `python-build-standalone` archives.
+    pub prefix: String,
+
+    /// Modification time recorded for every archive member, in seconds since the Unix epoch.
+    pub mtime: u64,
+
+    /// zstd compression level.
+    pub compression_level: i32,
+}
+
+impl Default for RepackageOptions {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_STRIP_PREFIX.to_string(),
+            mtime: 0,
+            compression_level: 19,
+        }
+    }
+}
+
+/// Write an extracted distribution as a reproducible zstd compressed tar archive.
+///
+/// Archives are byte-for-byte reproducible for a given tree and `options`:
+/// members are sorted by path, modification times are `options.mtime`, owners
+/// are `0`, and permissions are `0755` for directories and executable files
+/// and `0644` for other files. Symlinks are preserved. Hard links are stored
+/// as regular files.
+///
+/// The written archive can be extracted with `extract_distribution()`.
+/// Returns the hex SHA-256 of the archive.
+#[allow(unused)]
+pub fn repackage(extracted_root: &Path, dest: &Path, options: RepackageOptions) -> Result<String> {
+    let mut builder = tar::Builder::new(vec![]);
+    builder.follow_symlinks(false);
+
+    let prefix = Path::new(&options.prefix);
+
+    for entry in walkdir::WalkDir::new(extracted_root)
+        .min_depth(1)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(extracted_root)?;
+        let archive_path = prefix.join(relative);
+        let metadata = entry.path().symlink_metadata()?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(options.mtime);
+        header.set_uid(0);
+        header.set_gid(0);
+
+        if metadata.file_type().is_symlink() {
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_mode(0o777);
+            header.set_size(0);
+            header.set_link_name(fs::read_link(entry.path())?)?;
+            builder.append_data(&mut header, &archive_path, std::io::empty())?;
+        } else if metadata.is_dir() {
+            header.set_entry_type(tar::EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            builder.append_data(&mut header, &archive_path, std::io::empty())?;
+        } else {
+            #[cfg(unix)]
+            let executable = {
+                use std::os::unix::fs::PermissionsExt;
+                metadata.permissions().mode() & 0o111 != 0
+            };
+            #[cfg(windows)]
+            let executable = relative
+                .extension()
+                .map(|ext| ext == "exe" || ext == "dll")
+                .unwrap_or(false);
+
+            header.set_mode(if executable { 0o755 } else { 0o644 });
+            header.set_size(metadata.len());
+            builder.append_data(
+                &mut header,
+                &archive_path,
+                File::open(entry.path())
+                    .with_context(|| format!("opening {}", entry.path().display()))?,
+            )?;
+        }
+    }
+
+    let data = zstd::stream::encode_all(
+        std::io::Cursor::new(builder.into_inner()?),
+        options.compression_level,
+    )?;
+    fs::write(dest, &data).with_context(|| format!("writing {}", dest.display()))?;
+
+    Ok(hex::encode(Sha256::digest(&data)))
+}
+
+/// Resolve the path of the Python interpreter executable in an extracted distribution.
+///
+/// `extracted_root` is the root of the distribution. This is the destination
+/// directory of `extract_distribution()` when the default prefix is stripped,
+/// or the `python` directory within it otherwise. The path is derived from the
+/// record's layout and target triple. Once extracted, the distribution's
+/// `PYTHON.json` is authoritative.
+#[allow(unused)]
+pub fn interpreter_path(extracted_root: &Path, record: &PythonDistributionRecord) -> PathBuf {
+    let install = extracted_root.join(record.layout.install_dir());
+
+    if record.target_triple.contains("-windows-") {
+        install.join("python.exe")
+    } else {
+        install.join("bin").join("python3")
+    }
+}
+
+/// How `clone_extracted()` cloned files.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum CloneMethod {
+    /// Copy-on-write clones sharing storage until modified.
+    Reflink,
+    /// Hard links to the shared files.
+    HardLink,
+    /// Full copies.
+    Copy,
+}
+
+/// Clone `source` to a new file at `dest` sharing storage copy-on-write.
+#[cfg(target_os = "linux")]
+fn reflink_file(source: &Path, dest: &Path) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    /// `_IOW(0x94, 9, int)` from `linux/fs.h`.
+    const FICLONE: u32 = 0x4004_9409;
+
+    let source_fh = File::open(source)?;
+    let dest_fh = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+
+    // SAFETY: both descriptors are open for the duration of the call.
+    let res = unsafe { libc::ioctl(dest_fh.as_raw_fd(), FICLONE as _, source_fh.as_raw_fd()) };
+
+    if res == 0 {
+        Ok(())
+    } else {
+        let err = std::io::Error::last_os_error();
+        drop(dest_fh);
+        fs::remove_file(dest).ok();
+        Err(err)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink_file(_source: &Path, _dest: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "reflinks are not supported on this platform",
+    ))
+}
+
+/// Give a build its own copy of a distribution extracted to a shared directory.
+///
+/// This allows extracting a distribution once into a shared, possibly
+/// read-only, directory used by many builds. The directory tree under
+/// `shared_root` is recreated at `dest`, which must not exist. Files are
+/// cloned copy-on-write where the filesystem supports it (currently Linux
+/// filesystems supporting `FICLONE`, e.g. Btrfs and XFS). Otherwise they
+/// are hard linked, or copied if `dest` is on another filesystem. Symlinks
+/// are recreated.
+///
+/// Cloned and copied files are writable. Hard linked files share content
+/// with `shared_root`, so builds must replace rather than modify them. The
+/// shared directory being read-only enforces this.
+///
+/// Returns the least efficient method used for any file.
+#[allow(unused)]
+pub fn clone_extracted(shared_root: &Path, dest: &Path) -> Result<CloneMethod> {
+    if dest.exists() {
+        return Err(anyhow!("{} already exists", dest.display()));
+    }
+
+    // Once a method fails, it will likely fail for every other file too.
+    let mut method = CloneMethod::Reflink;
+
+    for entry in walkdir::WalkDir::new(shared_root).sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(shared_root)?);
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            create_dir_all(&target)
+                .with_context(|| format!("creating directory {}", target.display()))?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(entry.path())?;
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, &target)
+                .with_context(|| format!("creating symlink {}", target.display()))?;
+            #[cfg(windows)]
+            {
+                let _ = link;
+                fs::copy(entry.path(), &target)
+                    .with_context(|| format!("copying {}", entry.path().display()))?;
+            }
+        } else {
+            if method == CloneMethod::Reflink && reflink_file(entry.path(), &target).is_err() {
+                method = CloneMethod::HardLink;
+            }
+            if method == CloneMethod::HardLink && fs::hard_link(entry.path(), &target).is_err() {
+                method = CloneMethod::Copy;
+            }
+            if method == CloneMethod::Copy {
+                fs::copy(entry.path(), &target)
+                    .with_context(|| format!("copying {}", entry.path().display()))?;
+            }
+
+            if method != CloneMethod::HardLink {
+                let mut permissions = entry.metadata()?.permissions();
+
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    permissions.set_mode(permissions.mode() | 0o200);
+                }
+                #[cfg(windows)]
+                permissions.set_readonly(false);
+
+                fs::set_permissions(&target, permissions)?;
+            }
+        }
+    }
+
+    Ok(method)
+}
+
+/// Paths in an extracted distribution and whether each is a directory.
+pub type ExtractedLayout = BTreeSet<(PathBuf, bool)>;
+
+/// Describe the layout of an extracted distribution.
+///
+/// The layout is the set of paths relative to `extracted_root`, up to
+/// `max_depth` components deep, and whether each is a directory.
+#[allow(unused)]
+pub fn extracted_layout(extracted_root: &Path, max_depth: usize) -> Result<ExtractedLayout> {
+    let mut layout = ExtractedLayout::new();
+
+    for entry in walkdir::WalkDir::new(extracted_root)
+        .min_depth(1)
+        .max_depth(max_depth)
+    {
+        let entry = entry?;
+        let path = entry.path().strip_prefix(extracted_root)?.to_path_buf();
+
+        layout.insert((path, entry.file_type().is_dir()));
+    }
+
+    Ok(layout)
+}
+
+/// Group extracted distributions into classes having identical layouts.
+///
+/// `extracted` pairs records with the directories they were extracted to, as
+/// passed to `interpreter_path()`. Layouts are compared per `extracted_layout()`.
+/// A `max_depth` of `2` compares the `install/*` layout, ignoring versioned
+/// paths like `install/lib/python3.9`. Records in the same class can rely on
+/// the same layout assumptions.
+///
+/// Classes are ordered by their first record in `extracted`.
+#[allow(unused)]
+pub fn layout_equivalence_classes<'a>(
+    extracted: &[(&'a PythonDistributionRecord, &Path)],
+    max_depth: usize,
+) -> Result<Vec<Vec<&'a PythonDistributionRecord>>> {
+    let mut classes: Vec<(ExtractedLayout, Vec<&'a PythonDistributionRecord>)> = vec![];
+
+    for (record, root) in extracted {
+        let layout = extracted_layout(root, max_depth)?;
+
+        match classes.iter_mut().find(|(existing, _)| existing == &layout) {
+            Some((_, records)) => records.push(record),
+            None => classes.push((layout, vec![record])),
+        }
+    }
+
+    Ok(classes.into_iter().map(|(_, records)| records).collect())
+}
+
+/// Describes the flavor of a distribution.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistributionFlavor {
+    /// Distributions coming from the `python-build-standalone` project.
+    Standalone,
+
+    /// Statically linked distributions coming from the `python-build-standalone` project.
+    StandaloneStatic,
+
+    /// Dynamically linked distributions coming from the `python-build-standalone` project.
+    StandaloneDynamic,
+}
+
+/// A set of `DistributionFlavor`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FlavorSet(u8);
+
+impl FlavorSet {
+    fn bit(flavor: &DistributionFlavor) -> u8 {
+        match flavor {
+            DistributionFlavor::Standalone => 1,
+            DistributionFlavor::StandaloneStatic => 2,
+            DistributionFlavor::StandaloneDynamic => 4,
+        }
+    }
+
+    /// Add a flavor to the set.
+    pub fn insert(&mut self, flavor: &DistributionFlavor) {
+        self.0 |= Self::bit(flavor);
+    }
+
+    /// Whether the set contains a flavor.
+    pub fn contains(&self, flavor: &DistributionFlavor) -> bool {
+        self.0 & Self::bit(flavor) != 0
+    }
+
+    /// Obtain the flavors in the set.
+    #[allow(unused)]
+    pub fn iter(&self) -> impl Iterator<Item = DistributionFlavor> {
+        let set = *self;
+
+        vec![
+            DistributionFlavor::Standalone,
+            DistributionFlavor::StandaloneStatic,
+            DistributionFlavor::StandaloneDynamic,
+        ]
+        .into_iter()
+        .filter(move |flavor| set.contains(flavor))
+    }
+}
+
+impl Default for DistributionFlavor {
+    fn default() -> Self {
+        DistributionFlavor::Standalone
+    }
+}
+
+impl TryFrom<&str> for DistributionFlavor {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "standalone" => Ok(Self::Standalone),
+            "standalone_static" | "standalone-static" => Ok(Self::StandaloneStatic),
+            "standalone_dynamic" | "standalone-dynamic" => Ok(Self::StandaloneDynamic),
+            _ => Err(format!(
+                "distribution flavor {} not recognized; valid values: standalone, standalone_static, standalone_dynamic",
+                value
+            )),
+        }
+    }
+}
+
+impl FromStr for DistributionFlavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+impl fmt::Display for DistributionFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Standalone => "standalone",
+            Self::StandaloneStatic => "standalone_static",
+            Self::StandaloneDynamic => "standalone_dynamic",
+        })
+    }
+}
+
+type DistributionCacheKey = (PathBuf, PythonDistributionLocation);
+type DistributionCacheValue = Arc<Mutex<Option<Arc<StandaloneDistribution>>>>;
+
+/// A function called with the root of a freshly extracted distribution.
+pub type ExtractedHook = Arc<dyn Fn(&Path) -> Result<()> + Send + Sync>;
+
+/// Holds references to resolved PythonDistribution instances.
+pub struct DistributionCache {
+    cache: Mutex<HashMap<DistributionCacheKey, DistributionCacheValue>>,
+    default_dest_dir: Option<PathBuf>,
+    extracted_hooks: Vec<ExtractedHook>,
+}
+
+impl fmt::Debug for DistributionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DistributionCache")
+            .field("cache", &self.cache)
+            .field("default_dest_dir", &self.default_dest_dir)
+            .field("extracted_hooks", &self.extracted_hooks.len())
+            .finish()
+    }
+}
+
+impl DistributionCache {
+    pub fn new(default_dest_dir: Option<&Path>) -> Self {
+        Self {
+            cache: Mutex::new(HashMap::new()),
+            default_dest_dir: default_dest_dir.map(|x| x.to_path_buf()),
+            extracted_hooks: vec![],
+        }
+    }
+
+    /// Register a function to call on distributions after they are extracted.
+    ///
+    /// `f` receives the root of the extracted tree before the extraction is
+    /// considered complete, so changes it makes, e.g. patching a shebang or
+    /// adding a `sitecustomize.py`, are in place before the distribution is
+    /// used. An error from `f` aborts resolution. Functions are called in
+    /// registration order and only when a distribution is freshly extracted,
+    /// not when an existing extraction is reused. See
+    /// `StandaloneDistribution::from_tar_with_hooks()`.
+    #[allow(unused)]
+    pub fn on_extracted(&mut self, f: impl Fn(&Path) -> Result<()> + Send + Sync + 'static) {
+        self.extracted_hooks.push(Arc::new(f));
+    }
+
+    /// Resolve a `PythonDistribution` given its source and storage locations.
+    pub fn resolve_distribution(
+        &self,
+        logger: &slog::Logger,
+        location: &PythonDistributionLocation,
+        dest_dir: Option<&Path>,
+    ) -> Result<Arc<StandaloneDistribution>> {
+        let dest_dir = if let Some(p) = dest_dir {
+            p
+        } else if let Some(p) = &self.default_dest_dir {
+            p
+        } else {
+            return Err(anyhow!("no destination directory available"));
+        };
+
+        let key = (dest_dir.to_path_buf(), location.clone());
+
+        // This logic is whack. Surely there's a cleaner way to do this...
+        //
+        // The general problem is instances of this type are Send + Sync. And
+        // we do rely on multiple threads simultaneously accessing it. This
+        // occurs in tests for example, which use a global/static instance to
+        // cache resolved distributions to drastically reduce CPU overhead.
+        //
+        // We need a Mutex of some kind around the HashMap to allow
+        // multi-threaded access. But if that was the only Mutex that existed, we'd
+        // need to hold the Mutex while any thread was resolving a distribution
+        // and this would prevent multi-threaded distribution resolving.
+        //
+        // Or we could release that Mutex after a missing lookup and then each
+        // thread would race to resolve the distribution and insert. That's fine,
+        // but it results in redundancy and wasted CPU (several minutes worth for
+        // debug builds in the test harness).
+        //
+        // What we do instead is have HashMap values be Arc<Mutex<Option<T>>>.
+        // We then perform a 2 phase lookup.
+        //
+        // In the 1st lock, we lock the entire HashMap and do the key lookup.
+        // If it exists, we clone the Arc<T>. Else if it is missing, we insert
+        // a new key with `None` and return a clone of its Arc<T>. Either way,
+        // we have a handle on the Arc<Mutex<Option<T>>> in a populated. We then
+        // release the outer HashMap lock.
+        //
+        // We then lock the inner entry. With that lock hold, we return a clone
+        // of its `Some(T)` entry immediately or proceed to populate it. Only 1
+        // thread can hold this lock, ensuring only 1 thread performs the
+        // value resolution. Multiple threads can resolve different keys in
+        // parallel. By other threads will be blocked resolving a single key.
+
+        let entry = {
+            let mut lock = self
+                .cache
+                .lock()
+                .map_err(|e| anyhow!("cannot obtain distribution cache lock: {}", e))?;
+
+            if let Some(value) = lock.get(&key) {
+                value.clone()
+            } else {
+                let value = Arc::new(Mutex::new(None));
+                lock.insert(key.clone(), value.clone());
+
+                value
+            }
+        };
+
+        let mut lock = entry
+            .lock()
+            .map_err(|e| anyhow!("cannot obtain distribution lock: {}", e))?;
+
+        let value = lock.deref_mut();
+
+        if let Some(dist) = value {
+            Ok(dist.clone())
+        } else {
+            let dist = Arc::new(StandaloneDistribution::from_location_with_hooks(
+                logger,
+                location,
+                dest_dir,
+                &self.extracted_hooks,
+            )?);
+
+            lock.replace(dist.clone());
+
+            Ok(dist)
+        }
+    }
+}
+
+/// Obtain a `PythonDistribution` implementation of a flavor and from a location.
+///
+/// The distribution will be written to `dest_dir`.
+#[allow(unused)]
+pub fn resolve_distribution(
+    logger: &slog::Logger,
+    location: &PythonDistributionLocation,
+    dest_dir: &Path,
+) -> Result<Box<dyn PythonDistribution>> {
+    Ok(Box::new(StandaloneDistribution::from_location(
+        logger, location, dest_dir,
+    )?) as Box<dyn PythonDistribution>)
+}
+
+/// Obtain a `PythonDistribution` like `resolve_distribution()`, stopping once `token` is cancelled.
+///
+/// Cancellation is noticed during downloads and extraction and returns
+/// `DistributionError::Cancelled`. See `with_cancellation()` for what is left
+/// behind. Intended for use by orchestrators which may abort a build step,
+/// e.g. on Ctrl-C or a CI timeout.
+#[allow(unused)]
+pub fn resolve_distribution_cancelable(
+    logger: &slog::Logger,
+    location: &PythonDistributionLocation,
+    dest_dir: &Path,
+    token: &CancellationToken,
+) -> Result<Box<dyn PythonDistribution>> {
+    with_cancellation(token, || resolve_distribution(logger, location, dest_dir))
+}
+
+/// Resolve the location of the default Python distribution of a given flavor and build target.
+///
+/// Warnings about the selection are logged to `logger`.
+pub fn default_distribution_location(
+    logger: &slog::Logger,
+    flavor: &DistributionFlavor,
+    target: &str,
+    python_major_minor_version: Option<&str>,
+) -> Result<PythonDistributionLocation> {
+    let dist = PYTHON_DISTRIBUTIONS
+        .find_distribution_logged(
+            logger,
+            target,
+            flavor,
+            python_major_minor_version,
+            &SelectionPolicy::default(),
+        )
+        .map_err(|_| anyhow!("could not find default Python distribution for {}", target))?;
+
+    Ok(dist.location)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::testutil::*};
+
+    /// Write a zstd compressed tar archive with the given members to `path`.
+    ///
+    /// Returns the hex encoded SHA-256 of the archive.
+    fn write_test_archive(path: &Path, members: &[(&str, &[u8])]) -> Result<String> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data)?;
+        }
+
+        let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+        fs::write(path, &data)?;
+
+        Ok(hex::encode(Sha256::digest(&data)))
+    }
+
+    #[test]
+    fn test_concurrency_limit() {
+        let limit = Arc::new(ConcurrencyLimit::new(2));
+        let running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let peak = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let threads = (0..6)
+            .map(|_| {
+                let limit = limit.clone();
+                let running = running.clone();
+                let peak = peak.clone();
+
+                std::thread::spawn(move || {
+                    let _permit = limit.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(*limit.state.lock().unwrap(), (2, 0));
+
+        // Raising the limit wakes waiters.
+        limit.set_limit(1);
+        let held = limit.acquire();
+        let waiter = {
+            let limit = limit.clone();
+            std::thread::spawn(move || drop(limit.acquire()))
+        };
+        limit.set_limit(2);
+        waiter.join().unwrap();
+        drop(held);
+
+        assert!(set_max_concurrency(0).is_err());
+    }
+
+    #[test]
+    fn test_distribution_extension_modules() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let path = td.path().join("dist.tar.zst");
+        let sha256 = write_test_archive(
+            &path,
+            &[
+                ("python/install/lib/os.py", b""),
+                (
+                    "python/PYTHON.json",
+                    br#"{"build_info": {"extensions": {"_ssl": [], "_ctypes": []}}}"#,
+                ),
+            ],
+        )?;
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: sha256.clone(),
+        };
+
+        let modules = distribution_extension_modules(&location)?;
+        assert_eq!(
+            modules.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+            vec!["_ctypes", "_ssl"]
+        );
+
+        // Results are cached by digest, so the archive isn't read again.
+        fs::remove_file(&path)?;
+        assert_eq!(distribution_extension_modules(&location)?, modules);
+
+        let bad = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: "00".repeat(32),
+        };
+        write_test_archive(&path, &[("python/PYTHON.json", b"{}")])?;
+        assert!(distribution_extension_modules(&bad).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_standalone_distributions() -> Result<()> {
+        assert!(!get_all_standalone_distributions()?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_agent() {
+        assert_eq!(user_agent(), format!("pyoxidizer/{}", PYOXIDIZER_VERSION));
+    }
+
+    #[test]
+    fn test_set_proxy_invalid() {
+        assert!(set_proxy(Some("not a url")).is_err());
+        assert!(PROXY.read().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_revalidate_cached_download() -> Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let cache_path = td.path().join("dist.tar.zst");
+        fs::write(&cache_path, b"archive")?;
+        fs::write(etag_path(&cache_path), "\"v1\"")?;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = Url::parse(&format!("http://{}/dist.tar.zst", listener.local_addr()?))?;
+
+        // Respond 304 if the expected ETag is sent, else a new ETag.
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut conditional = false;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if line.to_lowercase().starts_with("if-none-match:") && line.contains("\"v1\"")
+                    {
+                        conditional = true;
+                    }
+                }
+
+                let response = if conditional {
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nETag: \"v2\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        revalidate_cached_download(&url, &cache_path)?;
+
+        fs::write(etag_path(&cache_path), "\"v0\"")?;
+        assert!(revalidate_cached_download(&url, &cache_path).is_err());
+
+        server.join().unwrap();
+
+        // No recorded ETag means nothing to revalidate.
+        fs::remove_file(etag_path(&cache_path))?;
+        revalidate_cached_download(&url, &cache_path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parts_location() -> Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let archive = td.path().join("dist.tar.zst");
+        let sha256 = write_test_archive(&archive, &[("python/PYTHON.json", b"{}")])?;
+        let data = fs::read(&archive)?;
+        let (first, second) = data.split_at(data.len() / 2);
+        let parts = [
+            ("/dist.tar.zst.part1".to_string(), first.to_vec()),
+            ("/dist.tar.zst.part2".to_string(), second.to_vec()),
+        ];
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let base = format!("http://{}", listener.local_addr()?);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                match parts.iter().find(|(part, _)| part == path) {
+                    Some((_, body)) => {
+                        let header = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        );
+                        stream.write_all(header.as_bytes()).ok();
+                        if request.starts_with("GET") {
+                            stream.write_all(body).ok();
+                        }
+                    }
+                    None => {
+                        stream
+                            .write_all(
+                                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                            )
+                            .ok();
+                    }
+                }
+            }
+        });
+
+        let location = PythonDistributionLocation::Parts {
+            urls: vec![
+                format!("{}/dist.tar.zst.part1", base),
+                format!("{}/dist.tar.zst.part2", base),
+            ],
+            sha256: sha256.clone(),
+        };
+        assert_eq!(location.filename(), "dist.tar.zst");
+        check_location_availability(&location)?;
+
+        let cache_dir = td.path().join("cache");
+        let path = resolve_python_distribution_archive(&get_logger()?, &location, &cache_dir)?;
+        assert_eq!(path, cache_dir.join("dist.tar.zst"));
+        assert_eq!(fs::read(&path)?, data);
+
+        let missing = PythonDistributionLocation::Parts {
+            urls: vec![
+                format!("{}/dist.tar.zst.part1", base),
+                format!("{}/dist.tar.zst.part3", base),
+            ],
+            sha256,
+        };
+        assert!(check_location_availability(&missing)
+            .unwrap_err()
+            .to_string()
+            .contains("part 2 of 2"));
+        let err = verify_location(&missing).unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("NETWORK"));
+        assert!(err.to_string().contains("part 2 of 2"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fastest_url() -> Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        // Serves HEAD requests with a status after a delay.
+        let serve = |status: &'static str, delay: Duration| -> Result<Url> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+
+                    std::thread::sleep(delay);
+                    let response = format!(
+                        "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    );
+                    stream.write_all(response.as_bytes()).ok();
+                }
+            });
+
+            Ok(url)
+        };
+
+        let slow = serve("200 OK", Duration::from_millis(500))?.join("dists/dist.tar.zst")?;
+        let fast = serve("200 OK", Duration::from_millis(0))?.join("mirror/")?;
+        let broken = serve("404 Not Found", Duration::from_millis(0))?;
+
+        assert_eq!(
+            fastest_url(&slow, &[broken.clone(), fast.clone()], MIRROR_PROBE_TIMEOUT),
+            fast.join("dists/dist.tar.zst")?
+        );
+        assert_eq!(
+            fastest_url(&slow, std::slice::from_ref(&broken), MIRROR_PROBE_TIMEOUT),
+            slow
+        );
+        assert_eq!(
+            fastest_url(&slow, &[broken], Duration::from_millis(100)),
+            slow
+        );
+        assert_eq!(fastest_url(&slow, &[], MIRROR_PROBE_TIMEOUT), slow);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_mirrors_file() -> Result<()> {
+        let toml_path = Path::new("mirrors.toml");
+        let json_path = Path::new("mirrors.json");
+
+        assert_eq!(
+            parse_mirrors_file(
+                toml_path,
+                "mirrors = [\n  \"https://b.example.com/python\",\n  'http://a.example.com',\n]\n"
+            )?,
+            vec![
+                Url::parse("https://b.example.com/python")?,
+                Url::parse("http://a.example.com")?
+            ]
+        );
+        assert_eq!(
+            parse_mirrors_file(json_path, r#"{"mirrors": ["https://a.example.com/"]}"#)?,
+            vec![Url::parse("https://a.example.com/")?]
+        );
+        assert!(parse_mirrors_file(toml_path, "mirrors = []")?.is_empty());
+
+        let err =
+            |path: &Path, content: &str| parse_mirrors_file(path, content).unwrap_err().to_string();
+
+        assert_eq!(
+            err(
+                toml_path,
+                "mirrors = [\n  \"https://a.example.com\",\n  \"ftp://b.example.com\",\n]"
+            ),
+            "mirrors.toml:3: mirror ftp://b.example.com must be an http or https URL"
+        );
+        assert_eq!(
+            err(
+                json_path,
+                "{\"mirrors\": [\n\"https://a.example.com\",\n\"https://a.example.com\"]}"
+            ),
+            "mirrors.json:2: mirror https://a.example.com is listed twice"
+        );
+        assert!(err(toml_path, "mirrors = [\"not a url\"]")
+            .starts_with("mirrors.toml:1: invalid mirror not a url: "));
+        assert_eq!(
+            err(toml_path, "mirrors = [\"https://a.example.com/?x=1\"]"),
+            "mirrors.toml:1: mirror https://a.example.com/?x=1 must not have a query or fragment"
+        );
+        assert!(err(toml_path, "\n\nmirrors = [1]").starts_with("mirrors.toml:3: "));
+        assert!(err(toml_path, "mirror = []").starts_with("mirrors.toml"));
+        assert!(err(json_path, "{\n\"mirrors\": {}}").starts_with("mirrors.json:2: "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_integrity_map() -> Result<()> {
+        // The integrity map is global. The map installed here only has
+        // entries for this test's URL, and the lock keeps other tests that
+        // install maps from running concurrently.
+        let _lock = crate::testutil::lock_global_settings();
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let basename = "integrity.tar.zst";
+        let sha256 =
+            write_test_archive(&td.path().join(basename), &[("python/PYTHON.json", b"{}")])?;
+        // The archive is cached, so nothing is downloaded.
+        let url = "https://mirror.example.com/integrity/integrity.tar.zst";
+
+        let logger = get_logger()?;
+        let download =
+            || download_distribution_from(&logger, url, &sha256, td.path(), basename, &[]);
+
+        let mut map = HashMap::new();
+        map.insert(url.to_string(), sha256.to_uppercase());
+        set_integrity_map(&map)?;
+        assert_eq!(download()?, td.path().join(basename));
+
+        map.insert(url.to_string(), "ab".repeat(32));
+        set_integrity_map(&map)?;
+        let err = download().unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("INTEGRITY"));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "sha256 {} of Python distribution {} matches its record but not the integrity map, which expects {} for {}",
+                sha256,
+                basename,
+                "ab".repeat(32),
+                url
+            )
+        );
+
+        // The record's digest is checked first.
+        let err =
+            download_distribution_from(&logger, url, &"cd".repeat(32), td.path(), basename, &[])
+                .unwrap_err();
+        assert_ne!(DistributionError::code_of(&err), Some("INTEGRITY"));
+
+        // An invalid map leaves the installed one in place.
+        map.insert(url.to_string(), "not hex".to_string());
+        assert!(set_integrity_map(&map).is_err());
+        assert!(download().is_err());
+
+        let map_path = td.path().join("integrity.json");
+        fs::write(
+            &map_path,
+            format!(
+                "{{\n  \"{}\": \"{}\",\n  \"{}\": \"bogus\"\n}}",
+                url, sha256, "https://b.example.com"
+            ),
+        )?;
+        assert_eq!(
+            load_integrity_map(&map_path).unwrap_err().to_string(),
+            format!(
+                "{}:3: invalid sha256 bogus for https://b.example.com",
+                map_path.display()
+            )
+        );
+        fs::write(&map_path, "{\n  \"https://b.example.com\": 1\n}")?;
+        assert!(load_integrity_map(&map_path)
+            .unwrap_err()
+            .to_string()
+            .starts_with(&format!("{}:2: ", map_path.display())));
+
+        fs::write(&map_path, format!("{{\"{}\": \"{}\"}}", url, sha256))?;
+        load_integrity_map(&map_path)?;
+        assert!(download().is_ok());
+
+        // Cached OCI blobs are checked without querying the registry.
+        let oci = PythonDistributionLocation::Oci {
+            reference: "registry.example.com/integrity/cpython:3.9".to_string(),
+            digest: sha256.clone(),
+        };
+        let blob_url = format!(
+            "https://registry.example.com/v2/integrity/cpython/blobs/sha256:{}",
+            sha256
+        );
+        fs::copy(
+            td.path().join(basename),
+            td.path().join(oci.filename().as_ref()),
+        )?;
+
+        map.clear();
+        map.insert(blob_url.clone(), sha256.clone());
+        set_integrity_map(&map)?;
+        assert_eq!(
+            resolve_python_distribution_archive(&logger, &oci, td.path())?,
+            td.path().join(oci.filename().as_ref())
+        );
+
+        map.insert(blob_url, "ab".repeat(32));
+        set_integrity_map(&map)?;
+        let err = resolve_python_distribution_archive(&logger, &oci, td.path()).unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("INTEGRITY"));
+
+        set_integrity_map(&HashMap::new())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mirror_fallback() -> Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        // Serves a body for every request.
+        let serve = |body: Vec<u8>| -> Result<Url> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                    }
+
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    stream.write_all(header.as_bytes()).ok();
+                    if request.starts_with("GET") {
+                        stream.write_all(&body).ok();
+                    }
+                }
+            });
+
+            Ok(url)
+        };
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let archive = td.path().join("dist.tar.zst");
+        let sha256 = write_test_archive(&archive, &[("python/PYTHON.json", b"{}")])?;
+        let data = fs::read(&archive)?;
+
+        let stale = serve(b"stale".to_vec())?.join("dist.tar.zst")?;
+        let good = serve(data.clone())?;
+
+        let cache_dir = td.path().join("cache");
+        fs::create_dir(&cache_dir)?;
+
+        let logger = get_logger()?;
+
+        let path = download_distribution_from(
+            &logger,
+            stale.as_str(),
+            &sha256,
+            &cache_dir,
+            "dist.tar.zst",
+            std::slice::from_ref(&good),
+        )?;
+        assert_eq!(fs::read(&path)?, data);
+
+        // Without alternatives the mismatch is reported as is.
+        fs::remove_file(&path)?;
+        let err = download_distribution_from(
+            &logger,
+            stale.as_str(),
+            &sha256,
+            &cache_dir,
+            "dist.tar.zst",
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("CHECKSUM"));
+
+        let also_stale = serve(b"also stale".to_vec())?;
+        let err = download_distribution_from(
+            &logger,
+            stale.as_str(),
+            &sha256,
+            &cache_dir,
+            "dist.tar.zst",
+            std::slice::from_ref(&also_stale),
+        )
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("MIRRORS_EXHAUSTED"));
+        let message = err.to_string();
+        assert!(message.contains(stale.as_str()));
+        assert!(message.contains(also_stale.join("dist.tar.zst")?.as_str()));
+        assert!(!cache_dir.join("dist.tar.zst").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_abi_compatibility() {
+        let mut record = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9"),
+            )
+            .unwrap();
+
+        assert_eq!(record.python_abi_tag(), "cp39");
+        assert!(record.is_abi_compatible_with("cp39"));
+        assert!(record.is_abi_compatible_with("abi3"));
+        assert!(record.is_abi_compatible_with("none"));
+        assert!(record.is_abi_compatible_with("cp38.cp39"));
+        assert!(!record.is_abi_compatible_with("cp38"));
+        assert!(!record.is_abi_compatible_with("cp39d"));
+
+        record.optimizations = Some(BuildOptimizations::Debug);
+        assert_eq!(record.python_abi_tag(), "cp39d");
+        assert!(record.is_abi_compatible_with("cp39d"));
+        assert!(!record.is_abi_compatible_with("abi3"));
+    }
+
+    #[test]
+    fn test_interpreter_path() {
+        let root = Path::new("dist");
+
+        for dist in PYTHON_DISTRIBUTIONS.iter() {
+            let expected = match dist.target_triple.as_str() {
+                "i686-pc-windows-msvc" | "x86_64-pc-windows-msvc" => {
+                    root.join("install").join("python.exe")
+                }
+                "x86_64-unknown-linux-gnu"
+                | "x86_64-unknown-linux-musl"
+                | "aarch64-apple-darwin"
+                | "x86_64-apple-darwin" => root.join("install").join("bin").join("python3"),
+                triple => panic!("unhandled target triple {}", triple),
+            };
+
+            assert_eq!(interpreter_path(root, dist), expected);
+        }
+
+        let mut flat = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+        flat.layout = DistributionLayout::Flat;
+        assert_eq!(
+            interpreter_path(root, &flat),
+            root.join("bin").join("python3")
+        );
+    }
+
+    #[test]
+    fn test_distribution_layout() {
+        assert_eq!(
+            DistributionLayout::from_python_json_path("install/bin/python3.9"),
+            DistributionLayout::Install
+        );
+        assert_eq!(
+            DistributionLayout::from_python_json_path("install\\python.exe"),
+            if cfg!(windows) {
+                DistributionLayout::Install
+            } else {
+                DistributionLayout::Flat
+            }
+        );
+        assert_eq!(
+            DistributionLayout::from_python_json_path("bin/python3.9"),
+            DistributionLayout::Flat
+        );
+        assert_eq!(
+            DistributionLayout::from_python_json_path("installer/python"),
+            DistributionLayout::Flat
+        );
+    }
+
+    #[test]
+    fn test_allowed_hosts() {
+        // The installed allowlist applies to every download of the process.
+        // So check against an allowlist of our own rather than installing one
+        // while other tests download.
+        let allowed = normalize_allowed_hosts(&["Mirror.example.com"]);
+
+        assert!(parse_distribution_url_allowing(
+            "https://MIRROR.example.com/dist.tar.zst",
+            &allowed
+        )
+        .is_ok());
+
+        let err = parse_distribution_url_allowing(
+            "https://disallowed.example.com/dist.tar.zst",
+            &allowed,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DistributionError>(),
+            Some(DistributionError::HostNotAllowed { host, .. }) if host == "disallowed.example.com"
+        ));
+
+        // By default, all hosts are allowed.
+        assert!(parse_distribution_url_allowing(
+            "https://disallowed.example.com/dist.tar.zst",
+            &normalize_allowed_hosts(&[])
+        )
+        .is_ok());
+        assert!(parse_distribution_url("https://disallowed.example.com/dist.tar.zst").is_ok());
+    }
+
+    #[test]
+    fn test_max_decompression_window() -> Result<()> {
+        use std::io::Write;
+
+        let mut encoder = zstd::stream::Encoder::new(vec![], 3)?;
+        encoder.window_log(24)?;
+        encoder.write_all(&vec![42u8; 1 << 16])?;
+        let compressed = encoder.finish()?;
+
+        assert!(set_max_decompression_window_log(Some(9)).is_err());
+
+        let mut data = vec![];
+        ArchiveDecoder::new(compressed.as_slice())?.read_to_end(&mut data)?;
+        assert_eq!(data.len(), 1 << 16);
+
+        // The installed limit applies to every decoder of the process. So use
+        // a limit of our own rather than installing one while other tests
+        // decompress.
+        let err = ArchiveDecoder::with_max_window_log(compressed.as_slice(), Some(20))?
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert!(err.to_string().contains("2^20"), "{}", err);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_window() -> Result<()> {
+        use std::io::Write;
+
+        let compress = |window_log| -> Result<Vec<u8>> {
+            let mut encoder = zstd::stream::Encoder::new(vec![], 3)?;
+            encoder.long_distance_matching(true)?;
+            encoder.window_log(window_log)?;
+            encoder.write_all(&vec![42u8; 1 << 16])?;
+            Ok(encoder.finish()?)
+        };
+
+        let compressed = compress(29)?;
+        assert_eq!(zstd_frame_window_log(&compressed), Some(29));
+        assert_eq!(zstd_frame_window_log(&compressed[..5]), None);
+
+        // zstd refuses such windows unless told otherwise. We accept them by default.
+        assert!(zstd::stream::decode_all(compressed.as_slice()).is_err());
+        let mut data = vec![];
+        ArchiveDecoder::new(compressed.as_slice())?.read_to_end(&mut data)?;
+        assert_eq!(data.len(), 1 << 16);
+
+        // A configured maximum is reported actionably.
+        let err = ArchiveDecoder::with_max_window_log(compressed.as_slice(), Some(20))?
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert!(err.to_string().contains("2^29"), "{}", err);
+        assert!(err.to_string().contains("zstd --long"), "{}", err);
+        assert!(err.to_string().contains("Some(29)"), "{}", err);
+
+        // A frame header declaring a window of 2^32 bytes. No limit decodes it.
+        let mut header = ZSTD_MAGIC.to_vec();
+        header.extend_from_slice(&[0x00, (32 - 10) << 3]);
+        assert_eq!(zstd_frame_window_log(&header), Some(32));
+        for window_log in [None, Some(20)] {
+            let err = ArchiveDecoder::with_max_window_log(header.as_slice(), window_log)
+                .err()
+                .unwrap();
+            assert_eq!(
+                err.to_string(),
+                "archive requires a zstd window of 2^32 bytes, larger than the decoder maximum of 2^31 bytes; \
+                 recompress it with a smaller window"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_supported_flavors() {
+        let mut record = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(
+            record.supported_flavors().iter().collect::<Vec<_>>(),
+            vec![
+                DistributionFlavor::Standalone,
+                DistributionFlavor::StandaloneDynamic
+            ]
+        );
+
+        record.supports_prebuilt_extension_modules = false;
+        let flavors = record.supported_flavors();
+        assert!(flavors.contains(&DistributionFlavor::StandaloneStatic));
+        assert!(!flavors.contains(&DistributionFlavor::StandaloneDynamic));
+    }
+
+    #[test]
+    fn test_cleanup_guard() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let guarded = |policy| -> Result<(CleanupGuard, PathBuf)> {
+            let path = td.path().join(format!("{:?}", policy));
+            create_dir_all(path.join("child"))?;
+
+            let mut guard = CleanupGuard::new(policy);
+            guard.track(&path);
+
+            Ok((guard, path))
+        };
+
+        let (guard, path) = guarded(CleanupPolicy::KeepAll)?;
+        guard.build_finished()?;
+        assert!(path.exists());
+
+        let (guard, path) = guarded(CleanupPolicy::DeleteOnDrop)?;
+        drop(guard);
+        assert!(!path.exists());
+
+        let (guard, path) = guarded(CleanupPolicy::DeleteAfterBuild)?;
+        drop(guard);
+        assert!(path.exists());
+
+        let (guard, path) = guarded(CleanupPolicy::DeleteAfterBuild)?;
+        guard.build_finished()?;
+        assert!(!path.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_distribution_flavor_round_trip() {
+        for flavor in [
+            DistributionFlavor::Standalone,
+            DistributionFlavor::StandaloneStatic,
+            DistributionFlavor::StandaloneDynamic,
+        ] {
+            assert_eq!(flavor.to_string().parse::<DistributionFlavor>(), Ok(flavor));
+        }
+
+        assert_eq!(
+            "standalone-static".parse::<DistributionFlavor>(),
+            Ok(DistributionFlavor::StandaloneStatic)
+        );
+
+        let err = "bogus".parse::<DistributionFlavor>().unwrap_err();
+        assert!(err.contains("standalone_dynamic"));
+    }
+
+    #[test]
+    fn test_list_archive_contents() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("dist.tar.zst");
+
+        let sha256 = write_test_archive(
+            &path,
+            &[
+                ("python/PYTHON.json", b"{}"),
+                ("python/install/foo", b"foobar"),
+            ],
+        )?;
+
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256,
+        };
+
+        assert_eq!(
+            list_archive_contents(&location)?,
+            vec![
+                ArchiveEntry {
+                    path: PathBuf::from("python/PYTHON.json"),
+                    size: 2,
+                },
+                ArchiveEntry {
+                    path: PathBuf::from("python/install/foo"),
+                    size: 6,
+                }
+            ]
+        );
+
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: "00".repeat(32),
+        };
+
+        assert!(list_archive_contents(&location).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_chunked() -> Result<()> {
+        let data = (0..100_001u32).map(|i| i as u8).collect::<Vec<_>>();
+
+        for chunk_size in [1, 7, DEFAULT_HASH_CHUNK_SIZE, 1 << 20] {
+            let mut hasher = Sha256::new();
+            let mut chunks = 0;
+            read_chunked(&mut data.as_slice(), chunk_size, |chunk| {
+                assert!(chunk.len() <= chunk_size);
+                hasher.update(chunk);
+                chunks += 1;
+            })?;
+
+            assert_eq!(hasher.finalize(), Sha256::digest(&data));
+            assert_eq!(chunks, data.chunks(chunk_size).count());
+        }
+
+        assert!(set_hash_chunk_size(0).is_err());
+        assert_eq!(
+            HASH_CHUNK_SIZE.load(Ordering::SeqCst),
+            DEFAULT_HASH_CHUNK_SIZE
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_location() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("dist.tar.zst");
+
+        let sha256 = write_test_archive(&path, &[("python/PYTHON.json", b"{}")])?;
+
+        verify_location(&PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256,
+        })?;
+
+        assert!(verify_location(&PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: "00".repeat(32),
+        })
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_git_location() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let repo = td.path().join("repo");
+        create_dir_all(repo.join("dists"))?;
+        let sha256 = write_test_archive(
+            &repo.join("dists/cpython.tar.zst"),
+            &[("python/PYTHON.json", b"{}")],
+        )?;
+
+        let repo_path = repo.display().to_string();
+        run_git(&["init", "-q", &repo_path], None)?;
+        run_git(&["-C", &repo_path, "add", "dists/cpython.tar.zst"], None)?;
+        run_git(
+            &[
+                "-C",
+                &repo_path,
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "add distribution",
+            ],
+            None,
+        )?;
+        run_git(&["-C", &repo_path, "tag", "v1"], None)?;
+
+        let location = PythonDistributionLocation::Git {
+            repo: repo_path.clone(),
+            rev: "v1".to_string(),
+            path: "dists/cpython.tar.zst".to_string(),
+            sha256: sha256.clone(),
+        };
+        assert_eq!(location.filename(), "cpython.tar.zst");
+        assert_eq!(location.sha256(), sha256);
+        check_location_availability(&location)?;
+        verify_location(&location)?;
+
+        let cache_dir = td.path().join("cache");
+        let archive = resolve_python_distribution_archive(&get_logger()?, &location, &cache_dir)?;
+        assert_eq!(archive, cache_dir.join("cpython.tar.zst"));
+        assert_eq!(
+            fs::read(&archive)?,
+            fs::read(repo.join("dists/cpython.tar.zst"))?
+        );
+
+        let wrong = PythonDistributionLocation::Git {
+            repo: repo_path.clone(),
+            rev: "v1".to_string(),
+            path: "dists/cpython.tar.zst".to_string(),
+            sha256: "00".repeat(32),
+        };
+        assert!(resolve_python_distribution_archive(
+            &get_logger()?,
+            &wrong,
+            &td.path().join("other")
+        )
+        .is_err());
+
+        let missing = PythonDistributionLocation::Git {
+            repo: repo_path.clone(),
+            rev: "v2".to_string(),
+            path: "dists/cpython.tar.zst".to_string(),
+            sha256: sha256.clone(),
+        };
+        assert!(check_location_availability(&missing).is_err());
+
+        // Bare repositories, as served by git hosts, work the same.
+        let bare_path = td.path().join("bare.git").display().to_string();
+        run_git(
+            &["clone", "-q", "--bare", "--", &repo_path, &bare_path],
+            None,
+        )?;
+        let bare = PythonDistributionLocation::Git {
+            repo: bare_path.clone(),
+            rev: "v1".to_string(),
+            path: "dists/cpython.tar.zst".to_string(),
+            sha256: sha256.clone(),
+        };
+        check_location_availability(&bare)?;
+        assert_eq!(
+            fs::read(resolve_python_distribution_archive(
+                &get_logger()?,
+                &bare,
+                &td.path().join("bare-cache")
+            )?)?,
+            fs::read(repo.join("dists/cpython.tar.zst"))?
+        );
+
+        // Values that git would parse as options are rejected before git runs.
+        let marker = td.path().join("injected");
+        let upload_pack = format!("--upload-pack=touch {}", marker.display());
+        for (repo, rev) in [
+            (upload_pack.as_str(), "v1"),
+            (bare_path.as_str(), upload_pack.as_str()),
+            (bare_path.as_str(), ""),
+        ] {
+            let location = PythonDistributionLocation::Git {
+                repo: repo.to_string(),
+                rev: rev.to_string(),
+                path: "dists/cpython.tar.zst".to_string(),
+                sha256: sha256.clone(),
+            };
+
+            assert!(
+                format!("{:?}", check_location_availability(&location).unwrap_err())
+                    .contains("invalid git")
+            );
+            assert!(
+                format!("{:?}", verify_location(&location).unwrap_err()).contains("invalid git")
+            );
+        }
+        assert!(!marker.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oci_location() -> Result<()> {
+        let reference = OciReference::parse("registry.example.com/python/cpython:3.9")?;
+        assert_eq!(
+            reference,
+            OciReference {
+                registry: "registry.example.com".to_string(),
+                repository: "python/cpython".to_string(),
+                reference: "3.9".to_string(),
+            }
+        );
+        assert_eq!(
+            reference.manifest_url(),
+            "https://registry.example.com/v2/python/cpython/manifests/3.9"
+        );
+        assert_eq!(
+            OciReference::parse("localhost:5000/cpython")?.reference,
+            "latest"
+        );
+        assert!(OciReference::parse("cpython").is_err());
+
+        let digest = "ab".repeat(32);
+        let location = PythonDistributionLocation::Oci {
+            reference: "registry.example.com/python/cpython:3.9".to_string(),
+            digest: digest.clone(),
+        };
+        assert_eq!(location.filename(), format!("{}.tar.zst", digest));
+        assert_eq!(location.sha256(), digest);
+
+        let manifest = format!(
+            r#"{{"schemaVersion": 2, "layers": [{{"digest": "sha256:{}"}}]}}"#,
+            digest
+        );
+        verify_oci_manifest(&reference, manifest.as_bytes(), &digest)?;
+        assert!(verify_oci_manifest(&reference, manifest.as_bytes(), &"cd".repeat(32)).is_err());
+
+        let pinned = OciReference::parse(&format!(
+            "registry.example.com/python/cpython@sha256:{}",
+            hex::encode(Sha256::digest(manifest.as_bytes()))
+        ))?;
+        verify_oci_manifest(&pinned, manifest.as_bytes(), &digest)?;
+        assert!(verify_oci_manifest(&pinned, b"{}", &digest).is_err());
+
+        assert_eq!(
+            OciReference::parse("localhost:5000/cpython")?.blob_url(&digest),
+            format!("http://localhost:5000/v2/cpython/blobs/sha256:{}", digest)
+        );
+
+        let challenge = parse_bearer_challenge(
+            r#"Bearer realm="https://auth.example.com/token",service=registry.example.com, scope="repository:a:pull,push""#,
+        )
+        .unwrap();
+        assert_eq!(
+            challenge.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "realm".to_string(),
+                    "https://auth.example.com/token".to_string()
+                ),
+                ("scope".to_string(), "repository:a:pull,push".to_string()),
+                ("service".to_string(), "registry.example.com".to_string()),
+            ]
+        );
+        assert!(parse_bearer_challenge(r#"Basic realm="registry""#).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oci_registry_auth() -> Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            net::TcpListener,
+        };
+
+        struct StaticCredentials(&'static str, &'static str);
+
+        impl CredentialProvider for StaticCredentials {
+            fn credentials(&self, _host: &str) -> Option<(String, String)> {
+                Some((self.0.to_string(), self.1.to_string()))
+            }
+        }
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let archive = td.path().join("dist.tar.zst");
+        let digest = write_test_archive(&archive, &[("python/PYTHON.json", b"{}")])?;
+        let blob = fs::read(&archive)?;
+        let manifest = format!(
+            r#"{{"schemaVersion": 2, "layers": [{{"digest": "sha256:{}"}}]}}"#,
+            digest
+        );
+
+        // A registry whose token service only grants user:secret a token.
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let registry = listener.local_addr()?.to_string();
+        let realm = format!("http://{}/token", registry);
+        let blob_path = format!("/v2/cpython/blobs/sha256:{}", digest);
+
+        let (served_realm, served_blob_path) = (realm.clone(), blob_path.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut authorization = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("authorization") {
+                            authorization = value.trim().to_string();
+                        }
+                    }
+                }
+
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                let bearer = authorization == "Bearer t0ken";
+
+                let (status, headers, body) = if path
+                    == "/token?service=registry.test&scope=repository%3Acpython%3Apull"
+                {
+                    // base64 of user:secret.
+                    if authorization == "Basic dXNlcjpzZWNyZXQ=" {
+                        ("200 OK", String::new(), br#"{"token": "t0ken"}"#.to_vec())
+                    } else {
+                        ("401 Unauthorized", String::new(), vec![])
+                    }
+                } else if !bearer {
+                    (
+                        "401 Unauthorized",
+                        format!(
+                            "WWW-Authenticate: Bearer realm=\"{}\",service=\"registry.test\",scope=\"repository:cpython:pull\"\r\n",
+                            served_realm
+                        ),
+                        vec![],
+                    )
+                } else if path == "/v2/cpython/manifests/3.9" {
+                    ("200 OK", String::new(), manifest.as_bytes().to_vec())
+                } else if path == served_blob_path {
+                    ("200 OK", String::new(), blob.clone())
+                } else {
+                    ("404 Not Found", String::new(), vec![])
+                };
+
+                let header = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    headers,
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).ok();
+                stream.write_all(&body).ok();
+            }
+        });
+
+        let client = distribution_http_client()?;
+        let reference = format!("{}/cpython:3.9", registry);
+
+        let resolved = resolve_oci_blob_with(
+            &client,
+            &reference,
+            &digest,
+            Some(&StaticCredentials("user", "secret")),
+        )?;
+        assert_eq!(
+            resolved.url.as_str(),
+            format!("http://{}{}", registry, blob_path)
+        );
+        assert_eq!(resolved.authorization.as_deref(), Some("Bearer t0ken"));
+
+        let cache_dir = td.path().join("cache");
+        fs::create_dir(&cache_dir)?;
+        let path = download_distribution_with(
+            &get_logger()?,
+            resolved.url.as_str(),
+            &digest,
+            &cache_dir,
+            "blob.tar.zst",
+            &[],
+            resolved.authorization.as_deref(),
+        )?;
+        assert_eq!(fs::read(&path)?, fs::read(&archive)?);
+
+        // The blob requires the token too.
+        let err = download_distribution_from(
+            &get_logger()?,
+            resolved.url.as_str(),
+            &digest,
+            &cache_dir,
+            "anonymous.tar.zst",
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("NETWORK"));
+
+        for provider in [None, Some(StaticCredentials("user", "wrong"))] {
+            let err = resolve_oci_blob_with(
+                &client,
+                &reference,
+                &digest,
+                provider.as_ref().map(|p| p as &dyn CredentialProvider),
+            )
+            .err()
+            .unwrap();
+            assert_eq!(DistributionError::code_of(&err), Some("UNAUTHORIZED"));
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "not authorized to access http://{}/v2/cpython/manifests/3.9: token service {} responded with HTTP 401 Unauthorized",
+                    registry, realm
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_codes() -> Result<()> {
+        let errors = [
+            DistributionError::NotFound {
+                target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                flavor: "standalone".to_string(),
+                python_version: "3.9".to_string(),
+            },
+            DistributionError::InvalidVersion {
+                version: "foo".to_string(),
+                message: "invalid".to_string(),
+            },
+            DistributionError::EndOfLife {
+                version: "3.6".to_string(),
+                eol_date: "2021-12-23".to_string(),
+            },
+            DistributionError::HostNotAllowed {
+                url: "https://example.com/".to_string(),
+                host: "example.com".to_string(),
+            },
+            DistributionError::NotCached {
+                filename: "cpython.tar.zst".to_string(),
+                cache_dir: PathBuf::from("cache"),
+            },
+            DistributionError::DigestMismatch {
+                name: "cpython.tar.zst".to_string(),
+            },
+            DistributionError::AttestationFailed {
+                name: "cpython.tar.zst".to_string(),
+                reason: "denied".to_string(),
+            },
+            DistributionError::Network {
+                url: "https://example.com/".to_string(),
+                message: "connection refused".to_string(),
+            },
+            DistributionError::Unauthorized {
+                url: "https://ghcr.io/v2/python/cpython/manifests/3.9".to_string(),
+                message: "registry responded with HTTP 403 Forbidden".to_string(),
+            },
+            DistributionError::BudgetExceeded {
+                budget: 1,
+                smallest: 2,
+            },
+            DistributionError::MirrorsExhausted {
+                name: "cpython.tar.zst".to_string(),
+                attempts: vec![],
+            },
+            DistributionError::Cancelled,
+            DistributionError::AbiMismatch {
+                host_abi: "cp39d".to_string(),
+                python_version: "3.9".to_string(),
+                distribution_abi: "cp39".to_string(),
+            },
+            DistributionError::ProvenanceFailed {
+                name: "cpython.tar.zst".to_string(),
+                reason: "untrusted builder".to_string(),
+            },
+            DistributionError::UnsupportedCompression {
+                detected: "gzip".to_string(),
+            },
+            DistributionError::IntegrityMismatch {
+                name: "dist.tar.zst".to_string(),
+                url: "https://example.com/dist.tar.zst".to_string(),
+                expected: "00".repeat(32),
+                actual: "11".repeat(32),
+            },
+        ];
+
+        let codes = errors.iter().map(|e| e.code()).collect::<BTreeSet<_>>();
+        assert_eq!(codes.len(), errors.len());
+        assert!(codes
+            .iter()
+            .all(|code| code.chars().all(|c| c.is_ascii_uppercase() || c == '_')));
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let path = td.path().join("bad.tar.zst");
+        fs::write(&path, b"content")?;
+        let err =
+            copy_local_distribution(&path, &"00".repeat(32), &td.path().join("cache")).unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("CHECKSUM"));
+
+        // Nothing listens on a port once its listener is dropped.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?
+            .local_addr()?
+            .port();
+        let err = download_distribution(
+            &get_logger()?,
+            &format!("http://127.0.0.1:{}/cpython.tar.zst", port),
+            &"00".repeat(32),
+            td.path(),
+        )
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("NETWORK"));
+
+        assert_eq!(DistributionError::code_of(&anyhow!("other")), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_provenance() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("provenance.tar.zst");
+        let sha256 = write_test_archive(&path, &[("python/PYTHON.json", b"{}")])?;
+
+        let builder = "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_generic_slsa3.yml@refs/tags/v1.2.0";
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "subject": [{"name": "provenance.tar.zst", "digest": {"sha256": sha256}}],
+            "predicateType": "https://slsa.dev/provenance/v0.2",
+            "predicate": {
+                "builder": {"id": builder},
+                "invocation": {
+                    "configSource": {
+                        "uri": "git+https://github.com/indygreg/python-build-standalone@refs/tags/20211017",
+                    },
+                },
+            },
+        })
+        .to_string();
+        let envelope = serde_json::json!({
+            "payloadType": "application/vnd.in-toto+json",
+            "payload": base64::encode(&statement),
+            "signatures": [],
+        });
+        let bundle = serde_json::json!({ "dsseEnvelope": envelope }).to_string();
+        let envelope = envelope.to_string();
+
+        let verifier = SlsaProvenanceVerifier {
+            builder_ids: vec![builder.to_string()],
+            source_repos: vec![
+                "https://github.com/indygreg/python-build-standalone.git".to_string()
+            ],
+        };
+
+        for attestation in [&statement, &envelope, &bundle] {
+            verify_provenance(&path, attestation.as_bytes(), &verifier)?;
+        }
+
+        // SLSA v1 provenance.
+        let v1 = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "provenance.tar.zst", "digest": {"sha256": sha256}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {
+                "buildDefinition": {
+                    "resolvedDependencies": [{
+                        "uri": "git+https://github.com/indygreg/python-build-standalone@refs/heads/main",
+                    }],
+                },
+                "runDetails": {"builder": {"id": builder}},
+            },
+        })
+        .to_string();
+        verify_provenance(&path, v1.as_bytes(), &verifier)?;
+
+        let rejected = |verifier: &SlsaProvenanceVerifier, attestation: &str| {
+            let err = verify_provenance(&path, attestation.as_bytes(), verifier).unwrap_err();
+            assert_eq!(DistributionError::code_of(&err), Some("PROVENANCE"));
+            err.to_string()
+        };
+
+        let untrusted_builder = SlsaProvenanceVerifier {
+            builder_ids: vec!["https://example.com/builder".to_string()],
+            ..verifier.clone()
+        };
+        assert!(rejected(&untrusted_builder, &statement).contains("builder"));
+
+        let untrusted_source = SlsaProvenanceVerifier {
+            source_repos: vec!["https://github.com/example/fork".to_string()],
+            ..verifier.clone()
+        };
+        assert!(rejected(&untrusted_source, &envelope).contains("source"));
+
+        let other_subject = statement.replace(&sha256, &"00".repeat(32));
+        assert!(rejected(&verifier, &other_subject).contains("subject"));
+
+        assert!(rejected(&verifier, "not json").contains("JSON"));
+
+        assert_eq!(
+            normalize_source_repo(
+                "git+ssh://git@github.com/indygreg/python-build-standalone.git@main"
+            ),
+            "ssh://git@github.com/indygreg/python-build-standalone"
+        );
+
+        // An empty policy only checks the subject.
+        verify_provenance(&path, v1.as_bytes(), &SlsaProvenanceVerifier::default())?;
+
+        // Missing archives are errors, not panics.
+        assert!(verify_provenance(
+            &td.path().join("missing.tar.zst"),
+            v1.as_bytes(),
+            &SlsaProvenanceVerifier::default()
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_digest_verifier() -> Result<()> {
+        // The verifier is global. The one installed here rejects only this
+        // test's archive and otherwise checks digests like the default, and
+        // the lock keeps other tests that install verifiers from running
+        // concurrently.
+        let _lock = crate::testutil::lock_global_settings();
+
+        struct DenyVerifier(String);
+
+        impl DigestVerifier for DenyVerifier {
+            fn verify(
+                &self,
+                name: &str,
+                expected_sha256: &str,
+                actual_sha256: &[u8],
+            ) -> Result<(), DistributionError> {
+                Sha256Verifier.verify(name, expected_sha256, actual_sha256)?;
+
+                if hex::encode(actual_sha256) == self.0 {
+                    Err(DistributionError::AttestationFailed {
+                        name: name.to_string(),
+                        reason: "not in transparency log".to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("attested.tar.zst");
+
+        let sha256 = write_test_archive(&path, &[("python/ATTESTED", b"attested")])?;
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: sha256.clone(),
+        };
+
+        verify_location(&location)?;
+
+        set_digest_verifier(Arc::new(DenyVerifier(sha256)));
+        let err = verify_location(&location).unwrap_err();
+        set_digest_verifier(Arc::new(Sha256Verifier));
+
+        assert!(matches!(
+            err.downcast_ref::<DistributionError>(),
+            Some(DistributionError::AttestationFailed { .. })
+        ));
+
+        let err = verify_location(&PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: "00".repeat(32),
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DistributionError>(),
+            Some(DistributionError::DigestMismatch { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repackage() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let root = td.path().join("tree");
+        create_dir_all(root.join("install/bin"))?;
+        create_dir_all(root.join("install/lib"))?;
+        fs::write(root.join("install/bin/python3.9"), b"python")?;
+        fs::write(root.join("install/lib/os.py"), b"import sys")?;
+        fs::write(root.join("PYTHON.json"), b"{}")?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("python3.9", root.join("install/bin/python3"))?;
+
+        let first = repackage(
+            &root,
+            &td.path().join("first.tar.zst"),
+            RepackageOptions::default(),
+        )?;
+
+        // Rewriting files changes their mtimes but not the archive.
+        fs::write(root.join("install/lib/os.py"), b"import sys")?;
+        let second = repackage(
+            &root,
+            &td.path().join("second.tar.zst"),
+            RepackageOptions::default(),
+        )?;
+        assert_eq!(first, second);
+        assert_eq!(
+            fs::read(td.path().join("first.tar.zst"))?,
+            fs::read(td.path().join("second.tar.zst"))?
+        );
+
+        let location = PythonDistributionLocation::Local {
+            local_path: td.path().join("first.tar.zst").display().to_string(),
+            sha256: first,
+        };
+        let paths = list_archive_contents(&location)?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect::<Vec<_>>();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(paths, sorted);
+        assert_eq!(paths[0], PathBuf::from("python/PYTHON.json"));
+
+        let dest_dir = td.path().join("extracted");
+        extract_distribution(&get_logger()?, &location, None, &dest_dir)?;
+        assert_eq!(fs::read(dest_dir.join("install/lib/os.py"))?, b"import sys");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(dest_dir.join("install/bin/python3"))?,
+            PathBuf::from("python3.9")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_check_extracted_permissions() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let root = td.path().join("python");
+        create_dir_all(root.join("install/bin"))?;
+        fs::write(root.join("install/bin/python3"), b"python")?;
+        fs::write(root.join("PYTHON.json"), b"{}")?;
+        fs::set_permissions(
+            root.join("install/bin/python3"),
+            fs::Permissions::from_mode(0o4755),
+        )?;
+        fs::set_permissions(root.join("PYTHON.json"), fs::Permissions::from_mode(0o666))?;
+
+        assert!(
+            check_extracted_permissions(&get_logger()?, &root, PermissionPolicy::Ignore)?
+                .is_empty()
+        );
+
+        let issues = check_extracted_permissions(&get_logger()?, &root, PermissionPolicy::Warn)?;
+        assert_eq!(
+            issues,
+            vec![
+                PermissionIssue {
+                    path: root.join("PYTHON.json"),
+                    mode: 0o666,
+                    unexpected: 0o002,
+                },
+                PermissionIssue {
+                    path: root.join("install/bin/python3"),
+                    mode: 0o4755,
+                    unexpected: 0o4000,
+                },
+            ]
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            format!(
+                "{} is setuid (mode 4755)",
+                root.join("install/bin/python3").display()
+            )
+        );
+
+        let err = check_extracted_permissions(&get_logger()?, &root, PermissionPolicy::Reject)
+            .unwrap_err();
+        assert!(err.to_string().contains("world writable"));
+
+        assert_eq!(
+            check_extracted_permissions(&get_logger()?, &root, PermissionPolicy::Strip)?.len(),
+            2
+        );
+        assert_eq!(
+            fs::metadata(root.join("install/bin/python3"))?
+                .permissions()
+                .mode()
+                & 0o7777,
+            0o755
+        );
+        assert!(
+            check_extracted_permissions(&get_logger()?, &root, PermissionPolicy::Reject)?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_clone_extracted() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let shared = td.path().join("shared");
+        create_dir_all(shared.join("install/bin"))?;
+        fs::write(shared.join("install/bin/python3.9"), b"python")?;
+        fs::write(shared.join("PYTHON.json"), b"{}")?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("python3.9", shared.join("install/bin/python3"))?;
+
+        let mut permissions = fs::metadata(shared.join("PYTHON.json"))?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(shared.join("PYTHON.json"), permissions)?;
+
+        let dest = td.path().join("build");
+        let method = clone_extracted(&shared, &dest)?;
+
+        assert_eq!(extracted_layout(&dest, 10)?, extracted_layout(&shared, 10)?);
+        assert_eq!(fs::read(dest.join("install/bin/python3.9"))?, b"python");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(dest.join("install/bin/python3"))?,
+            PathBuf::from("python3.9")
+        );
+
+        if method != CloneMethod::HardLink {
+            fs::write(dest.join("PYTHON.json"), b"modified")?;
+            assert_eq!(fs::read(shared.join("PYTHON.json"))?, b"{}");
+        }
+
+        assert!(clone_extracted(&shared, &dest).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_layout_equivalence_classes() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let linux = PYTHON_DISTRIBUTIONS
+            .iter()
+            .find(|dist| dist.target_triple == "x86_64-unknown-linux-gnu")
+            .unwrap();
+        let macos = PYTHON_DISTRIBUTIONS
+            .iter()
+            .find(|dist| dist.target_triple == "x86_64-apple-darwin")
+            .unwrap();
+        let windows = PYTHON_DISTRIBUTIONS
+            .iter()
+            .find(|dist| dist.target_triple == "x86_64-pc-windows-msvc")
+            .unwrap();
+
+        let mut roots = vec![];
+        for (name, files) in [
+            (
+                "linux",
+                vec!["install/bin/python3", "install/lib/python3.9/os.py"],
+            ),
+            (
+                "macos",
+                vec!["install/bin/python3", "install/lib/python3.8/os.py"],
+            ),
+            ("windows", vec!["install/python.exe", "install/Lib/os.py"]),
+        ] {
+            let root = td.path().join(name);
+            for file in files {
+                let path = root.join(file);
+                create_dir_all(path.parent().unwrap())?;
+                fs::write(&path, b"")?;
+            }
+            roots.push(root);
+        }
+
+        let extracted = vec![
+            (linux, roots[0].as_path()),
+            (windows, roots[2].as_path()),
+            (macos, roots[1].as_path()),
+        ];
+
+        assert_eq!(
+            layout_equivalence_classes(&extracted, 2)?,
+            vec![vec![linux, macos], vec![windows]]
+        );
+        assert_eq!(layout_equivalence_classes(&extracted, 3)?.len(), 3);
+
+        for (record, root) in extracted {
+            assert!(interpreter_path(root, record).is_file());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_members() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("dist.tar.zst");
+
+        let sha256 = write_test_archive(
+            &path,
+            &[
+                ("python/PYTHON.json", b"{}"),
+                ("python/install/bin/python3", b"python"),
+                ("python/install/lib/foo.py", b"foo"),
+            ],
+        )?;
+
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256,
+        };
+
+        let dest_dir = td.path().join("extracted");
+        let extracted = extract_members(
+            &location,
+            |p| p.starts_with("python/install/bin"),
+            &dest_dir,
+        )?;
+
+        assert_eq!(extracted, vec![PathBuf::from("python/install/bin/python3")]);
+        assert!(dest_dir.join("python/install/bin/python3").exists());
+        assert!(!dest_dir.join("python/PYTHON.json").exists());
+        assert!(!dest_dir.join("python/install/lib/foo.py").exists());
+
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: "00".repeat(32),
+        };
+        let dest_dir = td.path().join("bad");
+
+        assert!(extract_members(&location, |_| true, &dest_dir).is_err());
+        assert!(!dest_dir.exists());
+
+        // Missing archives are errors, not panics.
+        let location = PythonDistributionLocation::Local {
+            local_path: td.path().join("missing.tar.zst").display().to_string(),
+            sha256: "00".repeat(32),
+        };
+        let err = extract_members(&location, |_| true, &dest_dir).unwrap_err();
+        assert!(format!("{:?}", err).contains("missing.tar.zst"));
+        assert!(extract_distribution(&get_logger()?, &location, None, &dest_dir).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_distribution() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let prefixed_path = td.path().join("prefixed.tar.zst");
+        let prefixed = PythonDistributionLocation::Local {
+            local_path: prefixed_path.display().to_string(),
+            sha256: write_test_archive(
+                &prefixed_path,
+                &[
+                    ("python/PYTHON.json", b"{}"),
+                    ("python/install/bin/python3", b"python"),
+                ],
+            )?,
+        };
+
+        let bare_path = td.path().join("bare.tar.zst");
+        let bare = PythonDistributionLocation::Local {
+            local_path: bare_path.display().to_string(),
+            sha256: write_test_archive(&bare_path, &[("PYTHON.json", b"{}")])?,
+        };
+
+        let dest_dir = td.path().join("prefixed");
+        assert_eq!(
+            extract_distribution(&get_logger()?, &prefixed, None, &dest_dir)?,
+            vec![
+                PathBuf::from("PYTHON.json"),
+                PathBuf::from("install/bin/python3")
+            ]
+        );
+        assert!(dest_dir.join("install/bin/python3").is_file());
+
+        let dest_dir = td.path().join("bare");
+        assert_eq!(
+            extract_distribution(&get_logger()?, &bare, None, &dest_dir)?,
+            vec![PathBuf::from("PYTHON.json")]
+        );
+        assert!(dest_dir.join("PYTHON.json").is_file());
+
+        let dest_dir = td.path().join("wrong");
+        assert!(
+            extract_distribution(&get_logger()?, &prefixed, Some("cpython/"), &dest_dir).is_err()
+        );
+        assert!(!dest_dir.join("python").exists());
+
+        // Records strip their own prefix.
+        let cpython_path = td.path().join("cpython.tar.zst");
+        let record = PythonDistributionRecord {
+            location: PythonDistributionLocation::Local {
+                local_path: cpython_path.display().to_string(),
+                sha256: write_test_archive(
+                    &cpython_path,
+                    &[
+                        ("cpython/PYTHON.json", b"{}"),
+                        ("cpython/install/bin/python3", b"python"),
+                    ],
+                )?,
+            },
+            strip_prefix: Some("cpython/".to_string()),
+            ..PYTHON_DISTRIBUTIONS.iter().next().unwrap().clone()
+        };
+
+        let dest_dir = td.path().join("cpython");
+        assert_eq!(
+            record.extract(&get_logger()?, &dest_dir)?,
+            vec![
+                PathBuf::from("PYTHON.json"),
+                PathBuf::from("install/bin/python3")
+            ]
+        );
+        assert!(dest_dir.join("install/bin/python3").is_file());
+
+        let record = PythonDistributionRecord {
+            strip_prefix: None,
+            ..record
+        };
+        let dest_dir = td.path().join("cpython-unstripped");
+        record.extract(&get_logger()?, &dest_dir)?;
+        assert!(dest_dir.join("cpython/install/bin/python3").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_brotli_archive() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let big = (0..200_000u32)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect::<Vec<_>>();
+        let members: [(&str, &[u8]); 2] = [
+            ("python/PYTHON.json", b"{}"),
+            ("python/install/lib/big.bin", &big),
+        ];
+
+        let zstd_path = td.path().join("dist.tar.zst");
+        let zstd_sha256 = write_test_archive(&zstd_path, &members)?;
+
+        // Recompress the same tar with brotli.
+        let mut tar_data = vec![];
+        ArchiveDecoder::new(File::open(&zstd_path)?)?.read_to_end(&mut tar_data)?;
+        let mut brotli_data = vec![];
+        brotli::CompressorReader::new(tar_data.as_slice(), 4096, 9, 22)
+            .read_to_end(&mut brotli_data)?;
+        let brotli_path = td.path().join("dist.tar.br");
+        fs::write(&brotli_path, &brotli_data)?;
+
+        let extract =
+            |path: &Path, sha256: String, dest: &str| -> Result<Vec<(PathBuf, Vec<u8>)>> {
+                let dest = td.path().join(dest);
+                let location = PythonDistributionLocation::Local {
+                    local_path: path.display().to_string(),
+                    sha256,
+                };
+
+                extract_distribution(&get_logger()?, &location, None, &dest)?
+                    .into_iter()
+                    .map(|path| Ok((path.clone(), fs::read(dest.join(path))?)))
+                    .collect()
+            };
+
+        let from_zstd = extract(&zstd_path, zstd_sha256, "zstd")?;
+        let from_brotli = extract(
+            &brotli_path,
+            hex::encode(Sha256::digest(&brotli_data)),
+            "brotli",
+        )?;
+        assert_eq!(from_zstd.len(), 2);
+        assert_eq!(from_brotli, from_zstd);
+
+        let err = ArchiveDecoder::new(&b"neither zstd nor brotli"[..])?
+            .read_to_end(&mut vec![])
+            .unwrap_err();
+        assert!(err.to_string().contains("neither zstd nor valid brotli"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported_compression() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let path = td.path().join("dist.tar.gz");
+        let data = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+        fs::write(&path, data)?;
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: hex::encode(Sha256::digest(&data)),
+        };
+
+        let err = extract_distribution(&get_logger()?, &location, None, &td.path().join("dest"))
+            .unwrap_err();
+        assert_eq!(
+            DistributionError::code_of(&err),
+            Some("UNSUPPORTED_COMPRESSION")
+        );
+        assert!(err.to_string().contains("gzip"));
+        assert!(err.to_string().contains("zstd and brotli"));
+
+        for (name, magic) in UNSUPPORTED_COMPRESSION_MAGICS.iter() {
+            let err = ArchiveDecoder::new(*magic).err().unwrap();
+            assert!(err.to_string().contains(name));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_disk_sink_symlinked_parent() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let outside = td.path().join("outside");
+        fs::create_dir(&outside)?;
+
+        let mut sink = DiskSink::new(td.path().join("root"));
+        sink.write_file(Path::new("content"), &mut b"data".as_ref(), 0o4755)?;
+        sink.symlink(Path::new("escape"), &outside)?;
+
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            assert_eq!(
+                fs::metadata(td.path().join("root/content"))?
+                    .permissions()
+                    .mode()
+                    & 0o7777,
+                0o755
+            );
+        }
+
+        for err in [
+            sink.create_dir(Path::new("escape/dir")).unwrap_err(),
+            sink.write_file(Path::new("escape/evil"), &mut b"evil".as_ref(), 0o644)
+                .unwrap_err(),
+            sink.symlink(Path::new("escape/evil"), Path::new("/"))
+                .unwrap_err(),
+            sink.hard_link(Path::new("escape/evil"), Path::new("content"))
+                .unwrap_err(),
+            sink.hard_link(Path::new("evil"), Path::new("escape/file"))
+                .unwrap_err(),
+        ] {
+            assert!(err.to_string().contains("passes through symlink"));
+        }
+        assert_eq!(fs::read_dir(&outside)?.count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_distribution_into() -> Result<()> {
+        use std::collections::BTreeMap;
+
+        #[derive(Default)]
+        struct MemorySink(BTreeMap<PathBuf, String>);
+
+        impl FileSink for MemorySink {
+            fn create_dir(&mut self, path: &Path) -> Result<()> {
+                self.0.insert(path.to_path_buf(), "dir".to_string());
+                Ok(())
+            }
+
+            fn write_file(&mut self, path: &Path, data: &mut dyn Read, mode: u32) -> Result<()> {
+                let mut content = String::new();
+                data.read_to_string(&mut content)?;
+                self.0
+                    .insert(path.to_path_buf(), format!("file {:o} {}", mode, content));
+                Ok(())
+            }
+
+            fn symlink(&mut self, path: &Path, target: &Path) -> Result<()> {
+                self.0
+                    .insert(path.to_path_buf(), format!("symlink {}", target.display()));
+                Ok(())
+            }
+        }
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let path = td.path().join("dist.tar.zst");
+
+        let mut builder = tar::Builder::new(vec![]);
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder.append_data(&mut header, "python/install/bin", std::io::empty())?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o755);
+        header.set_size(6);
+        builder.append_data(&mut header, "python/install/bin/python3.9", &b"python"[..])?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("python3.9")?;
+        builder.append_data(&mut header, "python/install/bin/python3", std::io::empty())?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        header.set_link_name("python/install/bin/python3.9")?;
+        builder.append_data(&mut header, "python/install/python", std::io::empty())?;
+
+        let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+        fs::write(&path, &data)?;
+
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: hex::encode(Sha256::digest(&data)),
+        };
+
+        let mut sink = MemorySink::default();
+        extract_distribution_into(&location, None, &mut sink)?;
+
+        let mut expected = BTreeMap::new();
+        expected.insert(PathBuf::from("install/bin"), "dir".to_string());
+        expected.insert(
+            PathBuf::from("install/bin/python3.9"),
+            "file 755 python".to_string(),
+        );
+        expected.insert(
+            PathBuf::from("install/bin/python3"),
+            "symlink python3.9".to_string(),
+        );
+        expected.insert(
+            PathBuf::from("install/python"),
+            format!(
+                "symlink {}",
+                Path::new("..").join("install/bin/python3.9").display()
+            ),
+        );
+        assert_eq!(sink.0, expected);
+
+        // Nothing is written if the digest doesn't match.
+        let mut sink = MemorySink::default();
+        assert!(extract_distribution_into(
+            &PythonDistributionLocation::Local {
+                local_path: path.display().to_string(),
+                sha256: "00".repeat(32),
+            },
+            None,
+            &mut sink
+        )
+        .is_err());
+        assert!(sink.0.is_empty());
+
+        let dest_dir = td.path().join("disk");
+        extract_distribution(&get_logger()?, &location, None, &dest_dir)?;
+        assert!(dest_dir.join("install/python").is_file());
+        assert_eq!(fs::read(dest_dir.join("install/bin/python3"))?, b"python");
+
+        // Archives created like `tar -C dir -cf x.tar .` prefix members with
+        // `./`. `tar::Builder` strips that, so members are named directly.
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, entry_type, link_name, data) in [
+            ("./", tar::EntryType::Directory, None, ""),
+            ("./python/", tar::EntryType::Directory, None, ""),
+            ("./python/install/", tar::EntryType::Directory, None, ""),
+            (
+                "./python/install/python3.9",
+                tar::EntryType::Regular,
+                None,
+                "python",
+            ),
+            (
+                "./python/install/python",
+                tar::EntryType::Link,
+                Some("./python/install/python3.9"),
+                "",
+            ),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(entry_type);
+            header.set_mode(0o755);
+            header.set_size(data.len() as u64);
+            if let Some(link_name) = link_name {
+                header.as_gnu_mut().unwrap().linkname[..link_name.len()]
+                    .copy_from_slice(link_name.as_bytes());
+            }
+            header.set_cksum();
+            builder.append(&header, data.as_bytes())?;
+        }
+        let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+        let path = td.path().join("dot.tar.zst");
+        fs::write(&path, &data)?;
+        let location = PythonDistributionLocation::Local {
+            local_path: path.display().to_string(),
+            sha256: hex::encode(Sha256::digest(&data)),
+        };
+
+        let mut sink = MemorySink::default();
+        extract_distribution_into(&location, None, &mut sink)?;
+
+        let mut expected = BTreeMap::new();
+        expected.insert(PathBuf::from("install"), "dir".to_string());
+        expected.insert(
+            PathBuf::from("install/python3.9"),
+            "file 755 python".to_string(),
+        );
+        expected.insert(
+            PathBuf::from("install/python"),
+            format!(
+                "symlink {}",
+                Path::new("..").join("install/python3.9").display()
+            ),
+        );
+        assert_eq!(sink.0, expected);
+
+        let dest_dir = td.path().join("dot");
+        extract_distribution(&get_logger()?, &location, None, &dest_dir)?;
+        assert_eq!(fs::read(dest_dir.join("install/python"))?, b"python");
 
         Ok(())
     }
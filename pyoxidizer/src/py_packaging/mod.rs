@@ -16,5 +16,6 @@ pub mod filtering;
 pub mod libpython;
 pub mod packaging_tool;
 pub mod resource;
+pub mod sbom;
 pub mod standalone_builder;
 pub mod standalone_distribution;
@@ -0,0 +1,300 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Software bills of materials describing Python distributions.
+*/
+
+use {
+    crate::{
+        environment::PYOXIDIZER_VERSION,
+        py_packaging::standalone_distribution::StandaloneDistribution,
+    },
+    serde::Serialize,
+    std::collections::{BTreeMap, BTreeSet},
+    tugger_licensing::{LicenseFlavor, LicensedComponent},
+};
+
+/// CycloneDX specification version of documents we produce.
+const CYCLONEDX_SPEC_VERSION: &str = "1.4";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bom {
+    bom_format: &'static str,
+    spec_version: &'static str,
+    version: u32,
+    metadata: BomMetadata,
+    components: Vec<BomComponent>,
+    dependencies: Vec<BomDependency>,
+}
+
+#[derive(Serialize)]
+struct BomMetadata {
+    tools: Vec<BomTool>,
+    component: BomComponent,
+}
+
+#[derive(Serialize)]
+struct BomTool {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct BomComponent {
+    #[serde(rename = "type")]
+    component_type: &'static str,
+    #[serde(rename = "bom-ref")]
+    bom_ref: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    licenses: Vec<BomLicense>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    purl: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    properties: Vec<BomProperty>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum BomLicense {
+    Expression { expression: String },
+    Named { license: BomNamedLicense },
+}
+
+#[derive(Serialize)]
+struct BomNamedLicense {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct BomProperty {
+    name: &'static str,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BomDependency {
+    #[serde(rename = "ref")]
+    dependency_ref: String,
+    depends_on: Vec<String>,
+}
+
+/// Express the licenses of components as CycloneDX licenses.
+///
+/// Differing SPDX expressions are combined with `AND`, as all apply.
+fn bom_licenses<'a>(components: impl Iterator<Item = &'a LicensedComponent>) -> Vec<BomLicense> {
+    let mut expressions = BTreeSet::new();
+    let mut names = BTreeSet::new();
+
+    for component in components {
+        match component.license() {
+            LicenseFlavor::Spdx(expression) | LicenseFlavor::OtherExpression(expression) => {
+                expressions.insert(expression.to_string());
+            }
+            LicenseFlavor::PublicDomain => {
+                names.insert("Public Domain".to_string());
+            }
+            LicenseFlavor::Unknown(terms) => {
+                names.extend(terms.iter().cloned());
+            }
+            LicenseFlavor::None => {}
+        }
+    }
+
+    let expression = match expressions.len() {
+        0 => None,
+        1 => expressions.into_iter().next(),
+        _ => Some(
+            expressions
+                .iter()
+                .map(|e| format!("({})", e))
+                .collect::<Vec<_>>()
+                .join(" AND "),
+        ),
+    };
+
+    expression
+        .map(|expression| BomLicense::Expression { expression })
+        .into_iter()
+        .chain(names.into_iter().map(|name| BomLicense::Named {
+            license: BomNamedLicense { name },
+        }))
+        .collect()
+}
+
+/// Render a CycloneDX document for an interpreter and the libraries it bundles.
+///
+/// `libraries` maps library names to the licensed components of extension
+/// modules bundling them.
+fn render_bom(
+    implementation: &str,
+    version: &str,
+    target_triple: &str,
+    core: Option<&LicensedComponent>,
+    libraries: &BTreeMap<String, Vec<&LicensedComponent>>,
+) -> String {
+    let interpreter_ref = format!("{}@{}", implementation, version);
+
+    let components = libraries
+        .iter()
+        .map(|(name, licensed)| BomComponent {
+            component_type: "library",
+            bom_ref: format!("{}:{}", interpreter_ref, name),
+            name: name.clone(),
+            version: None,
+            licenses: bom_licenses(licensed.iter().copied()),
+            purl: None,
+            properties: vec![],
+        })
+        .collect::<Vec<_>>();
+
+    let bom = Bom {
+        bom_format: "CycloneDX",
+        spec_version: CYCLONEDX_SPEC_VERSION,
+        version: 1,
+        dependencies: vec![BomDependency {
+            dependency_ref: interpreter_ref.clone(),
+            depends_on: components.iter().map(|c| c.bom_ref.clone()).collect(),
+        }],
+        components,
+        metadata: BomMetadata {
+            tools: vec![BomTool {
+                name: "PyOxidizer",
+                version: PYOXIDIZER_VERSION,
+            }],
+            component: BomComponent {
+                component_type: "framework",
+                bom_ref: interpreter_ref,
+                name: implementation.to_string(),
+                version: Some(version.to_string()),
+                licenses: bom_licenses(core.into_iter()),
+                purl: Some(format!("pkg:generic/{}@{}", implementation, version)),
+                properties: vec![BomProperty {
+                    name: "pyoxidizer:target_triple",
+                    value: target_triple.to_string(),
+                }],
+            },
+        },
+    };
+
+    serde_json::to_string_pretty(&bom).expect("BOM is serializable")
+}
+
+/// Produce a CycloneDX JSON document describing a distribution.
+///
+/// The document's subject is the interpreter. Libraries bundled by the
+/// distribution's extension modules, e.g. OpenSSL, SQLite, and Tcl/Tk, are
+/// its components, with the licenses recorded for the extension modules in
+/// `PYTHON.json`. `python-build-standalone` doesn't record versions of
+/// bundled libraries, so library components have none. System libraries and
+/// frameworks aren't part of the distribution and aren't listed.
+#[allow(unused)]
+pub fn to_cyclonedx(dist: &StandaloneDistribution) -> String {
+    let mut libraries: BTreeMap<String, Vec<&LicensedComponent>> = BTreeMap::new();
+
+    for em in dist
+        .extension_modules
+        .values()
+        .flat_map(|variants| variants.iter())
+    {
+        for link in &em.link_libraries {
+            if link.system || link.framework {
+                continue;
+            }
+            if link.static_library.is_none() && link.dynamic_library.is_none() {
+                continue;
+            }
+
+            let entry = libraries.entry(link.name.clone()).or_default();
+            if let Some(license) = &em.license {
+                entry.push(license);
+            }
+        }
+    }
+
+    render_bom(
+        &dist.python_implementation,
+        &dist.version,
+        &dist.target_triple,
+        dist.core_license.as_ref(),
+        &libraries,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::Result};
+
+    #[test]
+    fn test_render_bom() -> Result<()> {
+        let core = LicensedComponent::new_spdx("cpython", "Python-2.0")?;
+        let ssl = LicensedComponent::new_spdx("_ssl", "OpenSSL")?;
+        let hashlib = LicensedComponent::new_spdx("_hashlib", "OpenSSL")?;
+        let sqlite = LicensedComponent::new_public_domain("_sqlite3");
+        let tkinter = LicensedComponent::new_spdx("_tkinter", "TCL")?;
+        let other = LicensedComponent::new_spdx("other", "MIT")?;
+
+        let mut libraries = BTreeMap::new();
+        libraries.insert("crypto".to_string(), vec![&ssl, &hashlib]);
+        libraries.insert("sqlite3".to_string(), vec![&sqlite]);
+        libraries.insert("tcl8.6".to_string(), vec![&tkinter, &other]);
+        libraries.insert("unlicensed".to_string(), vec![]);
+
+        let bom: serde_json::Value = serde_json::from_str(&render_bom(
+            "cpython",
+            "3.9.7",
+            "x86_64-unknown-linux-gnu",
+            Some(&core),
+            &libraries,
+        ))?;
+
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["specVersion"], CYCLONEDX_SPEC_VERSION);
+        assert_eq!(bom["version"], 1);
+
+        let subject = &bom["metadata"]["component"];
+        assert_eq!(subject["type"], "framework");
+        assert_eq!(subject["name"], "cpython");
+        assert_eq!(subject["version"], "3.9.7");
+        assert_eq!(subject["purl"], "pkg:generic/cpython@3.9.7");
+        assert_eq!(
+            subject["licenses"],
+            serde_json::json!([{"expression": "Python-2.0"}])
+        );
+
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 4);
+        assert!(components.iter().all(|c| c["type"] == "library"));
+        assert_eq!(components[0]["name"], "crypto");
+        assert_eq!(
+            components[0]["licenses"],
+            serde_json::json!([{"expression": "OpenSSL"}])
+        );
+        assert_eq!(
+            components[1]["licenses"],
+            serde_json::json!([{"license": {"name": "Public Domain"}}])
+        );
+        assert_eq!(
+            components[2]["licenses"],
+            serde_json::json!([{"expression": "(MIT) AND (TCL)"}])
+        );
+        assert!(components[3].get("licenses").is_none());
+
+        // Every component is a dependency of the interpreter.
+        let dependencies = bom["dependencies"].as_array().unwrap();
+        assert_eq!(dependencies.len(), 1);
+        assert_eq!(dependencies[0]["ref"], subject["bom-ref"]);
+        assert_eq!(
+            dependencies[0]["dependsOn"],
+            serde_json::Value::Array(components.iter().map(|c| c["bom-ref"].clone()).collect())
+        );
+
+        Ok(())
+    }
+}
@@ -9,8 +9,10 @@ use {
         binary::{LibpythonLinkMode, PythonBinaryBuilder},
         config::{default_memory_allocator, PyembedPythonInterpreterConfig},
         distribution::{
-            resolve_python_distribution_from_location, AppleSdkInfo, BinaryLibpythonLinkMode,
-            DistributionExtractLock, PythonDistribution, PythonDistributionLocation,
+            acquire_extraction_permit, apply_permission_policy, check_cancelled, extract_members,
+            resolve_python_distribution_from_location, AppleSdkInfo, ArchiveDecoder,
+            BinaryLibpythonLinkMode, CancelableReader, DistributionExtractLock, DistributionLayout,
+            ExtractedHook, PythonDistribution, PythonDistributionLocation,
         },
         distutils::prepare_hacked_distutils,
         standalone_builder::StandalonePythonExecutableBuilder,
@@ -32,17 +34,17 @@ use {
             PythonModuleSource, PythonPackageResource, PythonResource,
         },
     },
-    serde::Deserialize,
+    serde::{Deserialize, Serialize},
     slog::{info, warn},
     std::{
-        collections::{hash_map::RandomState, BTreeMap, HashMap},
+        collections::{hash_map::RandomState, BTreeMap, BTreeSet, HashMap},
         convert::TryFrom,
         io::{BufRead, BufReader, Read},
         path::{Path, PathBuf},
         sync::Arc,
     },
     tugger_file_manifest::{FileData, FileEntry},
-    tugger_licensing::{ComponentFlavor, LicensedComponent},
+    tugger_licensing::{ComponentFlavor, LicenseFlavor, LicensedComponent},
 };
 
 // This needs to be kept in sync with *compiler.py
@@ -54,6 +56,9 @@ const PYTHON_EXE_BASENAME: &str = "python.exe";
 #[cfg(unix)]
 const PYTHON_EXE_BASENAME: &str = "python3";
 
+/// File written to an extracted distribution directory once extraction is complete.
+const EXTRACT_COMPLETE_MARKER: &str = ".pyoxidizer-extract-complete";
+
 #[cfg(windows)]
 const PIP_EXE_BASENAME: &str = "pip3.exe";
 
@@ -207,12 +212,14 @@ struct PythonJsonMain {
     apple_sdk_deployment_target: Option<String>,
     libpython_link_mode: String,
     crt_features: Vec<String>,
+    cpython_commit: Option<String>,
     run_tests: String,
     build_info: PythonBuildInfo,
     licenses: Option<Vec<String>>,
     license_path: Option<String>,
     tcl_library_path: Option<String>,
     tcl_library_paths: Option<Vec<String>>,
+    library_build_modes: Option<BTreeMap<String, String>>,
 }
 
 fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
@@ -248,11 +255,40 @@ fn parse_python_json(path: &Path) -> Result<PythonJsonMain> {
     Ok(v)
 }
 
+/// Determine where the libffi used by `_ctypes` comes from.
+fn libffi_source(build_info: &PythonBuildInfo) -> LibSource {
+    let links = build_info
+        .extensions
+        .get("_ctypes")
+        .into_iter()
+        .flatten()
+        .flat_map(|variant| variant.links.iter())
+        .filter(|link| link.name == "ffi")
+        .collect::<Vec<_>>();
+
+    if links.iter().any(|link| !link.system.unwrap_or(false)) {
+        LibSource::Bundled
+    } else if links.is_empty() {
+        LibSource::Absent
+    } else {
+        LibSource::System
+    }
+}
+
 fn parse_python_json_from_distribution(dist_dir: &Path) -> Result<PythonJsonMain> {
     let python_json_path = dist_dir.join("python").join("PYTHON.json");
     parse_python_json(&python_json_path)
 }
 
+/// Resolve the temporary directory a distribution is extracted to before being moved into place.
+fn partial_extract_dir(extract_dir: &Path) -> Result<PathBuf> {
+    let name = extract_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("unable to determine extraction directory name"))?;
+
+    Ok(extract_dir.with_file_name(format!("{}.partial", name.to_string_lossy())))
+}
+
 /// Resolve the path to a `python` executable in a Python distribution.
 pub fn python_exe_path(dist_dir: &Path) -> Result<PathBuf> {
     let pi = parse_python_json_from_distribution(dist_dir)?;
@@ -360,6 +396,89 @@ pub enum StandaloneDistributionLinkMode {
     Dynamic,
 }
 
+/// Describes where a library used by a standalone distribution comes from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LibSource {
+    /// The library is built and shipped with the distribution.
+    Bundled,
+    /// The library must be provided by the target system.
+    System,
+    /// The distribution doesn't use the library.
+    Absent,
+}
+
+/// SPDX licenses of a distribution's interpreter and the libraries it bundles.
+///
+/// See `StandaloneDistribution::license_summary()`. Licenses are rendered as
+/// SPDX identifiers, with any exception. e.g. `Python-2.0` or
+/// `GPL-2.0 WITH Classpath-exception-2.0`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct LicenseSummary {
+    /// Licenses of the interpreter itself.
+    pub interpreter: BTreeSet<String>,
+
+    /// Licenses of libraries bundled by extension modules, keyed by module name.
+    ///
+    /// Only modules licensed differently from the interpreter are present.
+    /// e.g. `_ssl` with `OpenSSL`.
+    pub extension_modules: BTreeMap<String, BTreeSet<String>>,
+
+    /// Extension modules whose bundled libraries are in the public domain.
+    pub public_domain: BTreeSet<String>,
+}
+
+impl LicenseSummary {
+    /// Summarize the licenses of a core distribution and its extension modules.
+    fn from_components<'a>(
+        core: Option<&LicensedComponent>,
+        extension_modules: impl Iterator<Item = &'a LicensedComponent>,
+    ) -> Self {
+        let identifiers = |component: &LicensedComponent| {
+            component
+                .all_spdx_licenses()
+                .into_iter()
+                .map(|(license, exception)| match exception {
+                    Some(exception) => format!("{} WITH {}", license.name, exception.name),
+                    None => license.name.to_string(),
+                })
+                .collect::<BTreeSet<_>>()
+        };
+
+        let mut summary = Self {
+            interpreter: core.map(identifiers).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for component in extension_modules {
+            if component.license() == &LicenseFlavor::PublicDomain {
+                summary.public_domain.insert(component.name().to_string());
+                continue;
+            }
+
+            let licenses = identifiers(component);
+            if !licenses.is_empty() && licenses != summary.interpreter {
+                summary
+                    .extension_modules
+                    .entry(component.name().to_string())
+                    .or_default()
+                    .extend(licenses);
+            }
+        }
+
+        summary
+    }
+
+    /// Every license in the summary.
+    #[allow(unused)]
+    pub fn all(&self) -> BTreeSet<&str> {
+        self.interpreter
+            .iter()
+            .chain(self.extension_modules.values().flatten())
+            .map(|license| license.as_str())
+            .collect()
+    }
+}
+
 /// Represents a standalone Python distribution.
 ///
 /// This is a Python distributed produced by the `python-build-standalone`
@@ -437,6 +556,12 @@ pub struct StandaloneDistribution {
     /// Linking information for the core Python implementation.
     pub links_core: Vec<LibraryDependency>,
 
+    /// Whether `_ctypes` links against a bundled or the system libffi.
+    ///
+    /// Distributions using the system libffi fail to import `ctypes` on
+    /// targets lacking it.
+    pub libffi_source: LibSource,
+
     /// Filesystem location of pythonXY shared library for this distribution.
     ///
     /// Only set if `link_mode` is `StandaloneDistributionLinkMode::Dynamic`.
@@ -488,6 +613,189 @@ pub struct StandaloneDistribution {
 
     /// Configuration variables used by Python.
     config_vars: HashMap<String, String>,
+
+    /// Git commit of the CPython source the distribution was built from.
+    cpython_commit: Option<String>,
+
+    /// Build modes of bundled libraries, keyed by library.
+    library_build_modes: BTreeMap<String, String>,
+
+    /// Directory layout of the extracted distribution.
+    layout: DistributionLayout,
+}
+
+/// Unpack the members of a distribution tar archive into a directory.
+///
+/// Members are unpacked in archive order with `tar::Entry::unpack_in()`,
+/// which never writes outside of `absolute_path`. Members like
+/// `./python/PYTHON.json`, from archives created like `tar -cf x.tar .`, are
+/// unpacked without their leading `./`.
+///
+/// On Windows, symlinks aren't created. Instead, the `(source, dest)` pairs
+/// of files to copy in their place are returned.
+#[allow(clippy::unnecessary_unwrap)]
+fn unpack_archive<R: Read>(
+    tf: &mut tar::Archive<R>,
+    absolute_path: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let mut symlinks = vec![];
+
+    for entry in tf.entries()? {
+        let mut entry = entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
+
+        // The mtimes in the archive may be 0 / UNIX epoch. This shouldn't
+        // matter. However, pip will sometimes attempt to produce a zip file of
+        // its own content and Python's zip code won't handle times before 1980,
+        // which is later than UNIX epoch. This can lead to pip blowing up at
+        // run-time. We work around this by not adjusting the mtime when
+        // extracting the archive. This effectively makes the mtime "now."
+        entry.set_preserve_mtime(false);
+
+        // Windows doesn't support symlinks without special permissions.
+        // So we track symlinks explicitly and copy files post extract if
+        // running on that platform.
+        let link_name = entry.link_name().unwrap_or(None);
+
+        if link_name.is_some() && cfg!(target_family = "windows") {
+            // The entry's path is the file to write, relative to the archive's
+            // root. We need to expand to an absolute path to facilitate copying.
+
+            // The link name is the file to symlink to, or the file we're copying.
+            // This path is relative to the entry path. So we need join with the
+            // entry's directory and canonicalize. There is also a security issue
+            // at play: archives could contain bogus symlinks pointing outside the
+            // archive. So we detect this, just in case.
+
+            let mut dest = absolute_path.to_path_buf();
+            dest.extend(entry.path()?.components());
+            let dest = dest
+                .parse_dot()
+                .with_context(|| "dedotting symlinked source")?
+                .to_path_buf();
+
+            let mut source = dest
+                .parent()
+                .ok_or_else(|| anyhow!("unable to resolve parent"))?
+                .to_path_buf();
+            source.extend(link_name.unwrap().components());
+            let source = source
+                .parse_dot()
+                .with_context(|| "dedotting symlink destination")?
+                .to_path_buf();
+
+            if !source.starts_with(absolute_path) {
+                return Err(anyhow!("malicious symlink detected in archive"));
+            }
+
+            symlinks.push((source, dest));
+        } else {
+            entry
+                .unpack_in(absolute_path)
+                .with_context(|| "unable to extract tar member")?;
+        }
+    }
+
+    Ok(symlinks)
+}
+
+/// Resolve a path relative to an extracted tree's root without touching the filesystem.
+///
+/// Returns `None` if `..` components climb above the root.
+fn normalize_within_root(path: &Path) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            std::path::Component::Normal(c) => result.push(c),
+            std::path::Component::ParentDir if !result.pop() => return None,
+            _ => {}
+        }
+    }
+
+    Some(result)
+}
+
+/// Rewrite absolute symlinks in an extracted tree to relative ones.
+///
+/// Absolute symlink targets are interpreted relative to `root`, as if `root`
+/// were the file system root, e.g. a chroot or container image. A target
+/// within `root`'s own location is interpreted as the path below it. Every
+/// symlink, relative or rewritten, must resolve within `root` or an error is
+/// returned. Afterwards, the tree remains valid when moved or mounted
+/// elsewhere.
+///
+/// The signature matches `ExtractedHook` so the function can be registered
+/// with `DistributionCache::on_extracted()` or passed to
+/// `from_tar_with_hooks()`.
+#[allow(unused)]
+pub fn relativize_symlinks(root: &Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(root) {
+        let entry = entry?;
+
+        if !entry.file_type().is_symlink() {
+            continue;
+        }
+
+        let path = entry.path();
+        let rel_path = path.strip_prefix(root)?;
+        let rel_parent = rel_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let target = std::fs::read_link(path)
+            .with_context(|| format!("reading symlink {}", path.display()))?;
+
+        let resolved = if target.is_absolute() {
+            normalize_within_root(target.strip_prefix(root).unwrap_or(&target))
+        } else {
+            normalize_within_root(&rel_parent.join(&target))
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "symlink {} -> {} escapes the distribution root",
+                rel_path.display(),
+                target.display()
+            )
+        })?;
+
+        if !target.is_absolute() {
+            continue;
+        }
+
+        let parent_components = rel_parent.components().collect::<Vec<_>>();
+        let resolved_components = resolved.components().collect::<Vec<_>>();
+        let common = parent_components
+            .iter()
+            .zip(resolved_components.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut relative = PathBuf::new();
+        for _ in common..parent_components.len() {
+            relative.push("..");
+        }
+        relative.extend(&resolved_components[common..]);
+        if relative.as_os_str().is_empty() {
+            relative.push(".");
+        }
+
+        std::fs::remove_file(path)
+            .with_context(|| format!("removing symlink {}", path.display()))?;
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&relative, path)
+            .with_context(|| format!("creating symlink {}", path.display()))?;
+
+        #[cfg(windows)]
+        {
+            if root.join(&resolved).is_dir() {
+                std::os::windows::fs::symlink_dir(&relative, path)
+            } else {
+                std::os::windows::fs::symlink_file(&relative, path)
+            }
+            .with_context(|| format!("creating symlink {}", path.display()))?;
+        }
+    }
+
+    Ok(())
 }
 
 impl StandaloneDistribution {
@@ -496,20 +804,63 @@ impl StandaloneDistribution {
         location: &PythonDistributionLocation,
         distributions_dir: &Path,
     ) -> Result<Self> {
-        let (archive_path, extract_path) =
-            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
+        Self::from_location_with_hooks(logger, location, distributions_dir, &[])
+    }
 
-        Self::from_tar_zst_file(logger, &archive_path, &extract_path)
+    /// Create an instance from a `PythonDistributionLocation`, running hooks on fresh extractions.
+    ///
+    /// See `from_tar_with_hooks()` for when `hooks` are called.
+    pub fn from_location_with_hooks(
+        logger: &slog::Logger,
+        location: &PythonDistributionLocation,
+        distributions_dir: &Path,
+        hooks: &[ExtractedHook],
+    ) -> Result<Self> {
+        Self::from_location_with_seed(logger, location, None, distributions_dir, hooks)
     }
 
-    /// Create an instance from a .tar.zst file.
+    /// Create an instance from a `PythonDistributionLocation` with seed packages overlaid.
     ///
-    /// The distribution will be extracted to ``extract_dir`` if necessary.
-    pub fn from_tar_zst_file(
+    /// Like `from_location_with_hooks()`, except the archive at `seed`, if
+    /// any, is verified and extracted over a fresh extraction before `hooks`
+    /// are called. Seeded extractions are kept in a directory keyed by the
+    /// digests of both archives, so the plain extraction of the distribution
+    /// and extractions with other seeds are left alone.
+    pub fn from_location_with_seed(
         logger: &slog::Logger,
-        path: &Path,
-        extract_dir: &Path,
+        location: &PythonDistributionLocation,
+        seed: Option<&PythonDistributionLocation>,
+        distributions_dir: &Path,
+        hooks: &[ExtractedHook],
     ) -> Result<Self> {
+        let (archive_path, mut extract_path) =
+            resolve_python_distribution_from_location(logger, location, distributions_dir)?;
+
+        let mut hooks = hooks.to_vec();
+
+        if let Some(seed) = seed {
+            let mut name = extract_path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!("-seed.{}", &seed.sha256()[0..12]));
+            extract_path.set_file_name(name);
+
+            let seed = seed.clone();
+            let overlay: ExtractedHook = Arc::new(move |root: &Path| -> Result<()> {
+                extract_members(&seed, |_| true, root)?;
+                Ok(())
+            });
+            hooks.insert(0, overlay);
+        }
+
+        let dctx = ArchiveDecoder::new(CancelableReader::new(Self::open_tar_zst_file(
+            logger,
+            &archive_path,
+        )?))?;
+
+        Self::from_tar_with_hooks(logger, dctx, &extract_path, Some(location.sha256()), &hooks)
+    }
+
+    /// Open a .tar.zst file for reading.
+    fn open_tar_zst_file(logger: &slog::Logger, path: &Path) -> Result<BufReader<std::fs::File>> {
         let basename = path
             .file_name()
             .ok_or_else(|| anyhow!("unable to determine filename"))?
@@ -522,22 +873,69 @@ impl StandaloneDistribution {
         let fh = std::fs::File::open(path)
             .with_context(|| format!("unable to open {}", path.display()))?;
 
-        let reader = BufReader::new(fh);
         warn!(logger, "reading data from Python distribution...");
 
-        Self::from_tar_zst(reader, extract_dir)
+        Ok(BufReader::new(fh))
+    }
+
+    /// Create an instance from a .tar.zst file.
+    ///
+    /// The distribution will be extracted to ``extract_dir`` if necessary.
+    pub fn from_tar_zst_file(
+        logger: &slog::Logger,
+        path: &Path,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        Self::from_tar_zst(logger, Self::open_tar_zst_file(logger, path)?, extract_dir)
     }
 
     /// Extract and analyze a standalone distribution from a zstd compressed tar stream.
-    pub fn from_tar_zst<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
-        let dctx = zstd::stream::Decoder::new(source)?;
+    pub fn from_tar_zst<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+    ) -> Result<Self> {
+        let dctx = ArchiveDecoder::new(source)?;
 
-        Self::from_tar(dctx, extract_dir)
+        Self::from_tar(logger, dctx, extract_dir)
     }
 
     /// Extract and analyze a standalone distribution from a tar stream.
-    #[allow(clippy::unnecessary_unwrap)]
-    pub fn from_tar<R: Read>(source: R, extract_dir: &Path) -> Result<Self> {
+    pub fn from_tar<R: Read>(logger: &slog::Logger, source: R, extract_dir: &Path) -> Result<Self> {
+        Self::from_tar_with_digest(logger, source, extract_dir, None)
+    }
+
+    /// Extract and analyze a standalone distribution from a tar stream having a known digest.
+    ///
+    /// `digest` is the hex SHA-256 of the archive `source` is read from. It
+    /// is recorded in the directory's extraction marker. An existing
+    /// extraction is only reused if its marker records the same digest, so an
+    /// extraction of different content is replaced rather than reused. If
+    /// `None`, any complete extraction is reused.
+    pub fn from_tar_with_digest<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+        digest: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_tar_with_hooks(logger, source, extract_dir, digest, &[])
+    }
+
+    /// Extract and analyze a standalone distribution, running hooks on the extracted tree.
+    ///
+    /// Like `from_tar_with_digest()`, except each of `hooks` is called in
+    /// order with the root of a fresh extraction before it is marked complete.
+    /// Hooks may modify the tree, e.g. to patch files. The tree is moved into
+    /// place at `extract_dir` afterwards, so hooks shouldn't retain the path
+    /// they are given. An error from a hook aborts extraction and is returned.
+    /// Hooks aren't called when an existing extraction is reused.
+    pub fn from_tar_with_hooks<R: Read>(
+        logger: &slog::Logger,
+        source: R,
+        extract_dir: &Path,
+        digest: Option<&str>,
+        hooks: &[ExtractedHook],
+    ) -> Result<Self> {
         let mut tf = tar::Archive::new(source);
 
         {
@@ -545,97 +943,112 @@ impl StandaloneDistribution {
 
             // The content of the distribution could change between runs. But caching
             // the extraction does keep things fast.
-            let test_path = extract_dir.join("python").join("PYTHON.json");
-            if !test_path.exists() {
-                std::fs::create_dir_all(extract_dir)?;
-                let absolute_path = std::fs::canonicalize(extract_dir)?;
-
-                let mut symlinks = vec![];
-
-                for entry in tf.entries()? {
-                    let mut entry =
-                        entry.map_err(|e| anyhow!("failed to iterate over archive: {}", e))?;
-
-                    // The mtimes in the archive may be 0 / UNIX epoch. This shouldn't
-                    // matter. However, pip will sometimes attempt to produce a zip file of
-                    // its own content and Python's zip code won't handle times before 1980,
-                    // which is later than UNIX epoch. This can lead to pip blowing up at
-                    // run-time. We work around this by not adjusting the mtime when
-                    // extracting the archive. This effectively makes the mtime "now."
-                    entry.set_preserve_mtime(false);
-
-                    // Windows doesn't support symlinks without special permissions.
-                    // So we track symlinks explicitly and copy files post extract if
-                    // running on that platform.
-                    let link_name = entry.link_name().unwrap_or(None);
-
-                    if link_name.is_some() && cfg!(target_family = "windows") {
-                        // The entry's path is the file to write, relative to the archive's
-                        // root. We need to expand to an absolute path to facilitate copying.
-
-                        // The link name is the file to symlink to, or the file we're copying.
-                        // This path is relative to the entry path. So we need join with the
-                        // entry's directory and canonicalize. There is also a security issue
-                        // at play: archives could contain bogus symlinks pointing outside the
-                        // archive. So we detect this, just in case.
-
-                        let mut dest = absolute_path.clone();
-                        dest.extend(entry.path()?.components());
-                        let dest = dest
-                            .parse_dot()
-                            .with_context(|| "dedotting symlinked source")?
-                            .to_path_buf();
-
-                        let mut source = dest
-                            .parent()
-                            .ok_or_else(|| anyhow!("unable to resolve parent"))?
-                            .to_path_buf();
-                        source.extend(link_name.unwrap().components());
-                        let source = source
-                            .parse_dot()
-                            .with_context(|| "dedotting symlink destination")?
-                            .to_path_buf();
-
-                        if !source.starts_with(&absolute_path) {
-                            return Err(anyhow!("malicious symlink detected in archive"));
-                        }
+            //
+            // Extraction is performed into a temporary directory which is renamed
+            // into place once extraction completes. A marker file written as the
+            // last step of extraction denotes a complete extraction. This ensures an
+            // interrupted extraction never leaves a half-populated directory behind
+            // that subsequent runs would attempt to use. The marker records the
+            // digest of the extracted archive, allowing us to detect extractions
+            // of different content.
+            let marker_path = extract_dir.join(EXTRACT_COMPLETE_MARKER);
+            let reusable = match (std::fs::read_to_string(&marker_path), digest) {
+                (Ok(recorded), Some(digest)) => recorded.trim() == digest,
+                (Ok(_), None) => true,
+                (Err(_), _) => false,
+            };
 
-                        symlinks.push((source, dest));
-                    } else {
-                        entry
-                            .unpack_in(&absolute_path)
-                            .with_context(|| "unable to extract tar member")?;
-                    }
-                }
+            if !reusable {
+                let partial_dir = partial_extract_dir(extract_dir)?;
 
-                for (source, dest) in symlinks {
-                    std::fs::copy(&source, &dest).with_context(|| {
+                // We hold the extraction lock. So any existing partial directory
+                // is from an interrupted extraction and is safe to remove.
+                if partial_dir.exists() {
+                    remove_dir_all::remove_dir_all(&partial_dir).with_context(|| {
                         format!(
-                            "copying symlinked file {} -> {}",
-                            source.display(),
-                            dest.display(),
+                            "removing stale partial extraction {}",
+                            partial_dir.display()
                         )
                     })?;
                 }
 
-                // Ensure unpacked files are writable. We've had issues where we
-                // consume archives with read-only file permissions. When we later
-                // copy these files, we can run into trouble overwriting a read-only
-                // file.
-                let walk = walkdir::WalkDir::new(&absolute_path);
-                for entry in walk.into_iter() {
-                    let entry = entry?;
-
-                    let metadata = entry.metadata()?;
-                    let mut permissions = metadata.permissions();
-
-                    if permissions.readonly() {
-                        permissions.set_readonly(false);
-                        std::fs::set_permissions(entry.path(), permissions).with_context(|| {
-                            format!("unable to mark {} as writable", entry.path().display())
+                std::fs::create_dir_all(&partial_dir)?;
+                let absolute_path = std::fs::canonicalize(&partial_dir)?;
+
+                // Don't leave a partial extraction behind on failure, e.g. when
+                // the operation is cancelled.
+                let populated = (|| -> Result<()> {
+                    let _permit = acquire_extraction_permit();
+                    let symlinks = unpack_archive(&mut tf, &absolute_path)?;
+
+                    for (source, dest) in symlinks {
+                        std::fs::copy(&source, &dest).with_context(|| {
+                            format!(
+                                "copying symlinked file {} -> {}",
+                                source.display(),
+                                dest.display(),
+                            )
                         })?;
                     }
+
+                    // Check permissions as found in the archive, before we alter them.
+                    apply_permission_policy(logger, &absolute_path)?;
+
+                    // Ensure unpacked files are writable. We've had issues where we
+                    // consume archives with read-only file permissions. When we later
+                    // copy these files, we can run into trouble overwriting a read-only
+                    // file.
+                    let walk = walkdir::WalkDir::new(&absolute_path);
+                    for entry in walk.into_iter() {
+                        let entry = entry?;
+
+                        let metadata = entry.metadata()?;
+                        let mut permissions = metadata.permissions();
+
+                        if permissions.readonly() {
+                            permissions.set_readonly(false);
+                            std::fs::set_permissions(entry.path(), permissions).with_context(
+                                || format!("unable to mark {} as writable", entry.path().display()),
+                            )?;
+                        }
+                    }
+
+                    for hook in hooks {
+                        hook(&absolute_path).context("running post-extraction hook")?;
+                    }
+
+                    std::fs::write(
+                        absolute_path.join(EXTRACT_COMPLETE_MARKER),
+                        digest.unwrap_or_default(),
+                    )
+                    .context("writing extraction completion marker")?;
+
+                    check_cancelled()?;
+
+                    Ok(())
+                })();
+
+                if let Err(e) = populated {
+                    remove_dir_all::remove_dir_all(&partial_dir).ok();
+                    return Err(e);
                 }
+
+                // A directory without a matching marker is an incomplete extraction
+                // from an older version or an interrupted run, or an extraction of
+                // different content. Replace it.
+                if extract_dir.exists() {
+                    remove_dir_all::remove_dir_all(extract_dir).with_context(|| {
+                        format!("removing incomplete extraction {}", extract_dir.display())
+                    })?;
+                }
+
+                std::fs::rename(&partial_dir, extract_dir).with_context(|| {
+                    format!(
+                        "renaming {} to {}",
+                        partial_dir.display(),
+                        extract_dir.display()
+                    )
+                })?;
             }
         }
 
@@ -761,6 +1174,8 @@ impl StandaloneDistribution {
                 .clone(),
         };
 
+        let libffi_source = libffi_source(&pi.build_info);
+
         // Collect extension modules.
         for (module, variants) in &pi.build_info.extensions {
             let mut ems = PythonExtensionModuleVariants::default();
@@ -948,6 +1363,7 @@ impl StandaloneDistribution {
         };
 
         let inittab_object = python_path.join(pi.build_info.inittab_object);
+        let layout = DistributionLayout::from_python_json_path(&pi.python_exe);
 
         Ok(Self {
             base_dir: dist_dir.to_path_buf(),
@@ -976,6 +1392,7 @@ impl StandaloneDistribution {
             frozen_c,
             includes,
             links_core,
+            libffi_source,
             libraries,
             objs_core,
             libpython_shared_library,
@@ -988,9 +1405,56 @@ impl StandaloneDistribution {
             module_suffixes,
             crt_features: pi.crt_features,
             config_vars: pi.python_config_vars,
+            cpython_commit: pi.cpython_commit,
+            library_build_modes: pi.library_build_modes.unwrap_or_default(),
+            layout,
         })
     }
 
+    /// The git commit of the CPython source this distribution was built from.
+    ///
+    /// Read from the `cpython_commit` key of `PYTHON.json`. `None` if the
+    /// distribution doesn't record it.
+    #[allow(unused)]
+    pub fn cpython_commit(&self) -> Option<&str> {
+        self.cpython_commit.as_deref()
+    }
+
+    /// Build modes of bundled libraries, keyed by library.
+    ///
+    /// e.g. `sqlite3` => `threadsafe` or `openssl` => `fips`. Read from the
+    /// `library_build_modes` key of `PYTHON.json`. Libraries missing from the
+    /// map have an unknown build mode.
+    #[allow(unused)]
+    pub fn library_build_modes(&self) -> &BTreeMap<String, String> {
+        &self.library_build_modes
+    }
+
+    /// SPDX licenses of the interpreter and the libraries bundled by its extension modules.
+    ///
+    /// Derived from the license metadata in the distribution's `PYTHON.json`,
+    /// for feeding compliance processes such as SBOM generation. Licenses of
+    /// every extension module variant are included, whether or not a build
+    /// uses it.
+    #[allow(unused)]
+    pub fn license_summary(&self) -> LicenseSummary {
+        LicenseSummary::from_components(
+            self.core_license.as_ref(),
+            self.extension_modules
+                .values()
+                .flat_map(|variants| variants.iter())
+                .filter_map(|em| em.license.as_ref()),
+        )
+    }
+
+    /// The directory layout of this distribution.
+    ///
+    /// Derived from the location of `python_exe` in `PYTHON.json`.
+    #[allow(unused)]
+    pub fn layout(&self) -> DistributionLayout {
+        self.layout
+    }
+
     /// Determines support for building a libpython from this distribution.
     ///
     /// Returns a tuple of bools indicating whether this distribution can
@@ -1011,8 +1475,9 @@ impl StandaloneDistribution {
                 self.libpython_shared_library.is_some(),
             )
         } else if self.target_triple.contains("linux-musl") {
-            // Musl binaries don't support dynamic linking.
-            (true, false)
+            // Musl distributions have historically been static only. Only allow
+            // dynamic linking if the distribution provides a shared libpython.
+            (true, self.libpython_shared_library.is_some())
         } else {
             // Elsewhere we can choose which link mode to use.
             (true, true)
@@ -1453,14 +1918,668 @@ impl PythonDistribution for StandaloneDistribution {
 pub mod tests {
     use {
         super::*,
-        crate::testutil::*,
+        crate::{
+            py_packaging::distribution::{with_cancellation, CancellationToken, DistributionError},
+            testutil::*,
+        },
         python_packaging::{
             bytecode::CompileMode, policy::ExtensionModuleFilter,
             resource::BytecodeOptimizationLevel,
         },
+        sha2::{Digest, Sha256},
         std::collections::BTreeSet,
     };
 
+    #[test]
+    fn test_libffi_source() -> Result<()> {
+        let build_info = |links: &str| -> Result<PythonBuildInfo> {
+            Ok(serde_json::from_str(&format!(
+                r#"{{
+                    "core": {{"objs": [], "links": []}},
+                    "extensions": {{"_ctypes": [{{
+                        "in_core": false,
+                        "init_fn": "PyInit__ctypes",
+                        "links": {},
+                        "objs": [],
+                        "required": false,
+                        "variant": "default"
+                    }}]}},
+                    "inittab_object": "",
+                    "inittab_source": "",
+                    "inittab_cflags": [],
+                    "object_file_format": "elf"
+                }}"#,
+                links
+            ))?)
+        };
+
+        assert_eq!(
+            libffi_source(&build_info(
+                r#"[{"name": "ffi", "path_static": "build/lib/libffi.a"}]"#
+            )?),
+            LibSource::Bundled
+        );
+        assert_eq!(
+            libffi_source(&build_info(r#"[{"name": "ffi", "system": true}]"#)?),
+            LibSource::System
+        );
+        assert_eq!(
+            libffi_source(&build_info(r#"[{"name": "dl", "system": true}]"#)?),
+            LibSource::Absent
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_archive() -> Result<()> {
+        let mut builder = tar::Builder::new(vec![]);
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder.append_data(&mut header, "python/install/bin", std::io::empty())?;
+
+        for i in 0..64 {
+            let data = format!("file {}", i);
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(match i {
+                0 => 0o755,
+                2 => 0o4755,
+                _ => 0o644,
+            });
+            header.set_size(data.len() as u64);
+            builder.append_data(
+                &mut header,
+                format!("python/install/lib/file{}", i),
+                data.as_bytes(),
+            )?;
+        }
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_link_name("../lib/file0")?;
+        builder.append_data(&mut header, "python/install/bin/python3", std::io::empty())?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        header.set_link_name("python/install/lib/file1")?;
+        builder.append_data(&mut header, "python/install/lib/hardlink", std::io::empty())?;
+
+        let data = builder.into_inner()?;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let dest = std::fs::canonicalize(td.path())?;
+
+        let symlinks = unpack_archive(&mut tar::Archive::new(data.as_slice()), &dest)?;
+        for (source, dest) in symlinks {
+            std::fs::copy(source, dest)?;
+        }
+
+        let lib = dest.join("python/install/lib");
+        for i in 0..64 {
+            assert_eq!(
+                std::fs::read_to_string(lib.join(format!("file{}", i)))?,
+                format!("file {}", i)
+            );
+        }
+        assert_eq!(std::fs::read_to_string(lib.join("hardlink"))?, "file 1");
+        assert_eq!(
+            std::fs::read_to_string(dest.join("python/install/bin/python3"))?,
+            "file 0"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            assert_eq!(
+                std::fs::metadata(lib.join("file0"))?.permissions().mode() & 0o777,
+                0o755
+            );
+            assert_eq!(
+                std::fs::metadata(lib.join("file1"))?.permissions().mode() & 0o777,
+                0o644
+            );
+            // Like `tar::Entry::unpack_in()`, only permission bits are applied.
+            assert_eq!(
+                std::fs::metadata(lib.join("file2"))?.permissions().mode() & 0o7777,
+                0o755
+            );
+            assert!(
+                std::fs::symlink_metadata(dest.join("python/install/bin/python3"))?
+                    .file_type()
+                    .is_symlink()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_archive_large_members() -> Result<()> {
+        let large = vec![b'x'; 4 << 20];
+
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, data) in [
+            ("python/small", b"small".as_ref()),
+            ("python/large", large.as_slice()),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            builder.append_data(&mut header, name, data)?;
+        }
+        let data = builder.into_inner()?;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let dest = std::fs::canonicalize(td.path())?;
+
+        unpack_archive(&mut tar::Archive::new(data.as_slice()), &dest)?;
+        assert_eq!(std::fs::read(dest.join("python/small"))?, b"small");
+        assert_eq!(std::fs::read(dest.join("python/large"))?, large);
+
+        // A header can claim any size. Nothing that large is allocated.
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_path("python/huge")?;
+        header.set_size(1 << 60);
+        header.set_cksum();
+        builder.append(&header, b"short".as_ref())?;
+        let mut data = builder.into_inner()?;
+        data.truncate(1024);
+
+        assert!(unpack_archive(&mut tar::Archive::new(data.as_slice()), &dest).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_archive_dot_prefixed() -> Result<()> {
+        // `tar::Builder` strips leading `./`, so members are named directly,
+        // like `tar -C dir -cf x.tar .` names them.
+        let mut builder = tar::Builder::new(vec![]);
+        for (name, entry_type, link_name, data) in [
+            ("./", tar::EntryType::Directory, None, ""),
+            ("./python/", tar::EntryType::Directory, None, ""),
+            ("./python/PYTHON.json", tar::EntryType::Regular, None, "{}"),
+            (
+                "./python/link.json",
+                tar::EntryType::Link,
+                Some("./python/PYTHON.json"),
+                "",
+            ),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(entry_type);
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            if let Some(link_name) = link_name {
+                header.as_gnu_mut().unwrap().linkname[..link_name.len()]
+                    .copy_from_slice(link_name.as_bytes());
+            }
+            header.set_cksum();
+            builder.append(&header, data.as_bytes())?;
+        }
+        let data = builder.into_inner()?;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let dest = std::fs::canonicalize(td.path())?;
+
+        unpack_archive(&mut tar::Archive::new(data.as_slice()), &dest)?;
+        assert_eq!(
+            std::fs::read_to_string(dest.join("python/PYTHON.json"))?,
+            "{}"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("python/link.json"))?,
+            "{}"
+        );
+
+        // Members outside of the destination are never written.
+        let nested = dest.join("nested");
+        std::fs::create_dir(&nested)?;
+        for name in ["../escaped.json", "./../escaped.json"] {
+            let mut builder = tar::Builder::new(vec![]);
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(2);
+            header.set_cksum();
+            builder.append(&header, b"{}".as_ref())?;
+            let data = builder.into_inner()?;
+
+            unpack_archive(&mut tar::Archive::new(data.as_slice()), &nested).ok();
+            assert!(!dest.join("escaped.json").exists());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unpack_archive_symlinked_parent() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let outside = td.path().join("outside");
+        std::fs::create_dir(&outside)?;
+
+        let archive = |member: &str, entry_type: tar::EntryType| -> Result<Vec<u8>> {
+            let mut builder = tar::Builder::new(vec![]);
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_link_name(&outside)?;
+            builder.append_data(&mut header, "python/escape", std::io::empty())?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::file());
+            header.set_mode(0o644);
+            header.set_size(4);
+            builder.append_data(&mut header, "python/content", "data".as_bytes())?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_mode(0o644);
+            if entry_type.is_hard_link() {
+                header.set_size(0);
+                header.set_link_name("python/content")?;
+                builder.append_data(&mut header, member, std::io::empty())?;
+            } else {
+                header.set_size(4);
+                builder.append_data(&mut header, member, "evil".as_bytes())?;
+            }
+
+            Ok(builder.into_inner()?)
+        };
+
+        for (i, entry_type) in [tar::EntryType::file(), tar::EntryType::Link]
+            .iter()
+            .enumerate()
+        {
+            let dest = td.path().join(format!("dest{}", i));
+            std::fs::create_dir(&dest)?;
+
+            assert!(unpack_archive(
+                &mut tar::Archive::new(archive("python/escape/evil", *entry_type)?.as_slice()),
+                &std::fs::canonicalize(&dest)?,
+            )
+            .is_err());
+            assert!(!outside.join("evil").exists());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_digest_marker() -> Result<()> {
+        let archive = |content: &str| -> Result<Vec<u8>> {
+            let mut builder = tar::Builder::new(vec![]);
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(content.len() as u64);
+            builder.append_data(&mut header, "python/content", content.as_bytes())?;
+
+            Ok(builder.into_inner()?)
+        };
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let extract_dir = td.path().join("dist");
+        let content_path = extract_dir.join("python").join("content");
+
+        // These aren't real distributions, so analysis after extraction fails.
+        let extract = |content: &str, digest: &str| -> Result<()> {
+            assert!(StandaloneDistribution::from_tar_with_digest(
+                &get_logger()?,
+                archive(content)?.as_slice(),
+                &extract_dir,
+                Some(digest)
+            )
+            .is_err());
+
+            Ok(())
+        };
+
+        extract("first", "aa")?;
+        assert_eq!(std::fs::read_to_string(&content_path)?, "first");
+        assert_eq!(
+            std::fs::read_to_string(extract_dir.join(EXTRACT_COMPLETE_MARKER))?,
+            "aa"
+        );
+
+        // A matching digest reuses the existing extraction.
+        extract("second", "aa")?;
+        assert_eq!(std::fs::read_to_string(&content_path)?, "first");
+
+        // A different digest extracts again.
+        extract("third", "bb")?;
+        assert_eq!(std::fs::read_to_string(&content_path)?, "third");
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relativize_symlinks() -> Result<()> {
+        let archive = |links: &[(&str, &str)]| -> Result<Vec<u8>> {
+            let mut builder = tar::Builder::new(vec![]);
+
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o755);
+            header.set_size(5);
+            builder.append_data(&mut header, "python/install/lib/file0", "file0".as_bytes())?;
+
+            for (path, target) in links {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                header.set_link_name(target)?;
+                builder.append_data(&mut header, path, std::io::empty())?;
+            }
+
+            Ok(builder.into_inner()?)
+        };
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let root = std::fs::canonicalize(td.path())?.join("dist");
+        std::fs::create_dir(&root)?;
+
+        let data = archive(&[
+            ("python/install/bin/python3", "/python/install/lib/file0"),
+            ("python/install/bin/python", "python3"),
+            ("python/lib", "/python/install/lib"),
+            ("python/self", "/python/"),
+        ])?;
+        assert!(unpack_archive(&mut tar::Archive::new(data.as_slice()), &root)?.is_empty());
+
+        // Links to the root's own location are rewritten too.
+        std::os::unix::fs::symlink(
+            root.join("python/install/lib/file0"),
+            root.join("python/abs"),
+        )?;
+
+        relativize_symlinks(&root)?;
+
+        let link = |path: &str| std::fs::read_link(root.join(path));
+        assert_eq!(
+            link("python/install/bin/python3")?,
+            PathBuf::from("../lib/file0")
+        );
+        assert_eq!(link("python/install/bin/python")?, PathBuf::from("python3"));
+        assert_eq!(link("python/lib")?, PathBuf::from("install/lib"));
+        assert_eq!(link("python/self")?, PathBuf::from("."));
+        assert_eq!(link("python/abs")?, PathBuf::from("install/lib/file0"));
+
+        // Links remain valid once the tree is relocated.
+        let moved = root.with_file_name("moved");
+        std::fs::rename(&root, &moved)?;
+        for path in [
+            "python/install/bin/python3",
+            "python/install/bin/python",
+            "python/lib/file0",
+            "python/abs",
+        ] {
+            assert_eq!(std::fs::read_to_string(moved.join(path))?, "file0");
+        }
+
+        for escaping in ["../../../../etc/passwd", "/../etc/passwd"] {
+            let escape_root = td.path().join("escape");
+            std::fs::create_dir_all(&escape_root)?;
+            unpack_archive(
+                &mut tar::Archive::new(
+                    archive(&[("python/install/bin/python3", escaping)])?.as_slice(),
+                ),
+                &escape_root,
+            )?;
+
+            let err = relativize_symlinks(&escape_root).unwrap_err();
+            assert!(err.to_string().contains("escapes the distribution root"));
+            remove_dir_all::remove_dir_all(&escape_root)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seed_packages() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let write_archive = |name: &str, member: &str| -> Result<PythonDistributionLocation> {
+            let mut builder = tar::Builder::new(vec![]);
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(4);
+            builder.append_data(&mut header, member, "data".as_bytes())?;
+            let data = zstd::stream::encode_all(builder.into_inner()?.as_slice(), 3)?;
+
+            let path = td.path().join(name);
+            std::fs::write(&path, &data)?;
+
+            Ok(PythonDistributionLocation::Local {
+                local_path: path.display().to_string(),
+                sha256: hex::encode(Sha256::digest(&data)),
+            })
+        };
+
+        let location = write_archive("dist.tar.zst", "python/content")?;
+        let seed = write_archive("seed.tar.zst", "python/install/seeded.py")?;
+        let other_seed = write_archive("other-seed.tar.zst", "python/install/other.py")?;
+        let distributions_dir = td.path().join("distributions");
+
+        // These aren't real distributions, so analysis after extraction fails.
+        for seed in [None, Some(&seed), Some(&other_seed)] {
+            assert!(StandaloneDistribution::from_location_with_seed(
+                &get_logger()?,
+                &location,
+                seed,
+                &distributions_dir,
+                &[],
+            )
+            .is_err());
+        }
+
+        let plain = distributions_dir.join(format!("python.{}", &location.sha256()[0..12]));
+        let seeded = |seed: &PythonDistributionLocation| {
+            distributions_dir.join(format!(
+                "python.{}-seed.{}",
+                &location.sha256()[0..12],
+                &seed.sha256()[0..12]
+            ))
+        };
+
+        assert!(plain.join("python/content").exists());
+        assert!(!plain.join("python/install").exists());
+        assert!(seeded(&seed).join("python/content").exists());
+        assert!(seeded(&seed).join("python/install/seeded.py").exists());
+        assert!(!seeded(&seed).join("python/install/other.py").exists());
+        assert!(seeded(&other_seed).join("python/install/other.py").exists());
+        assert!(!seeded(&other_seed)
+            .join("python/install/seeded.py")
+            .exists());
+
+        // A seed not matching its digest fails extraction.
+        let tampered = PythonDistributionLocation::Local {
+            local_path: td.path().join("seed.tar.zst").display().to_string(),
+            sha256: "00".repeat(32),
+        };
+        let err = StandaloneDistribution::from_location_with_seed(
+            &get_logger()?,
+            &location,
+            Some(&tampered),
+            &distributions_dir,
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("CHECKSUM"));
+        assert!(!seeded(&tampered).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_hooks() -> Result<()> {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder.append_data(&mut header, "python/content", "first".as_bytes())?;
+        let archive = builder.into_inner()?;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let extract_dir = td.path().join("dist");
+
+        let patch: ExtractedHook = Arc::new(|root: &Path| -> Result<()> {
+            std::fs::write(root.join("python").join("sitecustomize.py"), "patched")?;
+            Ok(())
+        });
+        let fail: ExtractedHook = Arc::new(|_: &Path| -> Result<()> { Err(anyhow!("refused")) });
+
+        let err = StandaloneDistribution::from_tar_with_hooks(
+            &get_logger()?,
+            archive.as_slice(),
+            &extract_dir,
+            Some("aa"),
+            &[patch.clone(), fail],
+        )
+        .unwrap_err();
+        assert!(format!("{:?}", err).contains("refused"));
+        assert!(!extract_dir.join(EXTRACT_COMPLETE_MARKER).exists());
+
+        // These aren't real distributions, so analysis after extraction fails.
+        assert!(StandaloneDistribution::from_tar_with_hooks(
+            &get_logger()?,
+            archive.as_slice(),
+            &extract_dir,
+            Some("aa"),
+            &[patch]
+        )
+        .is_err());
+        assert_eq!(
+            std::fs::read_to_string(extract_dir.join("python").join("sitecustomize.py"))?,
+            "patched"
+        );
+        assert!(extract_dir.join(EXTRACT_COMPLETE_MARKER).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_license_summary() -> Result<()> {
+        let core = LicensedComponent::new_spdx("cpython", "Python-2.0")?;
+        let ssl = LicensedComponent::new_spdx("_ssl", "OpenSSL")?;
+        let ctypes = LicensedComponent::new_spdx("_ctypes", "Python-2.0")?;
+        let sqlite = LicensedComponent::new_public_domain("_sqlite3");
+        let readline = LicensedComponent::new_spdx("readline", "GPL-3.0 OR MIT")?;
+
+        let summary = LicenseSummary::from_components(
+            Some(&core),
+            [&ssl, &ctypes, &sqlite, &readline].iter().copied(),
+        );
+
+        assert_eq!(
+            summary.interpreter,
+            ["Python-2.0".to_string()].iter().cloned().collect()
+        );
+        assert_eq!(
+            summary.extension_modules.keys().collect::<Vec<_>>(),
+            ["_ssl", "readline"]
+        );
+        assert_eq!(
+            summary.public_domain.iter().collect::<Vec<_>>(),
+            ["_sqlite3"]
+        );
+        assert_eq!(
+            summary.all().into_iter().collect::<Vec<_>>(),
+            ["GPL-3.0", "MIT", "OpenSSL", "Python-2.0"]
+        );
+
+        assert_eq!(
+            LicenseSummary::from_components(None, std::iter::empty()),
+            LicenseSummary::default()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_cancelled() -> Result<()> {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(5);
+        builder.append_data(&mut header, "python/content", "first".as_bytes())?;
+        let archive = builder.into_inner()?;
+
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+        let extract_dir = td.path().join("dist");
+
+        let token = CancellationToken::new();
+        let cancel: ExtractedHook = {
+            let token = token.clone();
+            Arc::new(move |_: &Path| -> Result<()> {
+                token.cancel();
+                Ok(())
+            })
+        };
+
+        let err = with_cancellation(&token, || {
+            StandaloneDistribution::from_tar_with_hooks(
+                &get_logger()?,
+                CancelableReader::new(archive.as_slice()),
+                &extract_dir,
+                Some("aa"),
+                &[cancel],
+            )
+        })
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("CANCELLED"));
+        assert!(!extract_dir.exists());
+        assert!(!partial_extract_dir(&extract_dir)?.exists());
+
+        // Reads fail once cancelled, before anything is extracted.
+        let err = with_cancellation(&token, || {
+            StandaloneDistribution::from_tar_with_hooks(
+                &get_logger()?,
+                CancelableReader::new(archive.as_slice()),
+                &extract_dir,
+                Some("aa"),
+                &[],
+            )
+        })
+        .unwrap_err();
+        assert_eq!(DistributionError::code_of(&err), Some("CANCELLED"));
+        assert!(!extract_dir.exists());
+        assert!(!partial_extract_dir(&extract_dir)?.exists());
+
+        // The token only applies within `with_cancellation()`.
+        let mut data = vec![];
+        CancelableReader::new(archive.as_slice()).read_to_end(&mut data)?;
+        assert_eq!(data, archive);
+
+        Ok(())
+    }
+
     #[test]
     fn test_stdlib_annotations() -> Result<()> {
         let distribution = get_default_distribution()?;
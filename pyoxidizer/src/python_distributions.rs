@@ -5,238 +5,5921 @@
 //! Defines known Python distributions.
 
 use {
-    crate::py_packaging::distribution::{
-        DistributionFlavor, PythonDistributionLocation, PythonDistributionRecord,
+    crate::py_packaging::{
+        distribution::{
+            cached_distribution_path, check_location_availability, distribution_extension_modules,
+            distribution_library_build_modes, summarize_archive, ArchiveSummary,
+            BuildOptimizations, DistributionError, DistributionFlavor, DistributionLayout,
+            PythonDistributionLocation, PythonDistributionRecord,
+        },
+        standalone_distribution::StandaloneDistribution,
     },
+    anyhow::{anyhow, Result},
     itertools::Itertools,
     once_cell::sync::Lazy,
+    serde::Serialize,
+    sha2::{Digest, Sha256},
+    slog::warn,
+    std::{
+        borrow::Borrow,
+        collections::{BTreeMap, BTreeSet},
+        fmt,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex, RwLock,
+        },
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
 };
 
-pub struct PythonDistributionCollection {
-    dists: Vec<PythonDistributionRecord>,
-}
+/// A pre-release of a Python version.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum PreRelease {
+    /// An alpha release. e.g. `a1`.
+    Alpha(u64),
+    /// A beta release. e.g. `b2`.
+    Beta(u64),
+    /// A release candidate. e.g. `rc1`.
+    ReleaseCandidate(u64),
+}
+
+/// A parsed Python version.
+///
+/// Versions consist of a `X.Y` or `X.Y.Z` release. Per PEP 440, the release may
+/// be preceded by an `N!` epoch and followed by a pre-release suffix, then
+/// a post-release suffix. e.g. `3.9.6`, `3.10.0rc1`, or `1!3.9.6.post1`.
+///
+/// Versions are ordered per PEP 440: by epoch, then numerically by release,
+/// with pre-releases before and post-releases after their release. A missing
+/// patch component compares as `0`. So `3.9` == `3.9.0` < `3.10`. `Display`
+/// renders the normalized form. See `normalize_python_version()`.
+#[derive(Clone, Debug)]
+pub struct PythonVersion {
+    pub epoch: u64,
+    pub major: u64,
+    pub minor: u64,
+    /// `None` for `X.Y` versions.
+    pub patch: Option<u64>,
+    pub pre: Option<PreRelease>,
+    pub post: Option<u64>,
+}
+
+impl PythonVersion {
+    /// Construct a final `X.Y.Z` release version.
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            epoch: 0,
+            major,
+            minor,
+            patch: Some(patch),
+            pre: None,
+            post: None,
+        }
+    }
+
+    /// The `X.Y` major.minor version string.
+    pub fn major_minor(&self) -> String {
+        format!("{}.{}", self.major, self.minor)
+    }
+
+    /// Release components. e.g. `[3, 9, 6]`.
+    pub fn release(&self) -> Vec<u64> {
+        let mut release = vec![self.major, self.minor];
+        release.extend(self.patch);
+        release
+    }
+
+    fn sort_key(&self) -> (u64, [u64; 3], bool, Option<PreRelease>, Option<u64>) {
+        (
+            self.epoch,
+            [self.major, self.minor, self.patch.unwrap_or(0)],
+            // Final releases sort after their pre-releases.
+            self.pre.is_none(),
+            self.pre,
+            self.post,
+        )
+    }
+}
+
+impl PartialEq for PythonVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.sort_key() == other.sort_key()
+    }
+}
+
+impl Eq for PythonVersion {}
+
+impl PartialOrd for PythonVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PythonVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.epoch != 0 {
+            write!(f, "{}!", self.epoch)?;
+        }
+
+        write!(f, "{}.{}", self.major, self.minor)?;
+
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+
+        match self.pre {
+            Some(PreRelease::Alpha(n)) => write!(f, "a{}", n)?,
+            Some(PreRelease::Beta(n)) => write!(f, "b{}", n)?,
+            Some(PreRelease::ReleaseCandidate(n)) => write!(f, "rc{}", n)?,
+            None => {}
+        }
+
+        if let Some(post) = self.post {
+            write!(f, ".post{}", post)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(version: &str) -> Result<Self> {
+        let invalid = || {
+            anyhow!(
+                "invalid Python version {}; expected X.Y or X.Y.Z with an optional a, b, or rc pre-release suffix",
+                version
+            )
+        };
+        let number = |value: &str| -> Result<u64> {
+            if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+                Err(invalid())
+            } else {
+                value.parse::<u64>().map_err(|_| invalid())
+            }
+        };
+
+        let normalized = version.trim().to_lowercase();
+
+        let (epoch, normalized) = match normalized.split_once('!') {
+            Some((epoch, rest)) => (number(epoch)?, rest),
+            None => (0, normalized.as_str()),
+        };
+
+        let release_end = normalized
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(normalized.len());
+        let (release, mut suffix) = normalized.split_at(release_end);
+
+        // A `.` may separate the release from the pre-release suffix.
+        let release = if suffix.is_empty() {
+            release
+        } else {
+            release.strip_suffix('.').unwrap_or(release)
+        };
+
+        let components = release.split('.').map(number).collect::<Result<Vec<_>>>()?;
+        if !(2..=3).contains(&components.len()) {
+            return Err(invalid());
+        }
+
+        let mut pre = None;
+        let mut post = None;
+
+        while !suffix.is_empty() {
+            let rest = suffix.trim_start_matches(['-', '_', '.']);
+
+            let letters_end = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let (letters, rest) = rest.split_at(letters_end);
+
+            let rest = rest.trim_start_matches(['-', '_', '.']);
+            let digits_end = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (digits, rest) = rest.split_at(digits_end);
+            let n = if digits.is_empty() {
+                0
+            } else {
+                number(digits)?
+            };
+
+            match letters {
+                "a" | "alpha" if pre.is_none() && post.is_none() => {
+                    pre = Some(PreRelease::Alpha(n));
+                }
+                "b" | "beta" if pre.is_none() && post.is_none() => {
+                    pre = Some(PreRelease::Beta(n));
+                }
+                "rc" | "c" | "pre" | "preview" if pre.is_none() && post.is_none() => {
+                    pre = Some(PreRelease::ReleaseCandidate(n));
+                }
+                "post" | "rev" | "r" if post.is_none() => {
+                    post = Some(n);
+                }
+                _ => return Err(invalid()),
+            }
+
+            suffix = rest;
+        }
+
+        Ok(Self {
+            epoch,
+            major: components[0],
+            minor: components[1],
+            patch: components.get(2).copied(),
+            pre,
+            post,
+        })
+    }
+}
+
+/// Normalize a Python version string.
+///
+/// Versions are parsed as a `PythonVersion`. Suffixes are normalized per
+/// PEP 440: an optional `-`, `_`, or `.` separator may precede a suffix and
+/// its number, `alpha`, `beta`, `c`, `pre`, and `preview` are spelled `a`, `b`,
+/// and `rc`, `rev` and `r` are spelled `.post`, and a missing number is `0`.
+/// e.g. `3.9.7-RC1`, `3.9.7.rc.1`, and `3.9.7rc1` all normalize to `3.9.7rc1`.
+#[allow(unused)]
+pub fn normalize_python_version(version: &str) -> Result<String> {
+    Ok(version.parse::<PythonVersion>()?.to_string())
+}
+
+/// Describes the version specifier forms accepted by `VersionSpecifier::parse()`.
+const ACCEPTED_VERSION_FORMS: &str = "expected a version like 3.9 or 3.9.6, or comma separated specifiers like ==3.9.*, >=3.8, <3.10, ~=3.8, or !=3.8.5";
+
+/// A comparison operator in a version specifier clause.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum VersionOperator {
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    LessEqual,
+    Greater,
+    Less,
+    Compatible,
+}
+
+/// A single clause of a version specifier. e.g. `>=3.8`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct VersionClause {
+    operator: VersionOperator,
+    /// Release components of the version to compare against.
+    release: Vec<u64>,
+    /// Version to compare against. `None` for wildcards like `==3.*`.
+    version: Option<PythonVersion>,
+    /// Whether the version ended in `.*`.
+    wildcard: bool,
+}
+
+/// A pip style Python version specifier. e.g. `>=3.8,<3.10` or `==3.9.*`.
+///
+/// Supports the `==`, `!=`, `>=`, `<=`, `>`, `<`, and `~=` operators of PEP 440.
+/// `==` and `!=` may use a trailing `.*` wildcard. Pre-release versions only
+/// satisfy a specifier if one of its clauses names a pre-release.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionSpecifier {
+    clauses: Vec<VersionClause>,
+}
+
+impl VersionSpecifier {
+    /// Whether a string looks like a version specifier rather than a version.
+    pub fn is_specifier(value: &str) -> bool {
+        value.trim_start().starts_with(['=', '!', '<', '>', '~'])
+    }
+
+    /// Parse a version specifier.
+    pub fn parse(value: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            anyhow!(
+                "invalid Python version specifier {}: {}; {}",
+                value,
+                reason,
+                ACCEPTED_VERSION_FORMS
+            )
+        };
+
+        let mut clauses = vec![];
+
+        for clause in value.split(',') {
+            let clause = clause.trim();
+
+            let (operator, version) = [
+                ("==", VersionOperator::Equal),
+                ("!=", VersionOperator::NotEqual),
+                (">=", VersionOperator::GreaterEqual),
+                ("<=", VersionOperator::LessEqual),
+                ("~=", VersionOperator::Compatible),
+                (">", VersionOperator::Greater),
+                ("<", VersionOperator::Less),
+            ]
+            .iter()
+            .find_map(|(prefix, operator)| {
+                clause
+                    .strip_prefix(prefix)
+                    .map(|version| (*operator, version.trim()))
+            })
+            .ok_or_else(|| invalid(&format!("clause {} lacks an operator", clause)))?;
+
+            let (version, wildcard) = match version.strip_suffix(".*") {
+                Some(version) => (version, true),
+                None => (version, false),
+            };
+
+            if wildcard && !matches!(operator, VersionOperator::Equal | VersionOperator::NotEqual) {
+                return Err(invalid("wildcards are only allowed with == and !="));
+            }
+
+            let (release, version) = if wildcard && !version.contains('.') {
+                // e.g. `==3.*`.
+                let major = version
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|_| version.chars().all(|c| c.is_ascii_digit()))
+                    .ok_or_else(|| invalid(&format!("{} is not a valid version", version)))?;
+                (vec![major], None)
+            } else {
+                let version = version
+                    .parse::<PythonVersion>()
+                    .map_err(|_| invalid(&format!("{} is not a valid version", version)))?;
+                (version.release(), Some(version))
+            };
+
+            if operator == VersionOperator::Compatible && release.len() < 2 {
+                return Err(invalid("~= requires at least 2 version components"));
+            }
+
+            clauses.push(VersionClause {
+                operator,
+                release,
+                version,
+                wildcard,
+            });
+        }
+
+        Ok(Self { clauses })
+    }
+
+    /// Whether a Python version satisfies this specifier.
+    pub fn matches(&self, version: &PythonVersion) -> bool {
+        let release = version.release();
+
+        if version.pre.is_some()
+            && !self
+                .clauses
+                .iter()
+                .any(|clause| matches!(&clause.version, Some(version) if version.pre.is_some()))
+        {
+            return false;
+        }
+
+        self.clauses.iter().all(|clause| {
+            let prefix_matches = |prefix: &[u64]| {
+                release.len() >= prefix.len() && release[..prefix.len()] == prefix[..]
+            };
+
+            let clause_version = match (&clause.version, clause.wildcard) {
+                (Some(clause_version), false) => clause_version,
+                _ => {
+                    return match clause.operator {
+                        VersionOperator::Equal => prefix_matches(&clause.release),
+                        _ => !prefix_matches(&clause.release),
+                    };
+                }
+            };
+
+            let ordering = version.cmp(clause_version);
+
+            match clause.operator {
+                VersionOperator::Equal => ordering.is_eq(),
+                VersionOperator::NotEqual => !ordering.is_eq(),
+                VersionOperator::GreaterEqual => ordering.is_ge(),
+                VersionOperator::LessEqual => ordering.is_le(),
+                VersionOperator::Greater => ordering.is_gt(),
+                VersionOperator::Less => ordering.is_lt(),
+                VersionOperator::Compatible => {
+                    // `~=X.Y.Z` is `>=X.Y.Z, ==X.Y.*`.
+                    ordering.is_ge() && prefix_matches(&clause.release[..clause.release.len() - 1])
+                }
+            }
+        })
+    }
+}
+
+/// Which Python version to prefer when several satisfy a request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VersionPreference {
+    /// Prefer the newest version.
+    ///
+    /// `3.9` is requested if no version is given. Among versions satisfying a
+    /// specifier, the newest is chosen.
+    Newest,
+    /// Prefer the oldest version.
+    ///
+    /// Any version is accepted if no version is given. Among all matching
+    /// versions, the oldest is chosen, comparing versions numerically. So
+    /// `3.9` is older than `3.10`.
+    Oldest,
+}
+
+// `#[default]` on enum variants requires a newer Rust than we support.
+#[allow(clippy::derivable_impls)]
+impl Default for VersionPreference {
+    fn default() -> Self {
+        Self::Newest
+    }
+}
+
+/// Whether shared or static distributions are preferred on Windows.
+///
+/// Windows distributions of the `standalone` flavor may be either. This
+/// decides which is selected when both satisfy a request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WindowsFlavorPreference {
+    /// Prefer distributions with a shared `pythonXY.dll`.
+    ///
+    /// They are more versatile: statically linked Windows distributions don't
+    /// `declspec(dllexport)` Python symbols and can't load shared library
+    /// Python extensions, making them a pain to work with.
+    SharedFirst,
+    /// Prefer statically linked distributions.
+    #[allow(unused)]
+    StaticFirst,
+}
+
+// `#[default]` on enum variants requires a newer Rust than we support.
+#[allow(clippy::derivable_impls)]
+impl Default for WindowsFlavorPreference {
+    fn default() -> Self {
+        Self::SharedFirst
+    }
+}
+
+/// Additional constraints to apply when selecting a Python distribution.
+///
+/// The default value imposes no constraints beyond those expressed by
+/// the arguments to `PythonDistributionCollection::find_distribution()`.
+#[derive(Clone, Debug, Default)]
+pub struct SelectionPolicy {
+    /// Minimum build optimizations the distribution must have.
+    ///
+    /// Distributions with unknown optimizations never satisfy a minimum.
+    pub min_optimization: Option<BuildOptimizations>,
+
+    /// Oldest operating system version the distribution must run on.
+    ///
+    /// e.g. `10.12` to require a distribution running on macOS 10.12. Distributions
+    /// whose `min_os_version` is newer are excluded. Distributions without a
+    /// `min_os_version` are not excluded.
+    pub os_version_floor: Option<String>,
+
+    /// Only accept a distribution if its archive is already in this cache directory.
+    ///
+    /// The check happens after a distribution is selected: a selected distribution
+    /// that isn't cached results in `DistributionError::NotCached` rather than
+    /// another distribution being chosen. Neither the network nor the archive's
+    /// original location are consulted, even for local paths.
+    pub cached_only: Option<PathBuf>,
+
+    /// Build optimizations to accept, from most to least preferred.
+    ///
+    /// If non-empty, only distributions having one of these optimizations are
+    /// considered and the distribution with the most preferred optimization
+    /// is selected. e.g. `[Pgo, Noopt]` selects a PGO build if available,
+    /// else a noopt build, and never a debug build. The `optimizations` field
+    /// of the selected record reports which optimization was chosen.
+    pub optimization_preference: Vec<BuildOptimizations>,
+
+    /// Reject a selected distribution if its Python version is end-of-life.
+    ///
+    /// End-of-life dates come from `PYTHON_EOL_DATES`. Selecting an end-of-life
+    /// version results in `DistributionError::EndOfLife`.
+    pub reject_eol: bool,
+
+    /// Whether to prefer the newest or the oldest matching Python version.
+    pub version_preference: VersionPreference,
+
+    /// Whether to prefer shared or static distributions on Windows.
+    pub windows_flavor_preference: WindowsFlavorPreference,
+
+    /// Labels distributions must have, with these values.
+    pub required_labels: BTreeMap<String, String>,
+
+    /// Labels distributions must not have with these values.
+    ///
+    /// e.g. `approval=denied` excludes distributions labeled `approval=denied`
+    /// but not ones labeled `approval=granted` or without an `approval` label.
+    pub excluded_labels: BTreeMap<String, String>,
+
+    /// Extension modules distributions must have built in. e.g. `_ssl`.
+    ///
+    /// Modules are compared with `PythonDistributionRecord::extension_modules`.
+    /// Selection never reads archives, so records whose modules aren't known
+    /// are excluded. Call
+    /// `PythonDistributionCollection::inspect_extension_modules()` first to
+    /// fill them in.
+    pub required_modules: BTreeSet<String>,
+
+    /// Maximum archive size in bytes of the distribution.
+    ///
+    /// Distributions whose `archive_size` is known and larger are excluded and
+    /// the smallest fitting distribution is preferred. Distributions of unknown
+    /// size aren't excluded but are preferred last. If only too large
+    /// distributions satisfy a request, selection fails with
+    /// `DistributionError::BudgetExceeded`.
+    pub max_footprint: Option<u64>,
+
+    /// Exclude distributions marked `experimental`.
+    pub reject_experimental: bool,
+
+    /// Inclusive range of build timestamps distributions must be within.
+    ///
+    /// Bounds are `YYYYMMDDTHHMM` UTC timestamps as reported by
+    /// `PythonDistributionRecord::build_timestamp()`, or `YYYYMMDD` dates
+    /// covering the whole day. e.g. `("20210701", "20210731")`. Distributions
+    /// without a build timestamp are excluded when a range is set.
+    pub build_timestamp_range: Option<(String, String)>,
+
+    /// Build modes bundled libraries must have, keyed by library.
+    ///
+    /// e.g. `openssl` => `fips`. See
+    /// `PythonDistributionRecord::library_build_modes`. Distributions not
+    /// recording the mode of a library are excluded if a mode is required
+    /// for it. Selection never reads archives, so call
+    /// `PythonDistributionCollection::inspect_library_build_modes()` first to
+    /// consider records not specifying modes.
+    pub required_library_modes: BTreeMap<String, String>,
+}
+
+impl SelectionPolicy {
+    /// Verify a selected record satisfies constraints that apply after selection.
+    fn check_selected(&self, record: &PythonDistributionRecord) -> Result<(), DistributionError> {
+        if let Some(cache_dir) = &self.cached_only {
+            if cached_distribution_path(&record.location, cache_dir).is_none() {
+                return Err(DistributionError::NotCached {
+                    filename: record.location.filename().to_string(),
+                    cache_dir: cache_dir.clone(),
+                });
+            }
+        }
+
+        if self.reject_eol {
+            check_eol(&record.python_major_minor_version, days_since_epoch())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// End-of-life dates of Python `X.Y` versions, as `YYYY-MM-DD`.
+///
+/// Update alongside `PYTHON_DISTRIBUTIONS` when adding new Python versions.
+pub const PYTHON_EOL_DATES: &[(&str, &str)] = &[
+    ("3.6", "2021-12-23"),
+    ("3.7", "2023-06-27"),
+    ("3.8", "2024-10-07"),
+    ("3.9", "2025-10-31"),
+    ("3.10", "2026-10-31"),
+    ("3.11", "2027-10-31"),
+    ("3.12", "2028-10-31"),
+    ("3.13", "2029-10-31"),
+];
+
+/// Obtain the end-of-life date of a Python `X.Y` version, if known.
+#[allow(unused)]
+pub fn python_eol_date(python_major_minor_version: &str) -> Option<&'static str> {
+    PYTHON_EOL_DATES
+        .iter()
+        .find(|(version, _)| *version == python_major_minor_version)
+        .map(|(_, date)| *date)
+}
+
+/// Number of days between the UNIX epoch and a `YYYY-MM-DD` date.
+fn days_from_date(date: &str) -> Option<i64> {
+    let mut parts = date.split('-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (parts.next()??, parts.next()??, parts.next()??);
+
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil.
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    Some(era * 146097 + doe - 719468)
+}
+
+/// Number of days between the UNIX epoch and now.
+fn days_since_epoch() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| (d.as_secs() / 86400) as i64)
+        .unwrap_or(0)
+}
+
+/// Error if a Python `X.Y` version is end-of-life on the given day.
+fn check_eol(python_major_minor_version: &str, today: i64) -> Result<(), DistributionError> {
+    match python_eol_date(python_major_minor_version) {
+        Some(eol_date) if matches!(days_from_date(eol_date), Some(eol) if today >= eol) => {
+            Err(DistributionError::EndOfLife {
+                version: python_major_minor_version.to_string(),
+                eol_date: eol_date.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Compare dotted numeric version strings like `10.9` and `10.15`.
+///
+/// Missing trailing components are treated as `0`. Non-numeric components
+/// compare as `0`.
+fn compare_dotted_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| {
+        v.split('.')
+            .map(|c| c.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+
+    let (a, b) = (parse(a), parse(b));
+
+    for i in 0..a.len().max(b.len()) {
+        let ordering = a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0));
+
+        if ordering != std::cmp::Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    std::cmp::Ordering::Equal
+}
+
+/// Severity of a problem found by `PythonDistributionCollection::validate()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValidationSeverity {
+    /// The record is suspicious but may be legitimate.
+    Warning,
+    /// The record is definitely wrong.
+    Error,
+}
+
+/// A problem found by `PythonDistributionCollection::validate()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: ValidationSeverity,
+    pub message: String,
+}
+
+/// Whether we expect a record to support loading prebuilt extension modules.
+///
+/// This reflects the capabilities of `python-build-standalone` distributions at
+/// the time of writing. Upstream capabilities evolve, so a disagreement with this
+/// is only suspicious, not necessarily wrong. Returns `None` if there is no
+/// expectation.
+fn expected_prebuilt_extension_module_support(dist: &PythonDistributionRecord) -> Option<bool> {
+    let filename = dist.location.filename();
+
+    if dist.target_triple.contains("linux-musl") {
+        Some(false)
+    } else if dist.target_triple.contains("pc-windows") {
+        if filename.contains("-static-") {
+            Some(false)
+        } else if filename.contains("-shared-") {
+            Some(true)
+        } else {
+            None
+        }
+    } else if dist.target_triple.contains("linux-gnu") || dist.target_triple.contains("apple-") {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Check a record's metadata against the content of its archive.
+fn archive_issues(
+    dist: &PythonDistributionRecord,
+    summary: &ArchiveSummary,
+) -> Vec<ValidationIssue> {
+    let mut issues = vec![];
+    let filename = dist.location.filename();
+    let json = &summary.python_json;
+    let string = |key: &str| json.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+
+    let mut error = |message: String| {
+        issues.push(ValidationIssue {
+            severity: ValidationSeverity::Error,
+            message,
+        })
+    };
+
+    if string("target_triple") != dist.target_triple {
+        error(format!(
+            "{} is declared for {} but its PYTHON.json targets {}",
+            filename,
+            dist.target_triple,
+            string("target_triple")
+        ));
+    }
+
+    if string("python_version") != dist.python_version.to_string() {
+        error(format!(
+            "{} is declared as Python {} but its PYTHON.json has version {}",
+            filename,
+            dist.python_version,
+            string("python_version")
+        ));
+    }
+
+    let core = json.get("build_info").and_then(|info| info.get("core"));
+    let core_string = |key: &str| core.and_then(|core| core.get(key)).and_then(|v| v.as_str());
+    let flavors = dist.supported_flavors();
+
+    if flavors.contains(&DistributionFlavor::StandaloneDynamic) {
+        let loads_shared_libraries = json
+            .get("python_extension_module_loading")
+            .and_then(|v| v.as_array())
+            .map(|modes| modes.iter().any(|mode| mode == "shared-library"))
+            .unwrap_or(false);
+
+        if !loads_shared_libraries {
+            error(format!(
+                "{} supports {} but its archive can't load shared library extension modules",
+                filename,
+                DistributionFlavor::StandaloneDynamic
+            ));
+        }
+
+        if string("libpython_link_mode") == "shared" {
+            match core_string("shared_lib") {
+                Some(path) if summary.has_file(path) => {}
+                path => error(format!(
+                    "{} supports {} but its archive lacks the shared libpython {}",
+                    filename,
+                    DistributionFlavor::StandaloneDynamic,
+                    path.unwrap_or("(undeclared)")
+                )),
+            }
+        }
+    }
+
+    if flavors.contains(&DistributionFlavor::StandaloneStatic) {
+        let objs = core
+            .and_then(|core| core.get("objs"))
+            .and_then(|v| v.as_array())
+            .map(|objs| objs.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if objs.is_empty() {
+            error(format!(
+                "{} supports {} but its PYTHON.json declares no libpython object files",
+                filename,
+                DistributionFlavor::StandaloneStatic
+            ));
+        }
+
+        for path in objs.into_iter().chain(core_string("static_lib")) {
+            if !summary.has_file(path) {
+                error(format!(
+                    "{} supports {} but its archive lacks {}",
+                    filename,
+                    DistributionFlavor::StandaloneStatic,
+                    path
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+/// The result of checking the availability of a distribution.
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct AvailabilityReport {
+    /// The record that was checked.
+    pub record: PythonDistributionRecord,
+
+    /// `Ok(())` if the distribution is available or a description of why it isn't.
+    pub available: Result<(), String>,
+}
+
+/// Obtain a random delay of up to 250ms to stagger availability checks.
+fn availability_jitter() -> Duration {
+    // We don't need real randomness here. Sub-second clock noise suffices.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+
+    Duration::from_millis(u64::from(nanos % 250))
+}
+
+/// Describes what a packaged application needs from its Python distribution.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PackagingGoal {
+    /// Produce a single executable file without accompanying libraries.
+    SingleFileExecutable,
+
+    /// Load compiled extension modules not built by PyOxidizer, e.g. from wheels.
+    LoadsThirdPartyExtensions,
+}
+
+/// Determine the distribution flavor best suited to a packaging goal on a target.
+///
+/// Returns the flavor and a human readable rationale for choosing it.
+#[allow(unused)]
+pub fn flavor_for_goal(
+    target_triple: &str,
+    goal: PackagingGoal,
+) -> (DistributionFlavor, &'static str) {
+    let musl = target_triple.contains("-musl");
+    let windows = target_triple.contains("-windows-");
+
+    match goal {
+        PackagingGoal::SingleFileExecutable if musl => (
+            DistributionFlavor::StandaloneStatic,
+            "musl distributions are fully statically linked, producing an executable with no runtime library dependencies",
+        ),
+        PackagingGoal::SingleFileExecutable if windows => (
+            DistributionFlavor::StandaloneStatic,
+            "statically linked Windows distributions embed libpython in the executable, avoiding a pythonXY.dll; they cannot load extension modules not compiled into the binary",
+        ),
+        PackagingGoal::SingleFileExecutable => (
+            DistributionFlavor::Standalone,
+            "libpython is statically linked into executables on this target; only system libraries are loaded at run-time",
+        ),
+        PackagingGoal::LoadsThirdPartyExtensions if musl => (
+            DistributionFlavor::StandaloneDynamic,
+            "musl distributions are usually statically linked and cannot load extension modules; a musl distribution supporting them must be registered, or target linux-gnu instead",
+        ),
+        PackagingGoal::LoadsThirdPartyExtensions if windows => (
+            DistributionFlavor::StandaloneDynamic,
+            "loading extension modules on Windows requires a distribution with a shared pythonXY.dll, which must be distributed alongside the executable",
+        ),
+        PackagingGoal::LoadsThirdPartyExtensions => (
+            DistributionFlavor::StandaloneDynamic,
+            "distributions for this target can load extension modules while statically linking libpython",
+        ),
+    }
+}
+
+/// Whether a distribution of the given flavor can exist for a target triple.
+///
+/// This encodes how `python-build-standalone` distributions are built,
+/// independently of which distributions are registered:
+///
+/// * linux-gnu and Apple distributions can load extension modules, so are
+///   never `StandaloneStatic`.
+/// * linux-musl distributions are fully statically linked, so are never
+///   `StandaloneDynamic`.
+/// * Windows distributions are available in both flavors.
+///
+/// Returns `false` for target triples not built by `python-build-standalone`.
+#[allow(unused)]
+pub fn can_build_flavor(target_triple: &str, flavor: &DistributionFlavor) -> bool {
+    let static_ok = target_triple.contains("-linux-musl") || target_triple.contains("-windows-");
+    let dynamic_ok = target_triple.contains("-linux-gnu")
+        || target_triple.contains("-apple-")
+        || target_triple.contains("-windows-");
+
+    match flavor {
+        DistributionFlavor::Standalone => static_ok || dynamic_ok,
+        DistributionFlavor::StandaloneStatic => static_ok,
+        DistributionFlavor::StandaloneDynamic => dynamic_ok,
+    }
+}
+
+/// An operating system distributions can target.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TargetOs {
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl TargetOs {
+    /// Determine the operating system of a target triple, if known.
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        if triple.contains("-linux-") {
+            Some(Self::Linux)
+        } else if triple.contains("-apple-darwin") {
+            Some(Self::MacOs)
+        } else if triple.contains("-windows-") {
+            Some(Self::Windows)
+        } else {
+            None
+        }
+    }
+}
+
+/// A CPU architecture distributions can target.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TargetArch {
+    /// 32-bit x86. e.g. `i686`.
+    X86,
+    X86_64,
+    Aarch64,
+}
+
+impl TargetArch {
+    /// Determine the architecture of a target triple, if known.
+    pub fn from_triple(triple: &str) -> Option<Self> {
+        match triple.split('-').next()? {
+            "i386" | "i586" | "i686" => Some(Self::X86),
+            "x86_64" => Some(Self::X86_64),
+            "aarch64" | "arm64" => Some(Self::Aarch64),
+            _ => None,
+        }
+    }
+}
+
+/// Newest glibc `manylinux` wheels may require to be used on glibc Linux targets.
+///
+/// Matches the `manylinux2014` compatibility tag of our distributions.
+const MANYLINUX_MAX_GLIBC: (u32, u32) = (2, 17);
+
+/// Newest musl `musllinux` wheels may require to be used on musl Linux targets.
+const MUSLLINUX_MAX_MUSL: (u32, u32) = (1, 2);
+
+/// Parse the `X_Y` version in a wheel platform tag.
+fn parse_tag_version(value: &str) -> Option<(u32, u32)> {
+    let mut parts = value.splitn(2, '_');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+
+    Some((major, minor))
+}
+
+/// Whether a single wheel platform tag is usable by a target triple.
+fn wheel_tag_compatible(triple: &str, tag: &str) -> bool {
+    if tag == "any" {
+        return true;
+    }
+
+    let arch = match TargetArch::from_triple(triple) {
+        Some(arch) => arch,
+        None => return false,
+    };
+
+    match TargetOs::from_triple(triple) {
+        Some(TargetOs::Linux) => {
+            let linux_arch = match arch {
+                TargetArch::X86 => "_i686",
+                TargetArch::X86_64 => "_x86_64",
+                TargetArch::Aarch64 => "_aarch64",
+            };
+
+            let policy = match tag.strip_suffix(linux_arch) {
+                Some(policy) => policy,
+                None => return false,
+            };
+
+            if triple.ends_with("-musl") {
+                matches!(
+                    policy.strip_prefix("musllinux_").and_then(parse_tag_version),
+                    Some(version) if version <= MUSLLINUX_MAX_MUSL
+                )
+            } else {
+                let glibc = match policy {
+                    "manylinux1" => Some((2, 5)),
+                    "manylinux2010" => Some((2, 12)),
+                    "manylinux2014" => Some((2, 17)),
+                    _ => policy
+                        .strip_prefix("manylinux_")
+                        .and_then(parse_tag_version),
+                };
+
+                matches!(glibc, Some(version) if version <= MANYLINUX_MAX_GLIBC)
+            }
+        }
+        Some(TargetOs::MacOs) => {
+            let (deployment_target, archs): ((u32, u32), &[&str]) = match arch {
+                TargetArch::X86_64 => (
+                    (10, 9),
+                    &[
+                        "x86_64",
+                        "intel",
+                        "fat64",
+                        "fat3",
+                        "universal",
+                        "universal2",
+                    ],
+                ),
+                TargetArch::Aarch64 => ((11, 0), &["arm64", "universal2"]),
+                TargetArch::X86 => return false,
+            };
+
+            let rest = match tag.strip_prefix("macosx_") {
+                Some(rest) => rest,
+                None => return false,
+            };
+            // The architecture follows the second underscore.
+            let split = rest
+                .match_indices('_')
+                .nth(1)
+                .map(|(i, _)| (&rest[..i], &rest[i + 1..]));
+
+            match split {
+                Some((version, tag_arch)) => {
+                    matches!(parse_tag_version(version), Some(v) if v <= deployment_target)
+                        && archs.contains(&tag_arch)
+                }
+                None => false,
+            }
+        }
+        Some(TargetOs::Windows) => {
+            tag == match arch {
+                TargetArch::X86 => "win32",
+                TargetArch::X86_64 => "win_amd64",
+                TargetArch::Aarch64 => "win_arm64",
+            }
+        }
+        None => false,
+    }
+}
+
+/// Whether wheels having a platform tag can be used by distributions for a target triple.
+///
+/// `wheel_platform_tag` is the platform tag of a wheel's filename, e.g.
+/// `manylinux2014_x86_64`. Compressed tag sets like
+/// `manylinux_2_17_x86_64.manylinux2014_x86_64` are compatible if any of
+/// their tags is. glibc Linux targets accept `manylinux` tags requiring at
+/// most glibc 2.17 and musl Linux targets accept `musllinux` tags requiring
+/// at most musl 1.2. macOS targets accept `macosx` tags of a matching
+/// architecture, including fat and universal binaries, requiring at most the
+/// deployment target of our distributions: 10.9 for x86_64 and 11.0 for
+/// arm64. Windows targets accept `win32`, `win_amd64`, or `win_arm64`. `any`
+/// is compatible with every target. Otherwise, unknown triples and tags,
+/// including non-portable `linux_*` tags, are incompatible.
+///
+/// This only considers binary compatibility. Whether a distribution can load
+/// extension modules at all is recorded in
+/// `PythonDistributionRecord::supports_prebuilt_extension_modules`.
+#[allow(unused)]
+pub fn wheels_compatible(record_triple: &str, wheel_platform_tag: &str) -> bool {
+    wheel_platform_tag
+        .split('.')
+        .any(|tag| wheel_tag_compatible(record_triple, tag))
+}
+
+/// How close a target triple is to a preferred one. Lower is closer.
+///
+/// The same triple is closest, then triples of the same operating system and
+/// architecture (e.g. musl instead of glibc), then of the same operating
+/// system, then of the same architecture, then anything else.
+fn triple_distance(preferred: &str, triple: &str) -> u8 {
+    let os = TargetOs::from_triple(preferred);
+    let arch = TargetArch::from_triple(preferred);
+
+    let same_os = os.is_some() && os == TargetOs::from_triple(triple);
+    let same_arch = arch.is_some() && arch == TargetArch::from_triple(triple);
+
+    match (preferred == triple, same_os, same_arch) {
+        (true, _, _) => 0,
+        (false, true, true) => 1,
+        (false, true, false) => 2,
+        (false, false, true) => 3,
+        (false, false, false) => 4,
+    }
+}
+
+/// A C library Linux distributions can be built against.
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TargetLibc {
+    Gnu,
+    Musl,
+}
+
+/// Resolve the canonical target triple of an operating system and architecture.
+///
+/// e.g. `x86_64-unknown-linux-gnu` or `x86_64-pc-windows-msvc`. `libc` chooses
+/// between glibc and musl on Linux and defaults to glibc. It is ignored for
+/// other operating systems.
+#[allow(unused)]
+pub fn target_triple_for(os: TargetOs, arch: TargetArch, libc: Option<TargetLibc>) -> String {
+    let arch = match arch {
+        TargetArch::X86 => "i686",
+        TargetArch::X86_64 => "x86_64",
+        TargetArch::Aarch64 => "aarch64",
+    };
+
+    match os {
+        TargetOs::Linux => format!(
+            "{}-unknown-linux-{}",
+            arch,
+            match libc.unwrap_or(TargetLibc::Gnu) {
+                TargetLibc::Gnu => "gnu",
+                TargetLibc::Musl => "musl",
+            }
+        ),
+        TargetOs::MacOs => format!("{}-apple-darwin", arch),
+        TargetOs::Windows => format!("{}-pc-windows-msvc", arch),
+    }
+}
+
+/// A lookup that a proposed target triple alias would make succeed.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct AliasImpact {
+    /// The aliased target triple that would be queried.
+    pub target_triple: String,
+
+    /// The `X.Y` Python version that would be queried.
+    pub python_major_minor_version: String,
+
+    /// The distribution flavor that would be queried.
+    pub flavor: DistributionFlavor,
+
+    /// The record the lookup would resolve to.
+    pub record: PythonDistributionRecord,
+}
+
+/// How target triple coverage differs between two Python versions.
+///
+/// See `PythonDistributionCollection::triple_coverage_diff()`.
+#[allow(unused)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct CoverageDiff {
+    /// Target triples having distributions of only the first version.
+    pub only_a: BTreeSet<String>,
+
+    /// Target triples having distributions of only the second version.
+    pub only_b: BTreeSet<String>,
+
+    /// Target triples having distributions of both versions.
+    pub both: BTreeSet<String>,
+}
+
+/// Counts of records in a `PythonDistributionCollection`.
+///
+/// See `PythonDistributionCollection::stats()`. Maps are ordered by key.
+#[allow(unused)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct CollectionStats {
+    /// Total number of records.
+    pub records: usize,
+
+    /// Number of records per target triple.
+    pub by_target_triple: BTreeMap<String, usize>,
+
+    /// Number of records per `X.Y` Python version.
+    pub by_python_version: BTreeMap<String, usize>,
+
+    /// Number of records supporting each flavor. e.g. `standalone_static`.
+    pub by_flavor: BTreeMap<String, usize>,
+}
+
+/// A request for a distribution, as resolved by `PythonDistributionCollection::resolve_batch()`.
+#[allow(unused)]
+#[derive(Debug)]
+pub struct DistributionRequest {
+    /// The target triple to find a distribution for.
+    pub target_triple: String,
+
+    /// The distribution flavor to find.
+    pub flavor: DistributionFlavor,
+
+    /// The Python version or version specifier to find. `None` for the default.
+    pub python_version: Option<String>,
+}
+
+/// Requirements for selecting a Python distribution, as consumed by `PythonDistributionCollection::query()`.
+///
+/// Constructed for a target triple with `new()` and refined with the builder
+/// methods. e.g.
+/// `DistributionQuery::new("x86_64-unknown-linux-gnu").python_version("3.9")`.
+/// By default the `Standalone` flavor of the default Python version is
+/// selected with the default `SelectionPolicy`.
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct DistributionQuery {
+    target_triple: String,
+    python_version: Option<String>,
+    flavors: Vec<DistributionFlavor>,
+    policy: SelectionPolicy,
+}
+
+#[allow(unused)]
+impl DistributionQuery {
+    pub fn new(target_triple: impl ToString) -> Self {
+        Self {
+            target_triple: target_triple.to_string(),
+            python_version: None,
+            flavors: vec![DistributionFlavor::Standalone],
+            policy: SelectionPolicy::default(),
+        }
+    }
+
+    pub fn target_triple(mut self, value: impl ToString) -> Self {
+        self.target_triple = value.to_string();
+        self
+    }
+
+    /// The Python version or version specifier to select, as accepted by `find_distribution()`.
+    pub fn python_version(mut self, value: impl ToString) -> Self {
+        self.python_version = Some(value.to_string());
+        self
+    }
+
+    pub fn python_version_optional(mut self, value: Option<impl ToString>) -> Self {
+        self.python_version = value.map(|v| v.to_string());
+        self
+    }
+
+    pub fn flavor(mut self, flavor: DistributionFlavor) -> Self {
+        self.flavors = vec![flavor];
+        self
+    }
+
+    /// Flavors to accept, from most to least preferred.
+    ///
+    /// See `find_distribution_with_flavors()`. If empty, nothing is selected.
+    pub fn flavors(mut self, flavors: Vec<DistributionFlavor>) -> Self {
+        self.flavors = flavors;
+        self
+    }
+
+    /// Set `SelectionPolicy::optimization_preference`.
+    pub fn optimization_preference(mut self, value: Vec<BuildOptimizations>) -> Self {
+        self.policy.optimization_preference = value;
+        self
+    }
+
+    /// Set `SelectionPolicy::build_timestamp_range`.
+    pub fn build_timestamp_range(mut self, earliest: impl ToString, latest: impl ToString) -> Self {
+        self.policy.build_timestamp_range = Some((earliest.to_string(), latest.to_string()));
+        self
+    }
+
+    /// Whether to only select distributions for production ready targets.
+    ///
+    /// Sets `SelectionPolicy::reject_experimental`.
+    pub fn stable_only(mut self, value: bool) -> Self {
+        self.policy.reject_experimental = value;
+        self
+    }
+
+    /// Replace the selection policy, including settings made by other methods.
+    pub fn policy(mut self, policy: SelectionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// The number of records remaining after a selection filter.
+#[allow(unused)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilterTrace {
+    /// Name of the filter, as shown by `explain_selection()`.
+    pub name: &'static str,
+
+    /// Records satisfying this and every preceding filter.
+    pub remaining: usize,
+}
+
+/// How a single flavor was considered during selection.
+#[allow(unused)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectionAttempt {
+    pub flavor: DistributionFlavor,
+
+    /// Filters in the order they are applied. Empty if the request is invalid.
+    pub filters: Vec<FilterTrace>,
+
+    /// How ties between `finalists` are broken.
+    pub tiebreak: String,
+
+    /// Filenames of records satisfying every filter, in registration order.
+    pub finalists: Vec<String>,
+
+    /// Filename of the selected record.
+    pub selected: Option<String>,
+
+    /// Code of the error preventing selection. See `DistributionError::code()`.
+    pub error_code: Option<&'static str>,
+
+    /// Message of the error preventing selection.
+    pub error: Option<String>,
+}
+
+/// A machine-readable account of how a distribution was selected.
+///
+/// See `PythonDistributionCollection::query_traced()`.
+#[allow(unused)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SelectionTrace {
+    /// The number of records considered.
+    pub candidates: usize,
+
+    /// Flavors attempted, in order. Only the last can have selected a record.
+    pub attempts: Vec<SelectionAttempt>,
+}
+
+/// The version and ABI of an existing Python interpreter.
+///
+/// See `PythonDistributionCollection::find_compatible_with()`.
+#[allow(unused)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HostPython {
+    /// Full Python version. e.g. `3.9.7`.
+    pub version: String,
+
+    /// PEP 425 ABI tag. e.g. `cp39`, or `cp39d` for a debug build.
+    pub abi: String,
+}
+
+/// Python code printing the version and PEP 425 ABI tag of the running interpreter.
+///
+/// `sys.abiflags` doesn't exist on Windows, so the flags are derived there
+/// like `packaging.tags` does.
+const HOST_PYTHON_SCRIPT: &str = "\
+import sys
+if sys.implementation.name != 'cpython':
+    sys.exit('not a CPython interpreter: ' + sys.implementation.name)
+flags = getattr(sys, 'abiflags', None)
+if flags is None:
+    flags = ('d' if hasattr(sys, 'gettotalrefcount') else '') + ('m' if sys.version_info < (3, 8) else '')
+print('%d.%d.%d' % sys.version_info[:3])
+print('cp%d%d%s' % (sys.version_info[0], sys.version_info[1], flags))
+";
+
+#[allow(unused)]
+impl HostPython {
+    /// Detect the version and ABI of the Python interpreter at `python_exe`.
+    ///
+    /// The interpreter is run. Only CPython interpreters are supported.
+    pub fn from_interpreter(python_exe: &Path) -> Result<Self> {
+        let output = std::process::Command::new(python_exe)
+            .args(["-c", HOST_PYTHON_SCRIPT])
+            .output()
+            .map_err(|e| anyhow!("unable to run {}: {}", python_exe.display(), e))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "unable to determine ABI of {}: {}",
+                python_exe.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Self::from_script_output(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Parse the output of `HOST_PYTHON_SCRIPT`.
+    fn from_script_output(output: &str) -> Result<Self> {
+        let mut lines = output.lines().map(|line| line.trim());
+
+        match (lines.next(), lines.next()) {
+            (Some(version), Some(abi)) if !version.is_empty() && !abi.is_empty() => Ok(Self {
+                version: version.to_string(),
+                abi: abi.to_string(),
+            }),
+            _ => Err(anyhow!(
+                "unexpected output from Python interpreter: {}",
+                output
+            )),
+        }
+    }
+}
+
+/// SHA-256 digests of distributions that must never be selected.
+static DENIED_DIGESTS: Lazy<RwLock<Vec<String>>> = Lazy::new(|| RwLock::new(vec![]));
+
+/// Prevent distributions having any of the given SHA-256 digests from being selected.
+///
+/// Denied distributions are skipped during selection, as if they weren't
+/// registered. The next best distribution is selected instead, if any.
+/// `PythonDistributionCollection::find_distribution_logged()` logs a warning
+/// for each skipped distribution. Replaces previously denied digests.
+#[allow(unused)]
+pub fn set_denied_digests(digests: &[&str]) {
+    *DENIED_DIGESTS.write().unwrap() = digests.iter().map(|d| d.to_lowercase()).collect();
+}
+
+/// A resolved Python version requirement.
+#[derive(Clone)]
+enum VersionRequirement {
+    /// Any version.
+    Any,
+    /// Any version having this `X.Y` major.minor version.
+    MajorMinor(String),
+    /// Exactly this version.
+    Exact(PythonVersion),
+    /// Versions satisfying a specifier. The newest satisfying version is preferred.
+    Specifier(VersionSpecifier),
+}
+
+/// Resolved criteria for selecting a distribution.
+#[derive(Clone)]
+struct SelectionCriteria<'a> {
+    target_triple: &'a str,
+    flavor: &'a DistributionFlavor,
+    version: VersionRequirement,
+    policy: &'a SelectionPolicy,
+}
+
+impl<'a> SelectionCriteria<'a> {
+    /// Resolve criteria from arguments to `find_distribution_with_policy()`.
+    ///
+    /// Returns an error if the requested version is invalid.
+    fn new(
+        target_triple: &'a str,
+        flavor: &'a DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &'a SelectionPolicy,
+    ) -> Result<Self, DistributionError> {
+        if python_major_minor_version.is_none()
+            && policy.version_preference == VersionPreference::Oldest
+        {
+            return Ok(Self {
+                target_triple,
+                flavor,
+                version: VersionRequirement::Any,
+                policy,
+            });
+        }
+
+        let requested = python_major_minor_version.unwrap_or("3.9");
+        let invalid = |e: anyhow::Error| DistributionError::InvalidVersion {
+            version: requested.to_string(),
+            message: format!("{}", e),
+        };
+
+        let version = if VersionSpecifier::is_specifier(requested) {
+            VersionRequirement::Specifier(VersionSpecifier::parse(requested).map_err(invalid)?)
+        } else {
+            let python_version = requested.parse::<PythonVersion>().map_err(|_| {
+                invalid(anyhow!(
+                    "invalid Python version {}; {}",
+                    requested,
+                    ACCEPTED_VERSION_FORMS
+                ))
+            })?;
+
+            if python_version.patch.is_none()
+                && python_version.pre.is_none()
+                && python_version.post.is_none()
+                && python_version.epoch == 0
+            {
+                VersionRequirement::MajorMinor(python_version.major_minor())
+            } else {
+                VersionRequirement::Exact(python_version)
+            }
+        };
+
+        Ok(Self {
+            target_triple,
+            flavor,
+            version,
+            policy,
+        })
+    }
+
+    /// Names of the filters evaluated by `evaluate()`, in order.
+    const FILTER_NAMES: [&'static str; 12] = [
+        "target triple",
+        "python version",
+        "flavor",
+        "minimum optimization",
+        "OS version floor",
+        "optimization preference",
+        "labels",
+        "experimental",
+        "footprint",
+        "build timestamp",
+        "library build modes",
+        "required modules",
+    ];
+
+    /// Evaluate each individual filter of these criteria against a record.
+    ///
+    /// Returns whether the record satisfies each filter in `FILTER_NAMES`.
+    fn evaluate(&self, dist: &PythonDistributionRecord) -> [bool; 12] {
+        let version_matches = match &self.version {
+            VersionRequirement::Any => true,
+            VersionRequirement::MajorMinor(version) => &dist.python_major_minor_version == version,
+            VersionRequirement::Exact(version) => &dist.python_version == version,
+            VersionRequirement::Specifier(specifier) => specifier.matches(&dist.python_version),
+        };
+
+        let optimization_matches = match (&self.policy.min_optimization, &dist.optimizations) {
+            (None, _) => true,
+            (Some(min), Some(actual)) => actual >= min,
+            (Some(_), None) => false,
+        };
+
+        let os_version_matches = match (&self.policy.os_version_floor, &dist.min_os_version) {
+            (Some(floor), Some(min)) => {
+                compare_dotted_versions(min, floor) != std::cmp::Ordering::Greater
+            }
+            _ => true,
+        };
+
+        let preference_matches = self.policy.optimization_preference.is_empty()
+            || dist
+                .optimizations
+                .map(|o| self.policy.optimization_preference.contains(&o))
+                .unwrap_or(false);
+
+        let labels_match = self
+            .policy
+            .required_labels
+            .iter()
+            .all(|(key, value)| dist.labels.get(key) == Some(value))
+            && !self
+                .policy
+                .excluded_labels
+                .iter()
+                .any(|(key, value)| dist.labels.get(key) == Some(value));
+
+        let footprint_matches = match (self.policy.max_footprint, dist.archive_size) {
+            (Some(budget), Some(size)) => size <= budget,
+            _ => true,
+        };
+
+        let timestamp_matches = match &self.policy.build_timestamp_range {
+            None => true,
+            Some((earliest, latest)) => match dist.build_timestamp() {
+                // Date-only bounds compare against the date part of timestamps.
+                Some(timestamp) => {
+                    timestamp.as_str() >= earliest.as_str()
+                        && &timestamp[..latest.len().min(timestamp.len())] <= latest.as_str()
+                }
+                None => false,
+            },
+        };
+
+        let library_modes_match = self
+            .policy
+            .required_library_modes
+            .iter()
+            .all(|(library, mode)| dist.library_build_modes.get(library) == Some(mode));
+
+        [
+            dist.target_triple == self.target_triple,
+            version_matches,
+            dist.supported_flavors().contains(self.flavor),
+            optimization_matches,
+            os_version_matches,
+            preference_matches,
+            labels_match,
+            !(self.policy.reject_experimental && dist.experimental),
+            footprint_matches,
+            timestamp_matches,
+            library_modes_match,
+            self.policy.required_modules.is_empty()
+                || matches!(
+                    &dist.extension_modules,
+                    Some(modules) if self.policy.required_modules.is_subset(modules)
+                ),
+        ]
+    }
+
+    /// Whether a record satisfies these criteria.
+    fn matches(&self, dist: &PythonDistributionRecord) -> bool {
+        self.evaluate(dist).iter().all(|matches| *matches)
+    }
+
+    /// Count the records remaining after each filter.
+    fn trace_filters(&self, records: &[PythonDistributionRecord]) -> Vec<FilterTrace> {
+        let evaluations = records
+            .iter()
+            .map(|dist| self.evaluate(dist))
+            .collect::<Vec<_>>();
+
+        Self::FILTER_NAMES
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FilterTrace {
+                name,
+                remaining: evaluations
+                    .iter()
+                    .filter(|results| results[..=i].iter().all(|matches| *matches))
+                    .count(),
+            })
+            .collect()
+    }
+
+    /// Describe how records are chosen when several satisfy these criteria.
+    fn tiebreak(&self) -> String {
+        let mut steps = vec![];
+
+        if !self.policy.optimization_preference.is_empty() {
+            steps.push("most preferred optimization");
+        }
+
+        if self.policy.max_footprint.is_some() {
+            steps.push("smallest archive");
+        }
+
+        match self.policy.version_preference {
+            VersionPreference::Oldest => steps.push("oldest Python version"),
+            VersionPreference::Newest => {
+                if matches!(self.version, VersionRequirement::Specifier(_)) {
+                    steps.push("newest Python version");
+                }
+            }
+        }
+
+        if self.target_triple.contains("-windows-") {
+            steps.push(match self.policy.windows_flavor_preference {
+                WindowsFlavorPreference::SharedFirst => "shared distribution",
+                WindowsFlavorPreference::StaticFirst => "static distribution",
+            });
+        }
+
+        steps.push("first registered record");
+
+        steps.join(", then ")
+    }
+
+    /// Records satisfying these criteria that are skipped because their digest is denied.
+    ///
+    /// See `set_denied_digests()`.
+    fn denied<'r>(
+        &self,
+        records: &'r [PythonDistributionRecord],
+    ) -> Vec<&'r PythonDistributionRecord> {
+        let denied = DENIED_DIGESTS.read().unwrap();
+
+        records
+            .iter()
+            .filter(|dist| self.matches(dist))
+            .filter(|dist| denied.contains(&dist.location.sha256().to_lowercase()))
+            .collect()
+    }
+
+    /// Select the best record satisfying these criteria.
+    ///
+    /// The first matching record is selected unless an optimization preference
+    /// is in effect, in which case the first matching record having the most
+    /// preferred optimization is. With a footprint budget, the smallest record
+    /// is then preferred. When the version is given by a specifier,
+    /// the newest satisfying version is then preferred. If the policy prefers
+    /// the oldest version, the oldest matching version is preferred instead.
+    /// Finally, on Windows, shared or static distributions are preferred per
+    /// `SelectionPolicy::windows_flavor_preference`.
+    fn select<'r, T: Borrow<PythonDistributionRecord>>(&self, records: &'r [T]) -> Option<&'r T> {
+        let denied = DENIED_DIGESTS.read().unwrap();
+
+        records
+            .iter()
+            .filter(|dist| self.matches((*dist).borrow()))
+            .filter(|dist| !denied.contains(&(*dist).borrow().location.sha256().to_lowercase()))
+            .min_by_key(|dist| {
+                let dist = (*dist).borrow();
+
+                let preference = if self.policy.optimization_preference.is_empty() {
+                    None
+                } else {
+                    self.policy
+                        .optimization_preference
+                        .iter()
+                        .position(|o| Some(*o) == dist.optimizations)
+                };
+
+                let footprint = self
+                    .policy
+                    .max_footprint
+                    .map(|_| dist.archive_size.unwrap_or(u64::MAX));
+
+                let newest = match (self.policy.version_preference, &self.version) {
+                    (VersionPreference::Newest, VersionRequirement::Specifier(_)) => {
+                        Some(std::cmp::Reverse(&dist.python_version))
+                    }
+                    _ => None,
+                };
+
+                let oldest = match self.policy.version_preference {
+                    VersionPreference::Oldest => Some(&dist.python_version),
+                    VersionPreference::Newest => None,
+                };
+
+                let windows_flavor = if dist.target_triple.contains("-windows-") {
+                    Some(match self.policy.windows_flavor_preference {
+                        WindowsFlavorPreference::SharedFirst => {
+                            !dist.supports_prebuilt_extension_modules
+                        }
+                        WindowsFlavorPreference::StaticFirst => {
+                            dist.supports_prebuilt_extension_modules
+                        }
+                    })
+                } else {
+                    None
+                };
+
+                (preference, footprint, newest, oldest, windows_flavor)
+            })
+    }
+
+    /// Describe why no record was selected if only the footprint budget is to blame.
+    ///
+    /// Returns `DistributionError::BudgetExceeded` if records would satisfy these
+    /// criteria without `SelectionPolicy::max_footprint`.
+    fn budget_exceeded<T: Borrow<PythonDistributionRecord>>(
+        &self,
+        records: &[T],
+    ) -> Option<DistributionError> {
+        let budget = self.policy.max_footprint?;
+
+        let unconstrained = SelectionPolicy {
+            max_footprint: None,
+            ..self.policy.clone()
+        };
+        let criteria = SelectionCriteria {
+            policy: &unconstrained,
+            ..self.clone()
+        };
+
+        records
+            .iter()
+            .map(|dist| dist.borrow())
+            .filter(|dist| criteria.matches(dist))
+            .filter_map(|dist| dist.archive_size)
+            .min()
+            .map(|smallest| DistributionError::BudgetExceeded { budget, smallest })
+    }
+}
+
+/// A Python distribution obtained by `PythonDistributionCollection::resolve()`.
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct ResolvedDistribution {
+    /// The selected record.
+    pub record: PythonDistributionRecord,
+
+    /// Directory the distribution was extracted to.
+    pub path: PathBuf,
+
+    /// The requested Python version or version specifier, if any.
+    pub requested_version: Option<String>,
+
+    /// The Python version of the selected distribution.
+    pub selected_version: PythonVersion,
+}
+
+impl ResolvedDistribution {
+    /// Whether the selected version differs from the requested one.
+    ///
+    /// A version differs if it doesn't satisfy a requested specifier or isn't
+    /// the exact version requested. A requested `X.Y` matches any patch
+    /// release, but epoch, pre-release, and post-release must still equal
+    /// those requested. No requested version never differs.
+    pub fn version_differs(&self) -> bool {
+        let requested = match &self.requested_version {
+            Some(requested) => requested,
+            None => return false,
+        };
+
+        if VersionSpecifier::is_specifier(requested) {
+            return match VersionSpecifier::parse(requested) {
+                Ok(specifier) => !specifier.matches(&self.selected_version),
+                Err(_) => true,
+            };
+        }
+
+        match requested.parse::<PythonVersion>() {
+            Ok(version) if version.patch.is_none() => {
+                PythonVersion {
+                    patch: self.selected_version.patch,
+                    ..version
+                } != self.selected_version
+            }
+            Ok(version) => version != self.selected_version,
+            Err(_) => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PythonDistributionCollection {
+    dists: Vec<PythonDistributionRecord>,
+}
+
+impl PythonDistributionCollection {
+    /// Register a distribution with this collection.
+    ///
+    /// Records are consulted in the order they are registered. So the new
+    /// record is only chosen if no previously registered record matches.
+    #[allow(unused)]
+    pub fn register(&mut self, record: PythonDistributionRecord) {
+        self.dists.push(record);
+    }
+
+    /// Transform every record in this collection.
+    ///
+    /// This allows uniformly rewriting records, such as rewriting URLs to
+    /// point at a mirror. Record order is preserved.
+    #[allow(unused)]
+    pub fn map_records(
+        self,
+        f: impl Fn(PythonDistributionRecord) -> PythonDistributionRecord,
+    ) -> Self {
+        Self {
+            dists: self.dists.into_iter().map(f).collect(),
+        }
+    }
+
+    /// Fill in the extension modules of records from their archives.
+    ///
+    /// Records satisfying `pred` whose `extension_modules` aren't known are
+    /// inspected with `distribution_extension_modules()`, which may download
+    /// archives. Records that can't be inspected are logged and left without
+    /// modules, so `SelectionPolicy::required_modules` excludes them. Record
+    /// order is preserved.
+    #[allow(unused)]
+    pub fn inspect_extension_modules(
+        self,
+        logger: &slog::Logger,
+        pred: impl Fn(&PythonDistributionRecord) -> bool,
+    ) -> Self {
+        self.map_records(|mut record| {
+            if record.extension_modules.is_none() && pred(&record) {
+                match distribution_extension_modules(&record.location) {
+                    Ok(modules) => {
+                        record.extension_modules = Some(modules.as_ref().clone());
+                    }
+                    Err(e) => {
+                        warn!(
+                            logger,
+                            "extension modules of Python distribution {} could not be determined: {}",
+                            record.location.filename(),
+                            e
+                        );
+                    }
+                }
+            }
+
+            record
+        })
+    }
+
+    /// Fill in the library build modes of records from their archives.
+    ///
+    /// Records satisfying `pred` without any `library_build_modes` are
+    /// inspected with `distribution_library_build_modes()`, which may download
+    /// archives. Records that can't be inspected are logged and left without
+    /// modes. Record order is preserved.
+    #[allow(unused)]
+    pub fn inspect_library_build_modes(
+        self,
+        logger: &slog::Logger,
+        pred: impl Fn(&PythonDistributionRecord) -> bool,
+    ) -> Self {
+        self.map_records(|mut record| {
+            if record.library_build_modes.is_empty() && pred(&record) {
+                match distribution_library_build_modes(&record.location) {
+                    Ok(modes) => {
+                        record.library_build_modes = modes;
+                    }
+                    Err(e) => {
+                        warn!(
+                            logger,
+                            "library build modes of Python distribution {} could not be determined: {}",
+                            record.location.filename(),
+                            e
+                        );
+                    }
+                }
+            }
+
+            record
+        })
+    }
+
+    /// Find a Python distribution given requirements.
+    ///
+    /// `target_triple` is the Rust machine triple the distribution is built for.
+    /// `flavor` is the type of Python distribution.
+    /// `python_major_minor_version` is an optional version string being
+    /// requested. It is normalized via `normalize_python_version()`. An `X.Y`
+    /// version matches any distribution of that major.minor version. Other
+    /// versions must match a distribution's full version. A pip style version
+    /// specifier like `==3.9.*` or `>=3.8` may also be given, in which case the
+    /// newest satisfying version is selected. See `VersionSpecifier`. If `None`,
+    /// `3.9` is assumed. Invalid versions match no distributions.
+    ///
+    /// See `SelectionPolicy::version_preference` to select the oldest version
+    /// instead.
+    pub fn find_distribution(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Option<PythonDistributionRecord> {
+        self.find_distribution_with_policy(
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            &SelectionPolicy::default(),
+        )
+    }
+
+    /// Find a Python distribution matching any of several flavors, in priority order.
+    ///
+    /// Each flavor in `flavors` is tried in turn as by `find_distribution()`.
+    /// The first flavor yielding a distribution is returned along with it. e.g.
+    /// `[StandaloneDynamic, StandaloneStatic]` means dynamic if available, else
+    /// static.
+    ///
+    /// The order of `flavors` takes precedence over
+    /// `SelectionPolicy::windows_flavor_preference`. That preference only
+    /// decides between shared and static Windows distributions when the
+    /// `Standalone` flavor is tried, which accepts both.
+    #[allow(unused)]
+    pub fn find_distribution_with_flavors<'f>(
+        &self,
+        target_triple: &str,
+        flavors: &'f [DistributionFlavor],
+        python_major_minor_version: Option<&str>,
+    ) -> Option<(&'f DistributionFlavor, PythonDistributionRecord)> {
+        flavors.iter().find_map(|flavor| {
+            self.find_distribution(target_triple, flavor, python_major_minor_version)
+                .map(|record| (flavor, record))
+        })
+    }
+
+    /// Find a Python distribution for an operating system and architecture.
+    ///
+    /// This is like `find_distribution()` except the target triple is
+    /// resolved by `target_triple_for()`.
+    #[allow(unused)]
+    pub fn find_by_os_arch(
+        &self,
+        os: TargetOs,
+        arch: TargetArch,
+        libc: Option<TargetLibc>,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Option<PythonDistributionRecord> {
+        self.find_distribution(
+            &target_triple_for(os, arch, libc),
+            flavor,
+            python_major_minor_version,
+        )
+    }
+
+    /// Find a Python distribution, preferring but not requiring a target triple.
+    ///
+    /// For uses where any architecture will do, e.g. compiling bytecode.
+    /// Target triples are tried in order of closeness to `preferred_triple`:
+    /// the triple itself, then the same operating system and architecture,
+    /// then the same operating system, then the same architecture, then any.
+    /// Ties are broken by lexical triple order. The distribution selected for
+    /// the first triple having one satisfying the other requirements is
+    /// returned. Errors with the reason the first triple tried failed if none
+    /// does.
+    #[allow(unused)]
+    pub fn find_distribution_near(
+        &self,
+        preferred_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Result<PythonDistributionRecord, DistributionError> {
+        let triples = std::iter::once(preferred_triple)
+            .chain(self.all_target_triples())
+            .unique()
+            .sorted_by_key(|triple| (triple_distance(preferred_triple, triple), *triple));
+
+        let mut first_error = None;
+
+        for triple in triples {
+            match self.find_distribution_detailed(
+                triple,
+                flavor,
+                python_major_minor_version,
+                policy,
+            ) {
+                Ok(record) => return Ok(record),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        Err(first_error.expect("preferred triple is always tried"))
+    }
+
+    /// Find a Python distribution ABI compatible with an existing Python interpreter.
+    ///
+    /// For embedding alongside a host Python, e.g. to load extension modules
+    /// built for it. Only distributions of the host's `X.Y` version whose
+    /// `PythonDistributionRecord::python_abi_tag()` equals `host_abi` are
+    /// considered. The patch version of `host_version` is ignored, as CPython
+    /// keeps its ABI stable within a minor version. Errors with
+    /// `DistributionError::AbiMismatch` if distributions of the version exist
+    /// but none has the ABI. See `HostPython::from_interpreter()` to detect
+    /// the version and ABI of an interpreter.
+    #[allow(unused)]
+    pub fn find_compatible_with(
+        &self,
+        host_version: &str,
+        host_abi: &str,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+    ) -> Result<PythonDistributionRecord, DistributionError> {
+        let version = host_version.parse::<PythonVersion>().map_err(|e| {
+            DistributionError::InvalidVersion {
+                version: host_version.to_string(),
+                message: format!("{}", e),
+            }
+        })?;
+        let major_minor = version.major_minor();
+        let policy = SelectionPolicy::default();
+
+        // Selecting without regard to ABI reports why nothing matches, if so.
+        let any_abi =
+            self.find_distribution_detailed(target_triple, flavor, Some(&major_minor), &policy)?;
+
+        let compatible = Self {
+            dists: self
+                .dists
+                .iter()
+                .filter(|dist| dist.python_abi_tag() == host_abi)
+                .cloned()
+                .collect(),
+        };
+
+        compatible
+            .find_distribution_detailed(target_triple, flavor, Some(&major_minor), &policy)
+            .map_err(|_| DistributionError::AbiMismatch {
+                host_abi: host_abi.to_string(),
+                python_version: major_minor,
+                distribution_abi: any_abi.python_abi_tag(),
+            })
+    }
+
+    /// Find a Python distribution for the target of the running Cargo build script.
+    ///
+    /// The target triple is read from the `TARGET` environment variable Cargo
+    /// sets for build scripts. Rust target triples are the triples records are
+    /// keyed by, so no translation is performed. Errors if `TARGET` is unset,
+    /// if no distributions are known for it, or if none match `flavor` and
+    /// `python_major_minor_version`.
+    #[allow(unused)]
+    pub fn find_for_cargo_target(
+        &self,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Result<PythonDistributionRecord> {
+        self.find_for_target_env(
+            std::env::var("TARGET").ok(),
+            flavor,
+            python_major_minor_version,
+        )
+    }
+
+    /// Implements `find_for_cargo_target()` given the value of `TARGET`.
+    fn find_for_target_env(
+        &self,
+        target: Option<String>,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Result<PythonDistributionRecord> {
+        let target = target.ok_or_else(|| {
+            anyhow!("TARGET environment variable not set; is this running in a Cargo build script?")
+        })?;
+
+        if !self.dists.iter().any(|dist| dist.target_triple == target) {
+            return Err(anyhow!(
+                "no Python distributions are known for target {} (known targets: {})",
+                target,
+                self.all_target_triples().join(", ")
+            ));
+        }
+
+        Ok(self.find_distribution_detailed(
+            &target,
+            flavor,
+            python_major_minor_version,
+            &SelectionPolicy::default(),
+        )?)
+    }
+
+    /// Find the distribution of the newest stable Python version, for any target triple.
+    ///
+    /// Pre-releases are ignored and versions are compared numerically. Among
+    /// records of the newest version, the lexically first target triple is
+    /// chosen, then the first registered record. Intended for smoke tests and
+    /// examples.
+    #[allow(unused)]
+    pub fn newest_overall(&self, flavor: &DistributionFlavor) -> Option<&PythonDistributionRecord> {
+        self.dists
+            .iter()
+            .filter(|dist| dist.python_version.pre.is_none())
+            .filter(|dist| dist.supported_flavors().contains(flavor))
+            .min_by_key(|dist| {
+                (
+                    std::cmp::Reverse(&dist.python_version),
+                    dist.target_triple.as_str(),
+                )
+            })
+    }
+
+    /// Find a Python distribution given requirements, without cloning it.
+    ///
+    /// This is like `find_distribution()` except a reference to the record in
+    /// this collection is returned.
+    #[allow(unused)]
+    pub fn find_distribution_ref(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Option<&PythonDistributionRecord> {
+        self.select_record(
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            &SelectionPolicy::default(),
+        )
+        .ok()
+    }
+
+    /// Find a Python distribution given requirements and a selection policy.
+    ///
+    /// This is like `find_distribution()` except records not satisfying
+    /// `policy` are excluded from consideration.
+    pub fn find_distribution_with_policy(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Option<PythonDistributionRecord> {
+        self.find_distribution_detailed(target_triple, flavor, python_major_minor_version, policy)
+            .ok()
+    }
+
+    /// Find a Python distribution given requirements and a selection policy.
+    ///
+    /// This is like `find_distribution_with_policy()` except the reason no
+    /// distribution could be selected is reported.
+    pub fn find_distribution_detailed(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Result<PythonDistributionRecord, DistributionError> {
+        self.query(
+            &DistributionQuery::new(target_triple)
+                .python_version_optional(python_major_minor_version)
+                .flavor(flavor.clone())
+                .policy(policy.clone()),
+        )
+    }
+
+    /// Find a Python distribution like `find_distribution_detailed()`, logging warnings.
+    ///
+    /// Selection doesn't log anything itself. This logs a warning for each
+    /// record satisfying the requirements that was skipped because its digest
+    /// is denied, see `set_denied_digests()`, and if the selected record is
+    /// `experimental`.
+    pub fn find_distribution_logged(
+        &self,
+        logger: &slog::Logger,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Result<PythonDistributionRecord, DistributionError> {
+        if let Ok(criteria) =
+            SelectionCriteria::new(target_triple, flavor, python_major_minor_version, policy)
+        {
+            for dist in criteria.denied(&self.dists) {
+                warn!(
+                    logger,
+                    "skipping Python distribution {} because its digest {} is denied",
+                    dist.location.filename(),
+                    dist.location.sha256().to_lowercase()
+                );
+            }
+        }
+
+        let record = self.find_distribution_detailed(
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            policy,
+        )?;
+
+        if record.experimental {
+            warn!(
+                logger,
+                "Python distribution {} for {} is experimental and may not be production ready",
+                record.location.filename(),
+                record.target_triple
+            );
+        }
+
+        Ok(record)
+    }
+
+    /// Find a Python distribution satisfying a `DistributionQuery`.
+    ///
+    /// Flavors of the query are tried in order, and the distribution selected
+    /// for the first flavor having one is returned. If none does, the reason
+    /// the first flavor failed is reported.
+    pub fn query(
+        &self,
+        query: &DistributionQuery,
+    ) -> Result<PythonDistributionRecord, DistributionError> {
+        let mut first_error = None;
+
+        for flavor in &query.flavors {
+            match self.select_record(
+                &query.target_triple,
+                flavor,
+                query.python_version.as_deref(),
+                &query.policy,
+            ) {
+                Ok(record) => return Ok(record.clone()),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| DistributionError::NotFound {
+            target_triple: query.target_triple.clone(),
+            flavor: "(none)".to_string(),
+            python_version: query
+                .python_version
+                .clone()
+                .unwrap_or_else(|| "3.9".to_string()),
+        }))
+    }
+
+    /// Find a distribution like `query()`, also describing how it was selected.
+    ///
+    /// The trace records, for each flavor attempted, the records remaining
+    /// after each filter, the tiebreak, and the selected record or the error
+    /// preventing selection. It is the structured counterpart of
+    /// `explain_selection()`.
+    #[allow(unused)]
+    pub fn query_traced(
+        &self,
+        query: &DistributionQuery,
+    ) -> (Option<PythonDistributionRecord>, SelectionTrace) {
+        let mut trace = SelectionTrace {
+            candidates: self.dists.len(),
+            attempts: vec![],
+        };
+
+        for flavor in &query.flavors {
+            let mut attempt = SelectionAttempt {
+                flavor: flavor.clone(),
+                filters: vec![],
+                tiebreak: String::new(),
+                finalists: vec![],
+                selected: None,
+                error_code: None,
+                error: None,
+            };
+
+            if let Ok(criteria) = SelectionCriteria::new(
+                &query.target_triple,
+                flavor,
+                query.python_version.as_deref(),
+                &query.policy,
+            ) {
+                attempt.filters = criteria.trace_filters(&self.dists);
+                attempt.tiebreak = criteria.tiebreak();
+                attempt.finalists = self
+                    .dists
+                    .iter()
+                    .filter(|dist| criteria.matches(dist))
+                    .map(|dist| dist.location.filename().to_string())
+                    .collect();
+            }
+
+            match self.select_record(
+                &query.target_triple,
+                flavor,
+                query.python_version.as_deref(),
+                &query.policy,
+            ) {
+                Ok(record) => {
+                    attempt.selected = Some(record.location.filename().to_string());
+                    trace.attempts.push(attempt);
+
+                    return (Some(record.clone()), trace);
+                }
+                Err(e) => {
+                    attempt.error_code = Some(e.code());
+                    attempt.error = Some(e.to_string());
+                    trace.attempts.push(attempt);
+                }
+            }
+        }
+
+        (None, trace)
+    }
+
+    /// Find distributions for several requests, reporting the outcome of each.
+    ///
+    /// Each request is resolved with `find_distribution_detailed()`, so one
+    /// request failing doesn't affect others. Results are in request order. No
+    /// distribution is downloaded.
+    #[allow(unused)]
+    pub fn resolve_batch(
+        &self,
+        requests: impl IntoIterator<Item = DistributionRequest>,
+        policy: &SelectionPolicy,
+    ) -> Vec<(
+        DistributionRequest,
+        Result<PythonDistributionRecord, DistributionError>,
+    )> {
+        requests
+            .into_iter()
+            .map(|request| {
+                let result = self.find_distribution_detailed(
+                    &request.target_triple,
+                    &request.flavor,
+                    request.python_version.as_deref(),
+                    policy,
+                );
+
+                (request, result)
+            })
+            .collect()
+    }
+
+    /// Select the record satisfying requirements and a selection policy.
+    fn select_record(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Result<&PythonDistributionRecord, DistributionError> {
+        let not_found = || DistributionError::NotFound {
+            target_triple: target_triple.to_string(),
+            flavor: flavor.to_string(),
+            python_version: python_major_minor_version.unwrap_or("3.9").to_string(),
+        };
+
+        let criteria =
+            SelectionCriteria::new(target_triple, flavor, python_major_minor_version, policy)?;
+
+        let record = criteria.select(&self.dists).ok_or_else(|| {
+            criteria
+                .budget_exceeded(&self.dists)
+                .unwrap_or_else(not_found)
+        })?;
+
+        policy.check_selected(record)?;
+
+        Ok(record)
+    }
+
+    /// Explain how a distribution is selected for the given requirements.
+    ///
+    /// Returns a multi-line human readable description of the normalized
+    /// request, how many records remain after each selection filter, how ties
+    /// are broken, and the selected distribution or why none was selected.
+    /// Arguments are as for `find_distribution_detailed()`. The output only
+    /// depends on the arguments and the records in this collection.
+    #[allow(unused)]
+    pub fn explain_selection(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> String {
+        let mut lines = vec![format!(
+            "request: target triple {}, flavor {}, python version {}",
+            target_triple,
+            flavor,
+            python_major_minor_version.unwrap_or(match policy.version_preference {
+                VersionPreference::Newest => "3.9 (default)",
+                VersionPreference::Oldest => "any (default)",
+            })
+        )];
+
+        match SelectionCriteria::new(target_triple, flavor, python_major_minor_version, policy) {
+            Ok(criteria) => {
+                lines.push(format!(
+                    "normalized python version: {}",
+                    match &criteria.version {
+                        VersionRequirement::Any => "any".to_string(),
+                        VersionRequirement::MajorMinor(version) => format!("{}.*", version),
+                        VersionRequirement::Exact(version) => version.to_string(),
+                        VersionRequirement::Specifier(_) => format!(
+                            "specifier {}",
+                            python_major_minor_version.unwrap_or_default().trim()
+                        ),
+                    }
+                ));
+                lines.push(format!("candidates: {}", self.dists.len()));
+
+                for filter in criteria.trace_filters(&self.dists) {
+                    lines.push(format!(
+                        "after {} filter: {}",
+                        filter.name, filter.remaining
+                    ));
+                }
+
+                lines.push(format!("tiebreak: {}", criteria.tiebreak()));
+            }
+            Err(e) => {
+                lines.push(format!("error: {}", e));
+                return lines.join("\n");
+            }
+        }
+
+        match self.find_distribution_detailed(
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            policy,
+        ) {
+            Ok(record) => {
+                lines.push(format!("selected: {}", record.location.filename()));
+                lines.push(format!("location: {}", record.location.source()));
+                lines.push(format!("sha256: {}", record.location.sha256()));
+            }
+            Err(e) => {
+                lines.push(format!("error: {}", e));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    /// Select, obtain, and extract a Python distribution.
+    ///
+    /// This combines `find_distribution()` with downloading (or copying) the
+    /// distribution archive into `distributions_dir`, verifying it, and
+    /// extracting it there. Previously obtained archives and extractions are
+    /// reused. If the record has `seed_packages`, that archive is verified and
+    /// overlaid onto an extraction of the distribution of its own. See
+    /// `StandaloneDistribution::from_location_with_seed()`.
+    ///
+    /// Returns the selected record and the directory the distribution was
+    /// extracted to.
+    #[allow(unused)]
+    pub fn resolve(
+        &self,
+        logger: &slog::Logger,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        distributions_dir: &Path,
+    ) -> Result<ResolvedDistribution> {
+        self.resolve_with_policy(
+            logger,
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            distributions_dir,
+            &SelectionPolicy::default(),
+        )
+    }
+
+    /// Select, obtain, and extract a Python distribution given a selection policy.
+    ///
+    /// This is like `resolve()` except records not satisfying `policy` are
+    /// excluded from consideration. A warning is logged if the selected
+    /// version differs from the requested one. See
+    /// `ResolvedDistribution::version_differs()`.
+    #[allow(unused)]
+    pub fn resolve_with_policy(
+        &self,
+        logger: &slog::Logger,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        distributions_dir: &Path,
+        policy: &SelectionPolicy,
+    ) -> Result<ResolvedDistribution> {
+        let record = self.find_distribution_logged(
+            logger,
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            policy,
+        )?;
+
+        if let Some(seed) = &record.seed_packages {
+            warn!(logger, "overlaying seed packages from {}", seed.filename());
+        }
+
+        let dist = StandaloneDistribution::from_location_with_seed(
+            logger,
+            &record.location,
+            record.seed_packages.as_ref(),
+            distributions_dir,
+            &[],
+        )?;
+
+        let resolved = ResolvedDistribution {
+            requested_version: python_major_minor_version.map(|v| v.to_string()),
+            selected_version: record.python_version.clone(),
+            record,
+            path: dist.base_dir.clone(),
+        };
+
+        if resolved.version_differs() {
+            warn!(
+                logger,
+                "Python {} was requested but Python {} was selected",
+                resolved.requested_version.as_deref().unwrap_or_default(),
+                resolved.selected_version
+            );
+        }
+
+        Ok(resolved)
+    }
+
+    /// Convert into a `FrozenDistributionCollection`.
+    #[allow(unused)]
+    pub fn freeze(self) -> FrozenDistributionCollection {
+        FrozenDistributionCollection {
+            dists: self.dists.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    /// Find the record for a distribution obtained from a given URL.
+    ///
+    /// Only records having a `PythonDistributionLocation::Url` location are
+    /// considered.
+    #[allow(unused)]
+    pub fn find_by_url(&self, url: &str) -> Option<&PythonDistributionRecord> {
+        self.dists.iter().find(|dist| match &dist.location {
+            PythonDistributionLocation::Url { url: candidate, .. } => candidate == url,
+            PythonDistributionLocation::Local { .. }
+            | PythonDistributionLocation::Oci { .. }
+            | PythonDistributionLocation::Git { .. }
+            | PythonDistributionLocation::Parts { .. } => false,
+        })
+    }
+
+    /// Validate records in this collection for consistency.
+    ///
+    /// Returns a list of problems found. An empty list means no problems were
+    /// detected.
+    ///
+    /// Records sharing a URL must be identical. Differing metadata for the
+    /// same archive is reported as an error.
+    #[allow(unused)]
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+        let mut by_url: BTreeMap<&str, &PythonDistributionRecord> = BTreeMap::new();
+
+        for dist in &self.dists {
+            if let Some(expected) = expected_prebuilt_extension_module_support(dist) {
+                if dist.supports_prebuilt_extension_modules != expected {
+                    issues.push(ValidationIssue {
+                        severity: ValidationSeverity::Warning,
+                        message: format!(
+                            "{} declares supports_prebuilt_extension_modules={} but {} distributions are expected to declare {}",
+                            dist.location.filename(),
+                            dist.supports_prebuilt_extension_modules,
+                            dist.target_triple,
+                            expected
+                        ),
+                    });
+                }
+            }
+
+            if let PythonDistributionLocation::Url { url, .. } = &dist.location {
+                match by_url.get(url.as_str()) {
+                    Some(first) if *first != dist => {
+                        issues.push(ValidationIssue {
+                            severity: ValidationSeverity::Error,
+                            message: format!(
+                                "{} is declared by multiple records with conflicting metadata ({} {} and {} {})",
+                                url,
+                                first.target_triple,
+                                first.python_version,
+                                dist.target_triple,
+                                dist.python_version
+                            ),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        by_url.insert(url, dist);
+                    }
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Validate records against the content of their archives.
+    ///
+    /// This is a heavier variant of `validate()`: every archive is read, which
+    /// may download it. The target triple and version in each archive's
+    /// `PYTHON.json` must match its record and the archive must contain the
+    /// artifacts needed by each flavor the record supports. e.g. libpython
+    /// object files for `standalone_static`. Archives that can't be obtained
+    /// are reported as warnings, so this can run without network access.
+    #[allow(unused)]
+    pub fn validate_deep(&self) -> Vec<ValidationIssue> {
+        let mut issues = vec![];
+
+        for dist in &self.dists {
+            match summarize_archive(&dist.location) {
+                Ok(summary) => issues.extend(archive_issues(dist, &summary)),
+                Err(e) => issues.push(ValidationIssue {
+                    severity: ValidationSeverity::Warning,
+                    message: format!("could not inspect {}: {}", dist.location.filename(), e),
+                }),
+            }
+        }
+
+        issues
+    }
+
+    /// Compute a digest identifying the content of this collection.
+    ///
+    /// The fingerprint covers every field of every record and changes
+    /// whenever a record is added, removed, or modified. It does not depend on
+    /// the order records were registered in.
+    ///
+    /// Returns a hex encoded SHA-256 digest.
+    #[allow(unused)]
+    pub fn fingerprint(&self) -> String {
+        let mut lines = self
+            .dists
+            .iter()
+            .map(|dist| {
+                let describe = |location: &PythonDistributionLocation| match location {
+                    PythonDistributionLocation::Local { local_path, sha256 } => {
+                        format!("local:{}:{}", local_path, sha256)
+                    }
+                    PythonDistributionLocation::Url { url, sha256 } => {
+                        format!("url:{}:{}", url, sha256)
+                    }
+                    PythonDistributionLocation::Oci { reference, digest } => {
+                        format!("oci:{}:{}", reference, digest)
+                    }
+                    PythonDistributionLocation::Git {
+                        repo,
+                        rev,
+                        path,
+                        sha256,
+                    } => format!("git:{}:{}:{}:{}", repo, rev, path, sha256),
+                    PythonDistributionLocation::Parts { urls, sha256 } => {
+                        format!("parts:{}:{}", urls.join(" "), sha256)
+                    }
+                };
+
+                [
+                    dist.target_triple.as_str(),
+                    dist.python_major_minor_version.as_str(),
+                    &dist.python_version.to_string(),
+                    if dist.supports_prebuilt_extension_modules {
+                        "dynamic"
+                    } else {
+                        "static"
+                    },
+                    &describe(&dist.location),
+                    &dist
+                        .optimizations
+                        .map(|o| format!("{:?}", o))
+                        .unwrap_or_default(),
+                    dist.min_os_version.as_deref().unwrap_or(""),
+                    dist.strip_prefix.as_deref().unwrap_or(""),
+                    &dist
+                        .seed_packages
+                        .as_ref()
+                        .map(describe)
+                        .unwrap_or_default(),
+                    &dist
+                        .labels
+                        .iter()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .join(","),
+                    &format!("{:?}", dist.layout),
+                    &dist
+                        .archive_size
+                        .map(|size| size.to_string())
+                        .unwrap_or_default(),
+                    if dist.experimental {
+                        "experimental"
+                    } else {
+                        ""
+                    },
+                    &dist
+                        .library_build_modes
+                        .iter()
+                        .map(|(library, mode)| format!("{}={}", library, mode))
+                        .join(","),
+                    &dist
+                        .extension_modules
+                        .as_ref()
+                        .map(|modules| modules.iter().join(","))
+                        .unwrap_or_else(|| "?".to_string()),
+                ]
+                .join("\0")
+            })
+            .collect::<Vec<_>>();
+
+        lines.sort();
+
+        let mut hasher = Sha256::new();
+        for line in lines {
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+        }
+
+        hex::encode(hasher.finalize())
+    }
+
+    /// Find the distributions needed by a set of requests that aren't cached.
+    ///
+    /// Each request is a target triple, flavor, and Python version as accepted
+    /// by `find_distribution()`. Each is resolved and the selected records
+    /// whose archives aren't present in `cache_dir` are returned, once each and
+    /// in request order. Nothing is downloaded.
+    ///
+    /// Returns an error if a request can't be resolved.
+    #[allow(unused)]
+    pub fn missing_from_cache(
+        &self,
+        requests: &[(String, DistributionFlavor, String)],
+        cache_dir: &Path,
+    ) -> Result<Vec<PythonDistributionRecord>, DistributionError> {
+        let mut missing: Vec<PythonDistributionRecord> = vec![];
+
+        for (target_triple, flavor, version) in requests {
+            let record = self.find_distribution_detailed(
+                target_triple,
+                flavor,
+                Some(version),
+                &SelectionPolicy::default(),
+            )?;
+
+            if cached_distribution_path(&record.location, cache_dir).is_none()
+                && !missing.contains(&record)
+            {
+                missing.push(record);
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Check whether every registered distribution can be obtained.
+    ///
+    /// Up to `concurrency` checks are performed in parallel. Each check is
+    /// preceded by a small random delay to avoid bursts of requests to the same
+    /// server. See `check_location_availability()` for how availability is
+    /// determined.
+    ///
+    /// Results are returned in the order records were registered, regardless
+    /// of the order checks complete in.
+    #[allow(unused)]
+    pub fn check_availability(&self, concurrency: usize) -> Vec<AvailabilityReport> {
+        let records = Arc::new(self.dists.clone());
+        let next = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(Mutex::new(vec![None; records.len()]));
+
+        let workers = (0..concurrency.max(1).min(records.len()))
+            .map(|_| {
+                let records = records.clone();
+                let next = next.clone();
+                let results = results.clone();
+
+                std::thread::spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let record = match records.get(index) {
+                        Some(record) => record,
+                        None => break,
+                    };
+
+                    std::thread::sleep(availability_jitter());
+
+                    let available = check_location_availability(&record.location)
+                        .map_err(|e| format!("{:#}", e));
+
+                    results.lock().unwrap()[index] = Some(available);
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            if worker.join().is_err() {
+                break;
+            }
+        }
+
+        let results = results.lock().unwrap();
+
+        self.dists
+            .iter()
+            .zip(results.iter())
+            .map(|(record, available)| AvailabilityReport {
+                record: record.clone(),
+                available: available
+                    .clone()
+                    .unwrap_or_else(|| Err("availability check did not complete".to_string())),
+            })
+            .collect()
+    }
+
+    /// Obtain records for all registered distributions.
+    #[allow(unused)]
+    pub fn iter(&self) -> impl Iterator<Item = &PythonDistributionRecord> {
+        self.dists.iter()
+    }
+
+    /// Obtain copies of records matching a predicate.
+    ///
+    /// Records are in registration order.
+    #[allow(unused)]
+    pub fn find_all(
+        &self,
+        pred: impl Fn(&PythonDistributionRecord) -> bool,
+    ) -> Vec<PythonDistributionRecord> {
+        self.dists
+            .iter()
+            .filter(|dist| pred(dist))
+            .cloned()
+            .collect()
+    }
+
+    /// The number of registered records.
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.dists.len()
+    }
+
+    /// Whether no records are registered.
+    #[allow(unused)]
+    pub fn is_empty(&self) -> bool {
+        self.dists.is_empty()
+    }
+
+    /// Count records by target triple, Python version, and flavor.
+    #[allow(unused)]
+    pub fn stats(&self) -> CollectionStats {
+        let mut stats = CollectionStats {
+            records: self.dists.len(),
+            ..Default::default()
+        };
+
+        for dist in &self.dists {
+            *stats
+                .by_target_triple
+                .entry(dist.target_triple.clone())
+                .or_default() += 1;
+            *stats
+                .by_python_version
+                .entry(dist.python_major_minor_version.clone())
+                .or_default() += 1;
+
+            for flavor in dist.supported_flavors().iter() {
+                *stats.by_flavor.entry(flavor.to_string()).or_default() += 1;
+            }
+        }
+
+        stats
+    }
+
+    /// Preview the effect of adding target triple aliases.
+    ///
+    /// `aliases` maps an alias triple to the target triple it would resolve
+    /// to. For every alias, each `X.Y` Python version and distribution flavor
+    /// available for the aliased triple is queried. Queries that currently
+    /// find no distribution but would find one through the alias are
+    /// reported, ordered by alias, version, and flavor.
+    ///
+    /// This does not modify the collection.
+    #[allow(unused)]
+    pub fn alias_impact(&self, aliases: &BTreeMap<String, String>) -> Vec<AliasImpact> {
+        let mut impacts = vec![];
+
+        for (alias, target) in aliases {
+            let versions = self
+                .dists
+                .iter()
+                .filter(|dist| &dist.target_triple == target)
+                .map(|dist| dist.python_major_minor_version.as_str())
+                .sorted()
+                .dedup()
+                .collect::<Vec<_>>();
+
+            for version in versions {
+                for flavor in [
+                    DistributionFlavor::Standalone,
+                    DistributionFlavor::StandaloneStatic,
+                    DistributionFlavor::StandaloneDynamic,
+                ] {
+                    if self
+                        .find_distribution(alias, &flavor, Some(version))
+                        .is_some()
+                    {
+                        continue;
+                    }
+
+                    if let Some(record) = self.find_distribution(target, &flavor, Some(version)) {
+                        impacts.push(AliasImpact {
+                            target_triple: alias.clone(),
+                            python_major_minor_version: version.to_string(),
+                            flavor,
+                            record,
+                        });
+                    }
+                }
+            }
+        }
+
+        impacts
+    }
+
+    /// Group records by the `python-build-standalone` release they belong to.
+    ///
+    /// Keys are release tags as returned by `PythonDistributionRecord::release_tag()`.
+    /// Records without a release tag are omitted. Records of a release are in
+    /// registration order.
+    #[allow(unused)]
+    pub fn records_by_release(&self) -> BTreeMap<String, Vec<&PythonDistributionRecord>> {
+        let mut releases = BTreeMap::<String, Vec<&PythonDistributionRecord>>::new();
+
+        for dist in &self.dists {
+            if let Some(tag) = dist.release_tag() {
+                releases.entry(tag.to_string()).or_default().push(dist);
+            }
+        }
+
+        releases
+    }
+
+    /// Records whose build optimizations are unknown.
+    ///
+    /// Records are in registration order. Useful for tracking the backfill of
+    /// `optimizations` metadata.
+    #[allow(unused)]
+    pub fn records_missing_optimization(&self) -> Vec<&PythonDistributionRecord> {
+        self.dists
+            .iter()
+            .filter(|dist| dist.optimizations.is_none())
+            .collect()
+    }
+
+    /// All target triples of distributions in this collection.
+    #[allow(unused)]
+    pub fn all_target_triples(&self) -> impl Iterator<Item = &str> {
+        self.dists
+            .iter()
+            .map(|dist| dist.target_triple.as_str())
+            .sorted()
+            .dedup()
+    }
+
+    /// Compare which target triples have distributions of two `X.Y` Python versions.
+    ///
+    /// e.g. `("3.9", "3.10")` reports triples still lacking a 3.10 distribution
+    /// in `only_a`.
+    #[allow(unused)]
+    pub fn triple_coverage_diff(&self, version_a: &str, version_b: &str) -> CoverageDiff {
+        let triples = |version: &str| {
+            self.dists
+                .iter()
+                .filter(|dist| dist.python_major_minor_version == version)
+                .map(|dist| dist.target_triple.clone())
+                .collect::<BTreeSet<_>>()
+        };
+
+        let a = triples(version_a);
+        let b = triples(version_b);
+
+        CoverageDiff {
+            only_a: a.difference(&b).cloned().collect(),
+            only_b: b.difference(&a).cloned().collect(),
+            both: a.intersection(&b).cloned().collect(),
+        }
+    }
+
+    /// Target triples having at least one distribution not marked `experimental`.
+    #[allow(unused)]
+    pub fn stable_target_triples(&self) -> impl Iterator<Item = &str> {
+        self.dists
+            .iter()
+            .filter(|dist| !dist.experimental)
+            .map(|dist| dist.target_triple.as_str())
+            .sorted()
+            .dedup()
+    }
+
+    /// All `(target triple, major.minor version, flavor)` keys distributions can be selected by.
+    ///
+    /// Keys are ordered by target triple, then numerically by version, then by
+    /// flavor.
+    #[allow(unused)]
+    pub fn all_keys(&self) -> Vec<(&str, &str, DistributionFlavor)> {
+        let mut keys = self
+            .dists
+            .iter()
+            .flat_map(|dist| {
+                dist.supported_flavors().iter().map(move |flavor| {
+                    (
+                        dist.target_triple.as_str(),
+                        dist.python_major_minor_version.as_str(),
+                        flavor,
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        keys.sort_by(|a, b| {
+            let version = |v: &str| v.parse::<PythonVersion>().ok();
+
+            (a.0, version(a.1), a.2.to_string()).cmp(&(b.0, version(b.1), b.2.to_string()))
+        });
+        keys.dedup();
+
+        keys
+    }
+
+    /// Render the records of this collection as CSV, e.g. for spreadsheets.
+    ///
+    /// Columns are `target_triple`, `python_version`, `flavor`,
+    /// `optimizations`, `url`, and `sha256`, preceded by a header row. The
+    /// flavor is `standalone_dynamic` or `standalone_static`. For records not
+    /// obtained from a single URL, `url` is as described by
+    /// `PythonDistributionLocation::source()`. Rows are sorted by target triple,
+    /// then numerically by version, then by the remaining columns, so output
+    /// is stable across registration order.
+    #[allow(unused)]
+    pub fn to_csv(&self) -> String {
+        let field = |value: &str| {
+            if value.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        };
+
+        let mut rows = self
+            .dists
+            .iter()
+            .map(|dist| {
+                let flavor = if dist.supports_prebuilt_extension_modules {
+                    DistributionFlavor::StandaloneDynamic
+                } else {
+                    DistributionFlavor::StandaloneStatic
+                };
+                (
+                    dist.target_triple.as_str(),
+                    &dist.python_version,
+                    [
+                        flavor.to_string(),
+                        dist.optimizations
+                            .map(|o| o.to_string())
+                            .unwrap_or_default(),
+                        dist.location.source(),
+                        dist.location.sha256().to_string(),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+
+        let mut csv =
+            String::from("target_triple,python_version,flavor,optimizations,url,sha256\n");
+
+        for (triple, version, rest) in rows {
+            let mut fields = vec![field(triple), field(&version.to_string())];
+            fields.extend(rest.iter().map(|value| field(value)));
+
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Render a deterministic table of which distribution is selected for every key.
+    ///
+    /// Each line is `<triple> <version> <flavor> -> <location>`, for every key
+    /// of `all_keys()`. Comparing this against a stored copy catches unintended
+    /// changes to selection behavior.
+    #[allow(unused)]
+    pub fn selection_snapshot(&self) -> String {
+        self.all_keys()
+            .into_iter()
+            .map(|(triple, version, flavor)| {
+                let selected = match self.find_distribution(triple, &flavor, Some(version)) {
+                    Some(record) => record.location.source(),
+                    None => "none".to_string(),
+                };
+
+                format!("{} {} {} -> {}\n", triple, version, flavor, selected)
+            })
+            .collect()
+    }
+}
+
+/// An immutable collection of Python distributions.
+///
+/// Records are held behind `Arc`, making lookups cheap and allowing returned
+/// records to be shared across threads without cloning them. Instances are
+/// obtained via `PythonDistributionCollection::freeze()`.
+#[derive(Clone, Debug)]
+pub struct FrozenDistributionCollection {
+    dists: Vec<Arc<PythonDistributionRecord>>,
+}
+
+impl FrozenDistributionCollection {
+    /// Find a Python distribution given requirements.
+    ///
+    /// See `PythonDistributionCollection::find_distribution()` for semantics.
+    #[allow(unused)]
+    pub fn find_distribution(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+    ) -> Option<Arc<PythonDistributionRecord>> {
+        self.find_distribution_with_policy(
+            target_triple,
+            flavor,
+            python_major_minor_version,
+            &SelectionPolicy::default(),
+        )
+    }
+
+    /// Find a Python distribution given requirements and a selection policy.
+    ///
+    /// See `PythonDistributionCollection::find_distribution_with_policy()` for
+    /// semantics.
+    #[allow(unused)]
+    pub fn find_distribution_with_policy(
+        &self,
+        target_triple: &str,
+        flavor: &DistributionFlavor,
+        python_major_minor_version: Option<&str>,
+        policy: &SelectionPolicy,
+    ) -> Option<Arc<PythonDistributionRecord>> {
+        let criteria =
+            SelectionCriteria::new(target_triple, flavor, python_major_minor_version, policy)
+                .ok()?;
+
+        let record = criteria.select(&self.dists)?;
+
+        policy.check_selected(record).ok()?;
+
+        Some(record.clone())
+    }
+
+    /// Obtain records for all distributions.
+    #[allow(unused)]
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<PythonDistributionRecord>> {
+        self.dists.iter()
+    }
+}
+
+/// The `python-build-standalone` release all built-in distributions come from.
+#[allow(unused)]
+pub const BUILTIN_RELEASE_TAG: &str = "20210724";
+
+pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(|| {
+    let dists = vec![
+        // Linux glibc linked.
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "e72b8f82d6e56f7fff9563b1d65aca24efe8237fa690b3f328e578ff9f8587fb".to_string(),
+            },
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "343e2d349779efb7d46f7eb01ec6a202bff14293616ce8103bc8060c777bb231".to_string(),
+            },
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+
+        // Linux musl.
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-unknown-linux-musl-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "29652e8dec55f36b11e009df675dd404fe2345c3a7cb1c1bcc5cedca0c9cf635".to_string(),
+            },
+            target_triple: "x86_64-unknown-linux-musl".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-unknown-linux-musl-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "70974f0c687442aacf3b821379ee9423cdcf02809b39bd61f9d959f79b0d1630".to_string(),
+            },
+            target_triple: "x86_64-unknown-linux-musl".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+
+        // Whether shared or static distributions are chosen on Windows is
+        // decided by `WindowsFlavorPreference`, not by the order here.
+
+        // Windows shared.
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-i686-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "af5eeaccfb6ab9f1f894d82100c542db29324d703d7dd5d2c433c45b369e3da2".to_string(),
+            },
+            target_triple: "i686-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-i686-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "0cc12b92d6fa427bbb5e14d2a9f05d90d651558887566dab9391adddcadd9e15".to_string(),
+            },
+            target_triple: "i686-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "ad7a3f64383ff92bbf56478521bae602e6e4a6be6936b0f50e42e83575b4e527".to_string(),
+            },
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "6525f62ce45d1629b68b08836d0ab681cf3c6d2cf9c3fd63c8fd6b36a90fb9db".to_string(),
+            },
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+
+        // Windows static.
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-i686-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "b8b64491f52fdfd701d049f9edfd762e26b284b429cfd13c4d6ee630233d81c2".to_string(),
+            },
+            target_triple: "i686-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-i686-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "82235f8de14ec3123c20406fb9732766b6c0141e266824864e3cce43117b4330".to_string(),
+            },
+            target_triple: "i686-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "5271f109a142374f4ccfd4061190fcbb9deec5604bcd99a03e3bc8cdfe9e354b".to_string(),
+            },
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
+                sha256: "0bd9ac2d3221e8588b9e728b7b774807724b845c299682ae9a990c406ff5f83b".to_string(),
+            },
+            target_triple: "x86_64-pc-windows-msvc".to_string(),
+            supports_prebuilt_extension_modules: false,
+            optimizations: Some(BuildOptimizations::Noopt),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+
+        // macOS.
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-aarch64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "f5f9b84302aafdb0792d17c9301581fbce32a0eb9bc0838b2dffa023b1522a5e".to_string(),
+            },
+            target_triple: "aarch64-apple-darwin".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: Some("11.0".to_string()),
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.8".to_string(),
+            python_version: PythonVersion::new(3, 8, 11),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "291bb8ca565338959dbb93908557d52ce4de65e4d8bacfd0e3dc1c69917627e3".to_string(),
+            },
+            target_triple: "x86_64-apple-darwin".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: Some("10.9".to_string()),
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+        PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
+                sha256: "9e11a09bf1c2e4c1e2c4d0f403e7199186a6f993ecc135e31aa57294b5634cc7".to_string(),
+            },
+            target_triple: "x86_64-apple-darwin".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: Some("10.9".to_string()),
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        },
+    ];
+
+    PythonDistributionCollection { dists }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_target_triples() {
+        assert_eq!(
+            PYTHON_DISTRIBUTIONS
+                .all_target_triples()
+                .collect::<Vec<_>>(),
+            vec![
+                "aarch64-apple-darwin",
+                "i686-pc-windows-msvc",
+                "x86_64-apple-darwin",
+                "x86_64-pc-windows-msvc",
+                "x86_64-unknown-linux-gnu",
+                "x86_64-unknown-linux-musl",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_min_optimization() {
+        let policy = SelectionPolicy {
+            min_optimization: Some(BuildOptimizations::Pgo),
+            ..SelectionPolicy::default()
+        };
+
+        // musl distributions are only available as noopt builds.
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution_with_policy(
+                "x86_64-unknown-linux-musl",
+                &DistributionFlavor::Standalone,
+                None,
+                &policy,
+            )
+            .is_none());
+
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution_with_policy(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+                &policy,
+            )
+            .unwrap();
+        assert_eq!(record.optimizations, Some(BuildOptimizations::Pgo));
+
+        // The static Windows distributions are noopt. So requiring PGO leaves
+        // no static distribution.
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution_with_policy(
+                "x86_64-pc-windows-msvc",
+                &DistributionFlavor::StandaloneStatic,
+                None,
+                &policy,
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_version_specifier() -> Result<()> {
+        let matches = |spec: &str, version: &str| -> Result<bool> {
+            Ok(VersionSpecifier::parse(spec)?.matches(&version.parse()?))
+        };
+
+        assert!(matches("==3.9.*", "3.9.6")?);
+        assert!(!matches("==3.9.*", "3.10.0")?);
+        assert!(matches("!=3.9.*", "3.8.11")?);
+        assert!(matches(">=3.8", "3.8.0")?);
+        assert!(matches(">=3.8, <3.10", "3.9.6")?);
+        assert!(!matches(">=3.8,<3.10", "3.10.1")?);
+        assert!(matches("~=3.8", "3.9.6")?);
+        assert!(!matches("~=3.8.5", "3.9.0")?);
+        assert!(matches("~=3.8.5", "3.8.11")?);
+        assert!(matches("==3.9", "3.9.0")?);
+        assert!(!matches(">3.9.6", "3.9.6")?);
+        assert!(!matches(">=3.9", "3.10.0rc1")?);
+        assert!(matches(">=3.10.0a1", "3.10.0rc1")?);
+        assert!(!matches("<3.10.0", "3.10.0rc1")?);
+
+        for spec in [">=3.9.*", "3.9,<3.10", "==", ">=foo", "~=3"] {
+            assert!(VersionSpecifier::parse(spec).is_err(), "{}", spec);
+        }
+
+        let gnu = "x86_64-unknown-linux-gnu";
+        let find = |spec| {
+            PYTHON_DISTRIBUTIONS
+                .find_distribution(gnu, &DistributionFlavor::Standalone, Some(spec))
+                .map(|dist| dist.python_version.to_string())
+        };
+
+        assert_eq!(find(">=3.8"), Some("3.9.6".to_string()));
+        assert_eq!(find("<3.9"), Some("3.8.11".to_string()));
+        assert_eq!(find("==3.8.*"), Some("3.8.11".to_string()));
+        assert_eq!(find(">=3.10"), None);
+
+        let err = PYTHON_DISTRIBUTIONS
+            .find_distribution_detailed(
+                gnu,
+                &DistributionFlavor::Standalone,
+                Some(">=3.9.*"),
+                &SelectionPolicy::default(),
+            )
+            .unwrap_err();
+        assert!(matches!(err, DistributionError::InvalidVersion { .. }));
+        assert!(err.to_string().contains("~=3.8"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_preference_oldest() {
+        let gnu = "x86_64-unknown-linux-gnu";
+        let flavor = DistributionFlavor::Standalone;
+
+        let mut dists = PYTHON_DISTRIBUTIONS.clone();
+        let mut newer = dists.find_distribution(gnu, &flavor, Some("3.9")).unwrap();
+        newer.python_major_minor_version = "3.10".to_string();
+        newer.python_version = PythonVersion::new(3, 10, 0);
+        dists.register(newer);
+
+        let oldest = SelectionPolicy {
+            version_preference: VersionPreference::Oldest,
+            ..SelectionPolicy::default()
+        };
+        let find = |version, policy: &SelectionPolicy| {
+            dists
+                .find_distribution_with_policy(gnu, &flavor, version, policy)
+                .map(|dist| dist.python_version.to_string())
+        };
+
+        assert_eq!(find(None, &oldest), Some("3.8.11".to_string()));
+        assert_eq!(find(Some(">=3.9"), &oldest), Some("3.9.6".to_string()));
+        assert_eq!(
+            find(Some(">=3.9"), &SelectionPolicy::default()),
+            Some("3.10.0".to_string())
+        );
+        assert_eq!(find(Some("3.10"), &oldest), Some("3.10.0".to_string()));
+        assert_eq!(
+            find(None, &SelectionPolicy::default()),
+            Some("3.9.6".to_string())
+        );
+    }
+
+    #[test]
+    fn test_python_version() -> Result<()> {
+        let v = |s: &str| s.parse::<PythonVersion>();
+
+        assert!(v("3.9")? < v("3.10")?);
+        assert!(v("3.9.6")? < v("3.9.10")?);
+        assert_eq!(v("3.9")?, v("3.9.0")?);
+        assert!(v("3.10.0a7")? < v("3.10.0b2")?);
+        assert!(v("3.10.0b2")? < v("3.10.0rc1")?);
+        assert!(v("3.10.0rc1")? < v("3.10.0")?);
+        assert!(v("3.10.0")? < v("3.10.0.post1")?);
+        assert!(v("3.10.0rc1.post1")? < v("3.10.0")?);
+        assert!(v("3.11.0")? < v("1!3.9.6")?);
+
+        assert_eq!(
+            v("1!3.9.6-rc.1-post2")?,
+            PythonVersion {
+                epoch: 1,
+                major: 3,
+                minor: 9,
+                patch: Some(6),
+                pre: Some(PreRelease::ReleaseCandidate(1)),
+                post: Some(2),
+            }
+        );
+
+        for (value, expected) in [
+            ("3.9.6.post1", "3.9.6.post1"),
+            ("3.9.6-post1", "3.9.6.post1"),
+            ("3.9.6rev1", "3.9.6.post1"),
+            ("3.9.6.post", "3.9.6.post0"),
+            ("3.10.0rc1.post2", "3.10.0rc1.post2"),
+            ("1!3.9.6", "1!3.9.6"),
+            ("0!3.9", "3.9"),
+        ] {
+            assert_eq!(v(value)?.to_string(), expected, "{}", value);
+        }
+
+        for value in [
+            "!3.9",
+            "x!3.9",
+            "3.9.6.post1rc1",
+            "3.9.6rc1rc2",
+            "3.9.6.post1.post2",
+        ] {
+            assert!(v(value).is_err(), "{}", value);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_python_version() -> Result<()> {
+        assert_eq!(normalize_python_version("3.9")?, "3.9");
+        assert_eq!(normalize_python_version("3.9.7")?, "3.9.7");
+        assert_eq!(normalize_python_version(" 3.9.7 ")?, "3.9.7");
+
+        for (value, expected) in [
+            ("3.9.7rc1", "3.9.7rc1"),
+            ("3.9.7-rc1", "3.9.7rc1"),
+            ("3.9.7_rc1", "3.9.7rc1"),
+            ("3.9.7.rc1", "3.9.7rc1"),
+            ("3.9.7-rc.1", "3.9.7rc1"),
+            ("3.9.7RC1", "3.9.7rc1"),
+            ("3.9.7c1", "3.9.7rc1"),
+            ("3.9.7pre1", "3.9.7rc1"),
+            ("3.9.7preview1", "3.9.7rc1"),
+            ("3.9.7rc", "3.9.7rc0"),
+            ("3.10.0b2", "3.10.0b2"),
+            ("3.10.0-beta2", "3.10.0b2"),
+            ("3.10.0.b.2", "3.10.0b2"),
+            ("3.10.0a7", "3.10.0a7"),
+            ("3.10.0alpha7", "3.10.0a7"),
+            ("3.10.0_a7", "3.10.0a7"),
+            ("3.10a1", "3.10a1"),
+        ] {
+            assert_eq!(normalize_python_version(value)?, expected, "{}", value);
+        }
+
+        for value in [
+            "",
+            "3",
+            "3.",
+            "3.9.7.1",
+            "3..9",
+            "x.y",
+            "3.9.7dev1",
+            "3.9.7rc1x",
+        ] {
+            assert!(normalize_python_version(value).is_err(), "{}", value);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_distribution_version() {
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.8"),
+            )
+            .unwrap();
+        assert_eq!(record.python_version, PythonVersion::new(3, 8, 11));
+
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9.6"),
+            )
+            .unwrap();
+        assert_eq!(record.python_major_minor_version, "3.9");
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.9.6-rc1"),
+            )
+            .is_none());
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("bogus"),
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_musl_dynamic() {
+        let target = "x86_64-unknown-linux-musl";
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution(target, &DistributionFlavor::StandaloneDynamic, None)
+            .is_none());
+
+        let record = PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Url {
+                url: "https://example.com/cpython-3.9.6-x86_64-unknown-linux-musl-pgo.tar.zst"
+                    .to_string(),
+                sha256: "00".repeat(32),
+            },
+            target_triple: target.to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: Some(BuildOptimizations::Pgo),
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        };
+
+        let mut dists = PYTHON_DISTRIBUTIONS.clone();
+        dists.register(record.clone());
+
+        assert_eq!(
+            dists.find_distribution(target, &DistributionFlavor::StandaloneDynamic, None),
+            Some(record)
+        );
+        assert!(
+            !dists
+                .find_distribution(target, &DistributionFlavor::StandaloneStatic, None)
+                .unwrap()
+                .supports_prebuilt_extension_modules
+        );
+    }
+
+    #[test]
+    fn test_map_records() {
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut record| {
+            if let PythonDistributionLocation::Url { url, .. } = &mut record.location {
+                *url = url.replace("https://github.com/", "https://mirror.example.com/");
+            }
+
+            record
+        });
+
+        assert_eq!(dists.iter().count(), PYTHON_DISTRIBUTIONS.iter().count());
+
+        for (original, mapped) in PYTHON_DISTRIBUTIONS.iter().zip(dists.iter()) {
+            assert_eq!(original.target_triple, mapped.target_triple);
+
+            match &mapped.location {
+                PythonDistributionLocation::Url { url, .. } => {
+                    assert!(url.starts_with("https://mirror.example.com/"));
+                }
+                PythonDistributionLocation::Local { .. }
+                | PythonDistributionLocation::Oci { .. }
+                | PythonDistributionLocation::Git { .. }
+                | PythonDistributionLocation::Parts { .. } => panic!("unexpected location"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_os_version_floor() {
+        let find = |target: &str, floor: &str| {
+            PYTHON_DISTRIBUTIONS.find_distribution_with_policy(
+                target,
+                &DistributionFlavor::Standalone,
+                None,
+                &SelectionPolicy {
+                    os_version_floor: Some(floor.to_string()),
+                    ..SelectionPolicy::default()
+                },
+            )
+        };
+
+        assert_eq!(
+            find("x86_64-apple-darwin", "10.9")
+                .unwrap()
+                .min_os_version
+                .as_deref(),
+            Some("10.9")
+        );
+        assert!(find("x86_64-apple-darwin", "10.15").is_some());
+        assert!(find("x86_64-apple-darwin", "10.8").is_none());
+        assert!(find("aarch64-apple-darwin", "11").is_some());
+        assert!(find("aarch64-apple-darwin", "10.15").is_none());
+
+        // Records without a minimum OS version are unaffected.
+        assert!(find("x86_64-unknown-linux-gnu", "1.0").is_some());
+    }
+
+    #[test]
+    fn test_freeze() {
+        let frozen = PYTHON_DISTRIBUTIONS.clone().freeze();
+
+        let a = frozen
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+        let b = frozen
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(
+            Some(a.as_ref().clone()),
+            PYTHON_DISTRIBUTIONS.find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None
+            )
+        );
+
+        let handle = std::thread::spawn(move || {
+            frozen
+                .find_distribution(
+                    "x86_64-pc-windows-msvc",
+                    &DistributionFlavor::StandaloneStatic,
+                    Some("3.8"),
+                )
+                .map(|record| record.supports_prebuilt_extension_modules)
+        });
+
+        assert_eq!(handle.join().unwrap(), Some(false));
+    }
+
+    #[test]
+    fn test_windows_flavor_preference() {
+        let target = "x86_64-pc-windows-msvc";
+        let static_first = SelectionPolicy {
+            windows_flavor_preference: WindowsFlavorPreference::StaticFirst,
+            ..SelectionPolicy::default()
+        };
+
+        let forward = PYTHON_DISTRIBUTIONS.clone();
+        let mut reversed = PythonDistributionCollection { dists: vec![] };
+        for record in PYTHON_DISTRIBUTIONS.dists.iter().rev() {
+            reversed.register(record.clone());
+        }
+
+        for dists in [&forward, &reversed] {
+            let find = |policy: &SelectionPolicy| {
+                dists
+                    .find_distribution_with_policy(
+                        target,
+                        &DistributionFlavor::Standalone,
+                        Some("3.9"),
+                        policy,
+                    )
+                    .unwrap()
+                    .supports_prebuilt_extension_modules
+            };
+
+            assert!(find(&SelectionPolicy::default()));
+            assert!(!find(&static_first));
+        }
+    }
+
+    #[test]
+    fn test_labels() {
+        let target = "x86_64-unknown-linux-gnu";
+        let flavor = DistributionFlavor::Standalone;
+
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut record| {
+            if record.target_triple == target {
+                record.labels.insert(
+                    "approval".to_string(),
+                    if record.python_major_minor_version == "3.9" {
+                        "denied"
+                    } else {
+                        "granted"
+                    }
+                    .to_string(),
+                );
+            }
+
+            record
+        });
+
+        let find = |version, policy: &SelectionPolicy| {
+            dists
+                .find_distribution_with_policy(target, &flavor, version, policy)
+                .map(|dist| dist.python_major_minor_version)
+        };
+
+        let mut labels = BTreeMap::new();
+        labels.insert("approval".to_string(), "granted".to_string());
+        let granted = SelectionPolicy {
+            required_labels: labels.clone(),
+            ..SelectionPolicy::default()
+        };
+        assert_eq!(find(Some("3.8"), &granted), Some("3.8".to_string()));
+        assert_eq!(find(Some("3.9"), &granted), None);
+
+        labels.insert("approval".to_string(), "denied".to_string());
+        let not_denied = SelectionPolicy {
+            excluded_labels: labels,
+            ..SelectionPolicy::default()
+        };
+        assert_eq!(find(Some(">=3.8"), &not_denied), Some("3.8".to_string()));
+        assert_eq!(
+            find(Some(">=3.8"), &SelectionPolicy::default()),
+            Some("3.9".to_string())
+        );
+    }
+
+    #[test]
+    fn test_required_library_modes() -> Result<()> {
+        let target = "x86_64-unknown-linux-gnu";
+        let flavor = DistributionFlavor::Standalone;
+
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut record| {
+            if record.target_triple == target && record.python_major_minor_version == "3.8" {
+                record
+                    .library_build_modes
+                    .insert("sqlite3".to_string(), "threadsafe".to_string());
+            }
+
+            record
+        });
+
+        let find = |library: &str, mode: &str| {
+            let mut required_library_modes = BTreeMap::new();
+            required_library_modes.insert(library.to_string(), mode.to_string());
+
+            dists
+                .find_distribution_with_policy(
+                    target,
+                    &flavor,
+                    Some(">=3.8"),
+                    &SelectionPolicy {
+                        required_library_modes,
+                        ..SelectionPolicy::default()
+                    },
+                )
+                .map(|dist| dist.python_major_minor_version)
+        };
+
+        // Only the 3.8 distribution is known to have the mode. 3.9 is unknown.
+        assert_eq!(find("sqlite3", "threadsafe"), Some("3.8".to_string()));
+        assert_eq!(find("sqlite3", "single-thread"), None);
+        assert_eq!(find("openssl", "fips"), None);
+
+        // Unknown modes don't matter unless a mode is required.
+        assert_eq!(
+            dists
+                .find_distribution(target, &flavor, Some(">=3.8"))
+                .map(|dist| dist.python_major_minor_version),
+            Some("3.9".to_string())
+        );
+        assert_ne!(dists.fingerprint(), PYTHON_DISTRIBUTIONS.fingerprint());
+
+        // Modes can be filled in from archives.
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+
+        for (name, python_json) in [
+            ("unknown.tar.zst", br#"{}"#.to_vec()),
+            (
+                "threadsafe.tar.zst",
+                br#"{"library_build_modes": {"sqlite3": "threadsafe"}}"#.to_vec(),
+            ),
+        ] {
+            let mut builder = tar::Builder::new(vec![]);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(python_json.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "python/PYTHON.json", python_json.as_slice())?;
+            let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+
+            let path = td.path().join(name);
+            std::fs::write(&path, &data)?;
+
+            dists.register(PythonDistributionRecord {
+                location: PythonDistributionLocation::Local {
+                    local_path: path.display().to_string(),
+                    sha256: hex::encode(Sha256::digest(&data)),
+                },
+                ..PYTHON_DISTRIBUTIONS
+                    .find_distribution(target, &flavor, Some("3.9"))
+                    .unwrap()
+            });
+        }
+
+        let mut required_library_modes = BTreeMap::new();
+        required_library_modes.insert("sqlite3".to_string(), "threadsafe".to_string());
+        let policy = SelectionPolicy {
+            required_library_modes,
+            ..SelectionPolicy::default()
+        };
+
+        // Selection doesn't read archives, so uninspected records never match.
+        assert!(dists
+            .find_distribution_with_policy(target, &flavor, None, &policy)
+            .is_none());
+
+        let dists = dists.inspect_library_build_modes(&crate::testutil::get_logger()?, |_| true);
+        assert!(dists.dists[0].library_build_modes.is_empty());
+        assert_eq!(
+            dists
+                .find_distribution_with_policy(target, &flavor, None, &policy)
+                .unwrap()
+                .location
+                .filename(),
+            "threadsafe.tar.zst"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_distribution_with_flavors() {
+        let flavors = [
+            DistributionFlavor::StandaloneDynamic,
+            DistributionFlavor::StandaloneStatic,
+        ];
+
+        // musl distributions are static only.
+        let (flavor, record) = PYTHON_DISTRIBUTIONS
+            .find_distribution_with_flavors("x86_64-unknown-linux-musl", &flavors, None)
+            .unwrap();
+        assert_eq!(flavor, &DistributionFlavor::StandaloneStatic);
+        assert!(!record.supports_prebuilt_extension_modules);
+
+        let (flavor, record) = PYTHON_DISTRIBUTIONS
+            .find_distribution_with_flavors("x86_64-pc-windows-msvc", &flavors, None)
+            .unwrap();
+        assert_eq!(flavor, &DistributionFlavor::StandaloneDynamic);
+        assert!(record.supports_prebuilt_extension_modules);
+
+        let (flavor, record) = PYTHON_DISTRIBUTIONS
+            .find_distribution_with_flavors("x86_64-pc-windows-msvc", &flavors[1..], None)
+            .unwrap();
+        assert_eq!(flavor, &DistributionFlavor::StandaloneStatic);
+        assert!(!record.supports_prebuilt_extension_modules);
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution_with_flavors("x86_64-unknown-linux-musl", &flavors[..1], None)
+            .is_none());
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution_with_flavors("x86_64-unknown-linux-gnu", &[], None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_distribution_ref() {
+        let find = |version| {
+            PYTHON_DISTRIBUTIONS.find_distribution_ref(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                version,
+            )
+        };
+
+        let record = find(Some("3.8")).unwrap();
+        assert!(PYTHON_DISTRIBUTIONS
+            .dists
+            .iter()
+            .any(|dist| std::ptr::eq(dist, record)));
+        assert_eq!(
+            Some(record.clone()),
+            PYTHON_DISTRIBUTIONS.find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.8")
+            )
+        );
+        assert!(find(Some("2.7")).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(PYTHON_DISTRIBUTIONS.validate(), vec![]);
+
+        let mut dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut record| {
+            if record.target_triple == "x86_64-pc-windows-msvc" {
+                record.supports_prebuilt_extension_modules = true;
+            }
+
+            record
+        });
+
+        // Only the static distributions are inconsistent.
+        let issues = dists.validate();
+        assert_eq!(issues.len(), 2);
+        assert!(issues
+            .iter()
+            .all(|issue| issue.severity == ValidationSeverity::Warning
+                && issue.message.contains("-static-")));
+
+        dists.register(PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Local {
+                local_path: "/dist/cpython-3.9.6-x86_64-unknown-linux-musl.tar.zst".to_string(),
+                sha256: "00".repeat(32),
+            },
+            target_triple: "x86_64-unknown-linux-musl".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: None,
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        });
+        assert_eq!(dists.validate().len(), 3);
+
+        // Identical records may share a URL. Conflicting ones may not.
+        let mut dists = PYTHON_DISTRIBUTIONS.clone();
+        let mut record = dists
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+        dists.register(record.clone());
+        assert_eq!(dists.validate(), vec![]);
+
+        record.python_version = PythonVersion::new(3, 9, 7);
+        dists.register(record);
+        let issues = dists.validate();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, ValidationSeverity::Error);
+        assert!(issues[0].message.contains("3.9.7"));
+    }
+
+    #[test]
+    fn test_validate_deep() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let write_archive = |name: &str,
+                             members: &[(&str, &[u8])]|
+         -> Result<PythonDistributionLocation> {
+            let mut builder = tar::Builder::new(vec![]);
+            for (name, data) in members {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(data.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(&mut header, name, *data)?;
+            }
+            let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+
+            let path = td.path().join(name);
+            std::fs::write(&path, &data)?;
+
+            Ok(PythonDistributionLocation::Local {
+                local_path: path.display().to_string(),
+                sha256: hex::encode(Sha256::digest(&data)),
+            })
+        };
+
+        let static_json = br#"{
+            "target_triple": "x86_64-unknown-linux-musl",
+            "python_version": "3.9.6",
+            "libpython_link_mode": "static",
+            "python_extension_module_loading": ["builtin"],
+            "build_info": {"core": {"objs": ["build/core/main.o"], "static_lib": "build/lib/libpython3.9.a"}}
+        }"#;
+        let dynamic_json = br#"{
+            "target_triple": "x86_64-unknown-linux-gnu",
+            "python_version": "3.9.6",
+            "libpython_link_mode": "shared",
+            "python_extension_module_loading": ["builtin", "shared-library"],
+            "build_info": {"core": {"objs": [], "shared_lib": "install/lib/libpython3.9.so"}}
+        }"#;
+
+        let musl = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-musl",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+        let gnu = PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+            )
+            .unwrap();
+
+        let mut good_static = musl.clone();
+        good_static.location = write_archive(
+            "good-static.tar.zst",
+            &[
+                ("python/PYTHON.json", static_json),
+                ("python/build/core/main.o", b""),
+                ("python/build/lib/libpython3.9.a", b""),
+            ],
+        )?;
+        let mut good_dynamic = gnu.clone();
+        good_dynamic.location = write_archive(
+            "good-dynamic.tar.zst",
+            &[
+                ("python/PYTHON.json", dynamic_json),
+                ("python/install/lib/libpython3.9.so", b""),
+            ],
+        )?;
+
+        let dists = PythonDistributionCollection {
+            dists: vec![good_static.clone(), good_dynamic.clone()],
+        };
+        assert_eq!(dists.validate_deep(), vec![]);
+
+        let mut missing_static = musl;
+        missing_static.location = write_archive(
+            "missing-static.tar.zst",
+            &[
+                ("python/PYTHON.json", static_json),
+                ("python/build/core/main.o", b""),
+            ],
+        )?;
+        let mut wrong_triple = good_dynamic.clone();
+        wrong_triple.target_triple = "aarch64-unknown-linux-gnu".to_string();
+        let mut missing_archive = gnu;
+        missing_archive.location = PythonDistributionLocation::Local {
+            local_path: td.path().join("missing.tar.zst").display().to_string(),
+            sha256: "00".repeat(32),
+        };
+
+        let issues = PythonDistributionCollection {
+            dists: vec![missing_static, wrong_triple, missing_archive],
+        }
+        .validate_deep();
+
+        assert_eq!(
+            issues
+                .iter()
+                .map(|issue| issue.severity)
+                .collect::<Vec<_>>(),
+            vec![
+                ValidationSeverity::Error,
+                ValidationSeverity::Error,
+                ValidationSeverity::Warning
+            ]
+        );
+        assert!(issues[0].message.contains("lacks build/lib/libpython3.9.a"));
+        assert!(issues[1]
+            .message
+            .contains("targets x86_64-unknown-linux-gnu"));
+        assert!(issues[2]
+            .message
+            .starts_with("could not inspect missing.tar.zst"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_required_modules() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+
+        for (name, python_json) in [
+            (
+                "no-ssl.tar.zst",
+                br#"{"build_info": {"extensions": {"_ctypes": []}}}"#.to_vec(),
+            ),
+            (
+                "ssl.tar.zst",
+                br#"{"build_info": {"extensions": {"_ctypes": [], "_ssl": []}}}"#.to_vec(),
+            ),
+        ] {
+            let mut builder = tar::Builder::new(vec![]);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(python_json.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "python/PYTHON.json", python_json.as_slice())?;
+            let data = zstd::stream::encode_all(std::io::Cursor::new(builder.into_inner()?), 3)?;
+
+            let path = td.path().join(name);
+            std::fs::write(&path, &data)?;
+
+            dists.register(PythonDistributionRecord {
+                python_major_minor_version: "3.9".to_string(),
+                python_version: PythonVersion::new(3, 9, 6),
+                location: PythonDistributionLocation::Local {
+                    local_path: path.display().to_string(),
+                    sha256: hex::encode(Sha256::digest(&data)),
+                },
+                target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                supports_prebuilt_extension_modules: true,
+                optimizations: None,
+                min_os_version: None,
+                strip_prefix: None,
+                seed_packages: None,
+                labels: BTreeMap::new(),
+                layout: DistributionLayout::Install,
+                archive_size: None,
+                experimental: false,
+                library_build_modes: BTreeMap::new(),
+                extension_modules: None,
+            });
+        }
+
+        let target = "x86_64-unknown-linux-gnu";
+        let flavor = DistributionFlavor::Standalone;
+
+        assert_eq!(
+            dists
+                .find_distribution(target, &flavor, None)
+                .unwrap()
+                .location
+                .filename(),
+            "no-ssl.tar.zst"
+        );
+
+        let mut policy = SelectionPolicy::default();
+        policy.required_modules.insert("_ssl".to_string());
+
+        // Selection doesn't read archives, so uninspected records never match.
+        assert!(dists
+            .find_distribution_with_policy(target, &flavor, None, &policy)
+            .is_none());
+
+        dists.register(PythonDistributionRecord {
+            location: PythonDistributionLocation::Local {
+                local_path: td.path().join("missing.tar.zst").display().to_string(),
+                sha256: "00".repeat(32),
+            },
+            ..dists.dists[0].clone()
+        });
+        let unfiltered = dists.fingerprint();
+
+        let dists = dists.inspect_extension_modules(&crate::testutil::get_logger()?, |_| true);
+        assert_ne!(dists.fingerprint(), unfiltered);
+        assert_eq!(
+            dists.dists[0].extension_modules,
+            Some(vec!["_ctypes".to_string()].into_iter().collect())
+        );
+        assert_eq!(dists.dists[2].extension_modules, None);
+        assert_eq!(
+            dists
+                .find_distribution_with_policy(target, &flavor, None, &policy)
+                .unwrap()
+                .location
+                .filename(),
+            "ssl.tar.zst"
+        );
+
+        policy.required_modules.insert("_tkinter".to_string());
+        assert!(dists
+            .find_distribution_with_policy(target, &flavor, None, &policy)
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_availability_local() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+
+        for name in ["missing.tar.zst", "present.tar.zst", "also-missing.tar.zst"] {
+            dists.register(PythonDistributionRecord {
+                python_major_minor_version: "3.9".to_string(),
+                python_version: PythonVersion::new(3, 9, 6),
+                location: PythonDistributionLocation::Local {
+                    local_path: td.path().join(name).display().to_string(),
+                    sha256: "00".repeat(32),
+                },
+                target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                supports_prebuilt_extension_modules: true,
+                optimizations: None,
+                min_os_version: None,
+                strip_prefix: None,
+                seed_packages: None,
+                labels: BTreeMap::new(),
+                layout: DistributionLayout::Install,
+                archive_size: None,
+                experimental: false,
+                library_build_modes: BTreeMap::new(),
+                extension_modules: None,
+            });
+        }
+
+        std::fs::write(td.path().join("present.tar.zst"), b"")?;
+
+        let reports = dists.check_availability(2);
+        assert_eq!(
+            reports
+                .iter()
+                .map(|report| (
+                    report.record.location.filename().to_string(),
+                    report.available.is_ok()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("missing.tar.zst".to_string(), false),
+                ("present.tar.zst".to_string(), true),
+                ("also-missing.tar.zst".to_string(), false),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cached_only() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let source = td.path().join("source.tar.zst");
+        std::fs::write(&source, b"archive")?;
+        let sha256 = hex::encode(Sha256::digest(b"archive"));
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+        dists.register(PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Local {
+                local_path: source.display().to_string(),
+                sha256,
+            },
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: None,
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        });
+
+        let cache_dir = td.path().join("cache");
+        std::fs::create_dir(&cache_dir)?;
+
+        let policy = SelectionPolicy {
+            cached_only: Some(cache_dir.clone()),
+            ..SelectionPolicy::default()
+        };
+
+        let find = || {
+            dists.find_distribution_detailed(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+                &policy,
+            )
+        };
+
+        // The source file existing isn't sufficient.
+        assert!(matches!(find(), Err(DistributionError::NotCached { .. })));
+
+        // Content not matching the digest isn't sufficient.
+        std::fs::write(cache_dir.join("source.tar.zst"), b"bogus")?;
+        assert!(matches!(find(), Err(DistributionError::NotCached { .. })));
+
+        std::fs::write(cache_dir.join("source.tar.zst"), b"archive")?;
+        assert!(find().is_ok());
+
+        assert!(matches!(
+            dists.find_distribution_detailed(
+                "aarch64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                None,
+                &policy,
+            ),
+            Err(DistributionError::NotFound { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = PYTHON_DISTRIBUTIONS.fingerprint();
+        assert_eq!(fingerprint.len(), 64);
+
+        let reversed = PythonDistributionCollection {
+            dists: PYTHON_DISTRIBUTIONS.dists.iter().rev().cloned().collect(),
+        };
+        assert_eq!(reversed.fingerprint(), fingerprint);
+
+        let modified = PYTHON_DISTRIBUTIONS.clone().map_records(|mut dist| {
+            if dist.target_triple == "x86_64-unknown-linux-musl" {
+                dist.min_os_version = Some("1.0".to_string());
+            }
+            dist
+        });
+        assert_ne!(modified.fingerprint(), fingerprint);
+
+        let mut fewer = PYTHON_DISTRIBUTIONS.clone();
+        fewer.dists.pop();
+        assert_ne!(fewer.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_optimization_preference() {
+        let musl = "x86_64-unknown-linux-musl";
+        let gnu = "x86_64-unknown-linux-gnu";
+
+        let mut dists = PYTHON_DISTRIBUTIONS.clone();
+        // Make a debug build the first match.
+        let mut debug = PYTHON_DISTRIBUTIONS
+            .find_distribution(gnu, &DistributionFlavor::Standalone, None)
+            .unwrap();
+        debug.optimizations = Some(BuildOptimizations::Debug);
+        dists.dists.insert(0, debug);
+
+        let policy = SelectionPolicy {
+            optimization_preference: vec![BuildOptimizations::Pgo, BuildOptimizations::Noopt],
+            ..SelectionPolicy::default()
+        };
+
+        let find = |triple| {
+            dists
+                .find_distribution_with_policy(
+                    triple,
+                    &DistributionFlavor::Standalone,
+                    None,
+                    &policy,
+                )
+                .and_then(|dist| dist.optimizations)
+        };
+
+        assert_eq!(find(gnu), Some(BuildOptimizations::Pgo));
+        assert_eq!(find(musl), Some(BuildOptimizations::Noopt));
+
+        let policy = SelectionPolicy {
+            optimization_preference: vec![BuildOptimizations::Lto],
+            ..SelectionPolicy::default()
+        };
+        assert!(dists
+            .find_distribution_with_policy(gnu, &DistributionFlavor::Standalone, None, &policy)
+            .is_none());
+    }
+
+    #[test]
+    fn test_alias_impact() {
+        let mut aliases = BTreeMap::new();
+        aliases.insert(
+            "x86_64-unknown-linux-gnu".to_string(),
+            "x86_64-unknown-linux-musl".to_string(),
+        );
+        aliases.insert(
+            "x86_64-linux-musl".to_string(),
+            "x86_64-unknown-linux-musl".to_string(),
+        );
+
+        let impacts = PYTHON_DISTRIBUTIONS.alias_impact(&aliases);
+
+        // Aliasing an existing triple only affects flavors it lacks.
+        assert_eq!(
+            impacts
+                .iter()
+                .map(|impact| (
+                    impact.target_triple.as_str(),
+                    impact.python_major_minor_version.as_str(),
+                    impact.flavor.to_string(),
+                    impact.record.target_triple.as_str(),
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "x86_64-linux-musl",
+                    "3.8",
+                    "standalone".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+                (
+                    "x86_64-linux-musl",
+                    "3.8",
+                    "standalone_static".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+                (
+                    "x86_64-linux-musl",
+                    "3.9",
+                    "standalone".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+                (
+                    "x86_64-linux-musl",
+                    "3.9",
+                    "standalone_static".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+                (
+                    "x86_64-unknown-linux-gnu",
+                    "3.8",
+                    "standalone_static".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+                (
+                    "x86_64-unknown-linux-gnu",
+                    "3.9",
+                    "standalone_static".to_string(),
+                    "x86_64-unknown-linux-musl"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_explain_selection() {
+        let explanation = PYTHON_DISTRIBUTIONS.explain_selection(
+            "x86_64-unknown-linux-gnu",
+            &DistributionFlavor::Standalone,
+            Some(">=3.8"),
+            &SelectionPolicy::default(),
+        );
+
+        assert_eq!(
+            explanation,
+            [
+                "request: target triple x86_64-unknown-linux-gnu, flavor standalone, python version >=3.8",
+                "normalized python version: specifier >=3.8",
+                "candidates: 15",
+                "after target triple filter: 2",
+                "after python version filter: 2",
+                "after flavor filter: 2",
+                "after minimum optimization filter: 2",
+                "after OS version floor filter: 2",
+                "after optimization preference filter: 2",
+                "after labels filter: 2",
+                "after experimental filter: 2",
+                "after footprint filter: 2",
+                "after build timestamp filter: 2",
+                "after library build modes filter: 2",
+                "after required modules filter: 2",
+                "tiebreak: newest Python version, then first registered record",
+                "selected: cpython-3.9.6-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst",
+                "location: https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst",
+                "sha256: 343e2d349779efb7d46f7eb01ec6a202bff14293616ce8103bc8060c777bb231",
+            ]
+            .join("\n")
+        );
+
+        let explanation = PYTHON_DISTRIBUTIONS.explain_selection(
+            "x86_64-unknown-linux-musl",
+            &DistributionFlavor::StandaloneDynamic,
+            None,
+            &SelectionPolicy::default(),
+        );
+        assert!(explanation.contains("normalized python version: 3.9.*"));
+        assert!(explanation.contains("after flavor filter: 0"));
+        assert!(explanation.ends_with(
+            "error: could not find Python distribution for x86_64-unknown-linux-musl matching flavor standalone_dynamic and version 3.9"
+        ));
+
+        let explanation = PYTHON_DISTRIBUTIONS.explain_selection(
+            "x86_64-unknown-linux-musl",
+            &DistributionFlavor::Standalone,
+            Some("bogus"),
+            &SelectionPolicy::default(),
+        );
+        assert_eq!(explanation.lines().count(), 2);
+        assert!(explanation.lines().nth(1).unwrap().starts_with("error: "));
+    }
+
+    #[test]
+    fn test_eol() {
+        assert_eq!(days_from_date("1970-01-01"), Some(0));
+        assert_eq!(days_from_date("2000-03-01"), Some(11017));
+        assert_eq!(days_from_date("bogus"), None);
+
+        let day = |date| days_from_date(date).unwrap();
+
+        assert!(check_eol("3.8", day("2024-10-06")).is_ok());
+        assert!(matches!(
+            check_eol("3.8", day("2024-10-07")),
+            Err(DistributionError::EndOfLife { ref eol_date, .. }) if eol_date == "2024-10-07"
+        ));
+        assert!(check_eol("4.0", day("2100-01-01")).is_ok());
+
+        let policy = SelectionPolicy {
+            reject_eol: true,
+            ..SelectionPolicy::default()
+        };
+        assert!(matches!(
+            PYTHON_DISTRIBUTIONS.find_distribution_detailed(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.8"),
+                &policy
+            ),
+            Err(DistributionError::EndOfLife { .. })
+        ));
+
+        // Opt-in.
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_distribution(
+                "x86_64-unknown-linux-gnu",
+                &DistributionFlavor::Standalone,
+                Some("3.8")
+            )
+            .is_some());
+    }
+
+    #[test]
+    fn test_builtin_release_tag() {
+        for record in PYTHON_DISTRIBUTIONS.iter() {
+            match &record.location {
+                PythonDistributionLocation::Url { url, .. } => {
+                    assert!(
+                        url.contains(&format!("/releases/download/{}/", BUILTIN_RELEASE_TAG)),
+                        "{} is not from release {}",
+                        url,
+                        BUILTIN_RELEASE_TAG
+                    );
+                }
+                _ => panic!("unexpected location"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_triple_coverage_diff() -> Result<()> {
+        let diff = PYTHON_DISTRIBUTIONS.triple_coverage_diff("3.8", "3.9");
+
+        assert!(diff.only_a.is_empty());
+        assert_eq!(
+            diff.only_b.iter().collect::<Vec<_>>(),
+            vec!["aarch64-apple-darwin"]
+        );
+        assert_eq!(
+            diff.both.len() + diff.only_b.len(),
+            PYTHON_DISTRIBUTIONS.all_target_triples().count()
+        );
+        assert!(diff.both.contains("x86_64-unknown-linux-gnu"));
+
+        let reversed = PYTHON_DISTRIBUTIONS.triple_coverage_diff("3.9", "3.8");
+        assert_eq!(reversed.only_a, diff.only_b);
+        assert_eq!(reversed.both, diff.both);
+
+        let json = serde_json::to_value(&diff)?;
+        assert_eq!(json["only_b"], serde_json::json!(["aarch64-apple-darwin"]));
+
+        let none = PYTHON_DISTRIBUTIONS.triple_coverage_diff("3.9", "2.7");
+        assert!(none.both.is_empty() && none.only_b.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_traced() {
+        let query = DistributionQuery::new("x86_64-unknown-linux-musl")
+            .python_version(">=3.8")
+            .flavors(vec![
+                DistributionFlavor::StandaloneDynamic,
+                DistributionFlavor::StandaloneStatic,
+            ]);
+
+        let (record, trace) = PYTHON_DISTRIBUTIONS.query_traced(&query);
+        assert_eq!(record, PYTHON_DISTRIBUTIONS.query(&query).ok());
+        let record = record.unwrap();
+
+        assert_eq!(trace.candidates, PYTHON_DISTRIBUTIONS.len());
+        assert_eq!(trace.attempts.len(), 2);
+
+        let dynamic = &trace.attempts[0];
+        assert_eq!(dynamic.flavor, DistributionFlavor::StandaloneDynamic);
+        assert_eq!(
+            dynamic
+                .filters
+                .iter()
+                .map(|filter| filter.name)
+                .collect::<Vec<_>>(),
+            SelectionCriteria::FILTER_NAMES.to_vec()
+        );
+        assert_eq!(dynamic.filters[0].remaining, 2);
+        assert_eq!(dynamic.filters[2].remaining, 0);
+        assert!(dynamic.finalists.is_empty());
+        assert_eq!(dynamic.selected, None);
+        assert_eq!(dynamic.error_code, Some("NOT_FOUND"));
+
+        let r#static = &trace.attempts[1];
+        assert_eq!(r#static.finalists.len(), 2);
+        assert_eq!(
+            r#static.tiebreak,
+            "newest Python version, then first registered record"
+        );
+        assert_eq!(
+            r#static.selected.as_deref(),
+            Some(record.location.filename().as_ref())
+        );
+        assert_eq!(r#static.error, None);
+
+        // Invalid requests are traced without filters.
+        let (record, trace) = PYTHON_DISTRIBUTIONS.query_traced(
+            &DistributionQuery::new("x86_64-unknown-linux-musl").python_version("bogus"),
+        );
+        assert_eq!(record, None);
+        assert_eq!(trace.attempts.len(), 1);
+        assert!(trace.attempts[0].filters.is_empty());
+        assert_eq!(trace.attempts[0].error_code, Some("INVALID_VERSION"));
+    }
+
+    #[test]
+    fn test_query() -> Result<()> {
+        let triple = "x86_64-unknown-linux-gnu";
+
+        let record = PYTHON_DISTRIBUTIONS.query(&DistributionQuery::new(triple))?;
+        assert_eq!(
+            Some(record),
+            PYTHON_DISTRIBUTIONS.find_distribution(triple, &DistributionFlavor::Standalone, None)
+        );
+
+        let record = PYTHON_DISTRIBUTIONS.query(
+            &DistributionQuery::new(triple)
+                .target_triple("x86_64-unknown-linux-musl")
+                .python_version("3.8")
+                .flavor(DistributionFlavor::StandaloneStatic),
+        )?;
+        assert_eq!(record.target_triple, "x86_64-unknown-linux-musl");
+        assert_eq!(record.python_major_minor_version, "3.8");
+        assert!(!record.supports_prebuilt_extension_modules);
+
+        // Flavors are tried in order.
+        let record = PYTHON_DISTRIBUTIONS.query(&DistributionQuery::new(triple).flavors(vec![
+            DistributionFlavor::StandaloneDynamic,
+            DistributionFlavor::StandaloneStatic,
+        ]))?;
+        assert!(record.supports_prebuilt_extension_modules);
+
+        let err = PYTHON_DISTRIBUTIONS
+            .query(
+                &DistributionQuery::new(triple)
+                    .optimization_preference(vec![BuildOptimizations::Debug]),
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+
+        let err = PYTHON_DISTRIBUTIONS
+            .query(&DistributionQuery::new(triple).flavors(vec![]))
+            .unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_compatible_with() -> Result<()> {
+        let triple = "x86_64-unknown-linux-gnu";
+
+        let record = PYTHON_DISTRIBUTIONS.find_compatible_with(
+            "3.9.2",
+            "cp39",
+            triple,
+            &DistributionFlavor::Standalone,
+        )?;
+        assert_eq!(record.python_major_minor_version, "3.9");
+        assert_eq!(record.python_abi_tag(), "cp39");
+
+        let err = PYTHON_DISTRIBUTIONS
+            .find_compatible_with("3.9.2", "cp39d", triple, &DistributionFlavor::Standalone)
+            .unwrap_err();
+        assert_eq!(err.code(), "ABI_MISMATCH");
+        assert!(err.to_string().contains("cp39d"));
+
+        // Unknown versions report why nothing was found rather than the ABI.
+        let err = PYTHON_DISTRIBUTIONS
+            .find_compatible_with("2.7.18", "cp27mu", triple, &DistributionFlavor::Standalone)
+            .unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+
+        let err = PYTHON_DISTRIBUTIONS
+            .find_compatible_with("foo", "cp39", triple, &DistributionFlavor::Standalone)
+            .unwrap_err();
+        assert_eq!(err.code(), "INVALID_VERSION");
+
+        assert_eq!(
+            HostPython::from_script_output("3.8.10\ncp38\n")?,
+            HostPython {
+                version: "3.8.10".to_string(),
+                abi: "cp38".to_string(),
+            }
+        );
+        assert!(HostPython::from_script_output("3.8.10\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_distribution_near() {
+        assert_eq!(
+            triple_distance("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-gnu"),
+            0
+        );
+        assert_eq!(
+            triple_distance("x86_64-unknown-linux-gnu", "x86_64-unknown-linux-musl"),
+            1
+        );
+        assert_eq!(
+            triple_distance("aarch64-unknown-linux-gnu", "x86_64-unknown-linux-gnu"),
+            2
+        );
+        assert_eq!(
+            triple_distance("aarch64-unknown-linux-gnu", "aarch64-apple-darwin"),
+            3
+        );
+        assert_eq!(
+            triple_distance("i686-unknown-linux-gnu", "x86_64-apple-darwin"),
+            4
+        );
+        assert_eq!(triple_distance("bogus", "also-bogus"), 4);
+
+        let find = |triple, version| {
+            PYTHON_DISTRIBUTIONS.find_distribution_near(
+                triple,
+                &DistributionFlavor::Standalone,
+                version,
+                &SelectionPolicy::default(),
+            )
+        };
+
+        // Exact matches are preferred.
+        assert_eq!(
+            find("x86_64-unknown-linux-musl", None)
+                .unwrap()
+                .target_triple,
+            "x86_64-unknown-linux-musl"
+        );
+        // Then the same operating system.
+        assert_eq!(
+            find("aarch64-unknown-linux-gnu", None)
+                .unwrap()
+                .target_triple,
+            "x86_64-unknown-linux-gnu"
+        );
+        // Then the same architecture.
+        assert_eq!(
+            find("aarch64-unknown-freebsd", None).unwrap().target_triple,
+            "aarch64-apple-darwin"
+        );
+        assert_eq!(
+            find("aarch64-pc-windows-msvc", None).unwrap().target_triple,
+            "i686-pc-windows-msvc"
+        );
+        // Unknown triples fall back to anything.
+        assert!(find("riscv64gc-unknown-none-elf", None).is_ok());
+        // Only requirements other than the triple are mandatory.
+        assert_eq!(
+            find("aarch64-apple-darwin", Some("3.8"))
+                .unwrap()
+                .target_triple,
+            "x86_64-apple-darwin"
+        );
+        assert_eq!(
+            find("x86_64-unknown-linux-gnu", Some("2.7"))
+                .unwrap_err()
+                .code(),
+            "NOT_FOUND"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        assert_eq!(PYTHON_DISTRIBUTIONS.len(), 15);
+        assert!(!PYTHON_DISTRIBUTIONS.is_empty());
+
+        let empty = PythonDistributionCollection { dists: vec![] };
+        assert!(empty.is_empty());
+        assert_eq!(empty.stats(), CollectionStats::default());
+
+        let stats = PYTHON_DISTRIBUTIONS.stats();
+        assert_eq!(stats.records, 15);
+        assert_eq!(
+            stats.by_target_triple.values().sum::<usize>(),
+            stats.records
+        );
+        assert_eq!(
+            stats.by_python_version.values().sum::<usize>(),
+            stats.records
+        );
+        assert_eq!(stats.by_target_triple["aarch64-apple-darwin"], 1);
+        assert_eq!(stats.by_flavor["standalone"], stats.records);
+        assert_eq!(
+            stats.by_flavor["standalone_static"] + stats.by_flavor["standalone_dynamic"],
+            stats.records
+        );
+        assert_eq!(
+            stats.by_target_triple.keys().collect::<Vec<_>>(),
+            PYTHON_DISTRIBUTIONS
+                .all_target_triples()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_resolve_batch() {
+        let request = |target_triple: &str, python_version: Option<&str>| DistributionRequest {
+            target_triple: target_triple.to_string(),
+            flavor: DistributionFlavor::Standalone,
+            python_version: python_version.map(|v| v.to_string()),
+        };
+
+        let results = PYTHON_DISTRIBUTIONS.resolve_batch(
+            vec![
+                request("x86_64-unknown-linux-gnu", Some("3.8")),
+                request("bogus", None),
+                request("x86_64-unknown-linux-musl", Some("not a version")),
+                request("x86_64-unknown-linux-musl", None),
+            ],
+            &SelectionPolicy::default(),
+        );
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|(request, _)| request.target_triple.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "x86_64-unknown-linux-gnu",
+                "bogus",
+                "x86_64-unknown-linux-musl",
+                "x86_64-unknown-linux-musl"
+            ]
+        );
+        assert_eq!(
+            results[0].1.as_ref().unwrap().python_major_minor_version,
+            "3.8"
+        );
+        assert_eq!(results[1].1.as_ref().unwrap_err().code(), "NOT_FOUND");
+        assert_eq!(results[2].1.as_ref().unwrap_err().code(), "INVALID_VERSION");
+        assert_eq!(
+            results[3].1.as_ref().unwrap().target_triple,
+            "x86_64-unknown-linux-musl"
+        );
+    }
+
+    #[test]
+    fn test_experimental() -> Result<()> {
+        let musl = "x86_64-unknown-linux-musl";
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut dist| {
+            if dist.target_triple == musl {
+                dist.experimental = true;
+            }
+            dist
+        });
+
+        let find = |reject_experimental| {
+            dists.find_distribution_with_policy(
+                musl,
+                &DistributionFlavor::Standalone,
+                None,
+                &SelectionPolicy {
+                    reject_experimental,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(find(false).unwrap().experimental);
+        assert!(find(true).is_none());
+        assert!(
+            dists
+                .find_distribution_logged(
+                    &crate::testutil::get_logger()?,
+                    musl,
+                    &DistributionFlavor::Standalone,
+                    None,
+                    &SelectionPolicy::default(),
+                )?
+                .experimental
+        );
+
+        assert!(dists.all_target_triples().any(|triple| triple == musl));
+        assert!(!dists.stable_target_triples().any(|triple| triple == musl));
+        assert_eq!(
+            PYTHON_DISTRIBUTIONS
+                .stable_target_triples()
+                .collect::<Vec<_>>(),
+            PYTHON_DISTRIBUTIONS
+                .all_target_triples()
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_timestamp_range() {
+        let gnu = "x86_64-unknown-linux-gnu";
+        let record = PYTHON_DISTRIBUTIONS
+            .find_distribution(gnu, &DistributionFlavor::Standalone, Some("3.9"))
+            .unwrap();
+        assert_eq!(record.build_timestamp().as_deref(), Some("20210724T1424"));
+
+        let find = |dists: &PythonDistributionCollection, earliest: &str, latest: &str| {
+            dists
+                .query(
+                    &DistributionQuery::new(gnu)
+                        .python_version("3.9")
+                        .build_timestamp_range(earliest, latest),
+                )
+                .ok()
+        };
+
+        assert_eq!(
+            find(&PYTHON_DISTRIBUTIONS, "20210724T1424", "20210724T1424"),
+            Some(record.clone())
+        );
+        assert_eq!(
+            find(&PYTHON_DISTRIBUTIONS, "20210701", "20210724"),
+            Some(record.clone())
+        );
+        assert_eq!(
+            find(&PYTHON_DISTRIBUTIONS, "20210724T1425", "20210731"),
+            None
+        );
+        assert_eq!(find(&PYTHON_DISTRIBUTIONS, "20210601", "20210723"), None);
+
+        // Records without a timestamp are excluded when a range is given.
+        let untimed = PYTHON_DISTRIBUTIONS.clone().map_records(|mut dist| {
+            dist.location = PythonDistributionLocation::Local {
+                local_path: "/dists/cpython.tar.zst".to_string(),
+                sha256: dist.location.sha256().to_string(),
+            };
+            dist
+        });
+        assert!(untimed.iter().all(|dist| dist.build_timestamp().is_none()));
+        assert_eq!(find(&untimed, "20000101", "29991231"), None);
+        assert!(untimed
+            .find_distribution(gnu, &DistributionFlavor::Standalone, Some("3.9"))
+            .is_some());
+    }
+
+    #[test]
+    fn test_max_footprint() {
+        let gnu = "x86_64-unknown-linux-gnu";
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut dist| {
+            if dist.target_triple == gnu {
+                dist.archive_size = Some(if dist.python_major_minor_version == "3.8" {
+                    50
+                } else {
+                    100
+                });
+            }
+            dist
+        });
+
+        let find = |budget| {
+            dists.find_distribution_detailed(
+                gnu,
+                &DistributionFlavor::Standalone,
+                Some(">=3.8"),
+                &SelectionPolicy {
+                    max_footprint: budget,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(find(None).unwrap().python_major_minor_version, "3.9");
+        assert_eq!(find(Some(200)).unwrap().python_major_minor_version, "3.8");
+        assert_eq!(find(Some(75)).unwrap().python_major_minor_version, "3.8");
+        assert_eq!(find(Some(50)).unwrap().python_major_minor_version, "3.8");
+
+        let err = find(Some(10)).unwrap_err();
+        assert_eq!(err.code(), "BUDGET_EXCEEDED");
+        assert!(matches!(
+            err,
+            DistributionError::BudgetExceeded {
+                budget: 10,
+                smallest: 50
+            }
+        ));
+
+        // Records of unknown size aren't excluded.
+        let musl = PYTHON_DISTRIBUTIONS
+            .find_distribution_detailed(
+                "x86_64-unknown-linux-musl",
+                &DistributionFlavor::Standalone,
+                None,
+                &SelectionPolicy {
+                    max_footprint: Some(10),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(musl.archive_size, None);
+
+        // Other reasons for no match aren't reported as budget errors.
+        let err = dists
+            .find_distribution_detailed(
+                gnu,
+                &DistributionFlavor::Standalone,
+                Some("3.7"),
+                &SelectionPolicy {
+                    max_footprint: Some(10),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), "NOT_FOUND");
+    }
+
+    #[test]
+    fn test_records_missing_optimization() {
+        assert!(PYTHON_DISTRIBUTIONS
+            .records_missing_optimization()
+            .is_empty());
+
+        let musl = "x86_64-unknown-linux-musl";
+        let dists = PYTHON_DISTRIBUTIONS.clone().map_records(|mut dist| {
+            if dist.target_triple == musl {
+                dist.optimizations = None;
+            }
+            dist
+        });
+
+        let missing = dists.records_missing_optimization();
+        assert!(!missing.is_empty());
+        assert!(missing.iter().all(|dist| dist.target_triple == musl));
+        assert_eq!(
+            missing.len(),
+            PYTHON_DISTRIBUTIONS
+                .iter()
+                .filter(|dist| dist.target_triple == musl)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_find_all() {
+        let musl = "x86_64-unknown-linux-musl";
+
+        let found = PYTHON_DISTRIBUTIONS.find_all(|dist| dist.target_triple == musl);
+        assert!(!found.is_empty());
+        assert_eq!(
+            found,
+            PYTHON_DISTRIBUTIONS
+                .iter()
+                .filter(|dist| dist.target_triple == musl)
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+
+        assert!(PYTHON_DISTRIBUTIONS.find_all(|_| false).is_empty());
+        assert_eq!(
+            PYTHON_DISTRIBUTIONS.find_all(|_| true).len(),
+            PYTHON_DISTRIBUTIONS.len()
+        );
+    }
+
+    #[test]
+    fn test_records_by_release() {
+        let mut dists = PYTHON_DISTRIBUTIONS.clone();
+        dists.register(PythonDistributionRecord {
+            python_major_minor_version: "3.9".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Local {
+                local_path: "/dist.tar.zst".to_string(),
+                sha256: "00".repeat(32),
+            },
+            target_triple: "x86_64-unknown-linux-gnu".to_string(),
+            supports_prebuilt_extension_modules: true,
+            optimizations: None,
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        });
+
+        let releases = dists.records_by_release();
+
+        assert_eq!(
+            releases.keys().collect::<Vec<_>>(),
+            vec![BUILTIN_RELEASE_TAG]
+        );
+        assert_eq!(
+            releases[BUILTIN_RELEASE_TAG].len(),
+            PYTHON_DISTRIBUTIONS.iter().count()
+        );
+    }
+
+    #[test]
+    fn test_denied_digests() -> Result<()> {
+        // The deny list is global. The digests denied here belong only to
+        // records this test registers, and the lock keeps other tests that
+        // change the list from running concurrently.
+        let _lock = crate::testutil::lock_global_settings();
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+        for (version, sha256) in [("3.9.7", "AA"), ("3.9.6", "bb")] {
+            dists.register(PythonDistributionRecord {
+                python_major_minor_version: "3.9".to_string(),
+                python_version: version.parse().unwrap(),
+                location: PythonDistributionLocation::Local {
+                    local_path: format!("/{}.tar.zst", version),
+                    sha256: sha256.repeat(32),
+                },
+                target_triple: "x86_64-unknown-linux-gnu".to_string(),
+                supports_prebuilt_extension_modules: true,
+                optimizations: None,
+                min_os_version: None,
+                strip_prefix: None,
+                seed_packages: None,
+                labels: BTreeMap::new(),
+                layout: DistributionLayout::Install,
+                archive_size: None,
+                experimental: false,
+                library_build_modes: BTreeMap::new(),
+                extension_modules: None,
+            });
+        }
+
+        let find = || {
+            dists
+                .find_distribution(
+                    "x86_64-unknown-linux-gnu",
+                    &DistributionFlavor::Standalone,
+                    None,
+                )
+                .map(|dist| dist.python_version.to_string())
+        };
+
+        assert_eq!(find(), Some("3.9.7".to_string()));
+
+        set_denied_digests(&[&"aa".repeat(32)]);
+        assert_eq!(find(), Some("3.9.6".to_string()));
+        assert_eq!(
+            dists
+                .find_distribution_logged(
+                    &crate::testutil::get_logger()?,
+                    "x86_64-unknown-linux-gnu",
+                    &DistributionFlavor::Standalone,
+                    None,
+                    &SelectionPolicy::default(),
+                )?
+                .python_version
+                .to_string(),
+            "3.9.6"
+        );
+
+        set_denied_digests(&[&"aa".repeat(32), &"BB".repeat(32)]);
+        assert_eq!(find(), None);
 
-impl PythonDistributionCollection {
-    /// Find a Python distribution given requirements.
-    ///
-    /// `target_triple` is the Rust machine triple the distribution is built for.
-    /// `flavor` is the type of Python distribution.
-    /// `python_major_minor_version` is an optional `X.Y` version string being
-    /// requested. If `None`, `3.9` is assumed.
-    pub fn find_distribution(
-        &self,
-        target_triple: &str,
-        flavor: &DistributionFlavor,
-        python_major_minor_version: Option<&str>,
-    ) -> Option<PythonDistributionRecord> {
-        let python_major_minor_version = python_major_minor_version.unwrap_or("3.9");
+        set_denied_digests(&[]);
+        assert_eq!(find(), Some("3.9.7".to_string()));
 
-        self.dists
-            .iter()
-            .filter(|dist| dist.python_major_minor_version == python_major_minor_version)
-            .filter(|dist| dist.target_triple == target_triple)
-            .filter(|dist| match flavor {
-                DistributionFlavor::Standalone => true,
-                DistributionFlavor::StandaloneStatic => !dist.supports_prebuilt_extension_modules,
-                DistributionFlavor::StandaloneDynamic => dist.supports_prebuilt_extension_modules,
-            })
-            .cloned()
-            .next()
+        Ok(())
     }
 
-    /// Obtain records for all registered distributions.
-    #[allow(unused)]
-    pub fn iter(&self) -> impl Iterator<Item = &PythonDistributionRecord> {
-        self.dists.iter()
+    #[test]
+    fn test_missing_from_cache() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("pyoxidizer-test")
+            .tempdir()?;
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+        for (triple, content) in [
+            ("x86_64-unknown-linux-gnu", "gnu"),
+            ("x86_64-unknown-linux-musl", "musl"),
+        ] {
+            dists.register(PythonDistributionRecord {
+                python_major_minor_version: "3.9".to_string(),
+                python_version: PythonVersion::new(3, 9, 6),
+                location: PythonDistributionLocation::Url {
+                    url: format!("https://example.com/{}.tar.zst", triple),
+                    sha256: hex::encode(Sha256::digest(content.as_bytes())),
+                },
+                target_triple: triple.to_string(),
+                supports_prebuilt_extension_modules: false,
+                optimizations: None,
+                min_os_version: None,
+                strip_prefix: None,
+                seed_packages: None,
+                labels: BTreeMap::new(),
+                layout: DistributionLayout::Install,
+                archive_size: None,
+                experimental: false,
+                library_build_modes: BTreeMap::new(),
+                extension_modules: None,
+            });
+        }
+
+        std::fs::write(td.path().join("x86_64-unknown-linux-gnu.tar.zst"), b"gnu")?;
+
+        let request = |triple: &str| {
+            (
+                triple.to_string(),
+                DistributionFlavor::Standalone,
+                "3.9".to_string(),
+            )
+        };
+
+        let missing = dists.missing_from_cache(
+            &[
+                request("x86_64-unknown-linux-gnu"),
+                request("x86_64-unknown-linux-musl"),
+                request("x86_64-unknown-linux-musl"),
+            ],
+            td.path(),
+        )?;
+        assert_eq!(
+            missing
+                .iter()
+                .map(|dist| dist.target_triple.as_str())
+                .collect::<Vec<_>>(),
+            vec!["x86_64-unknown-linux-musl"]
+        );
+
+        assert!(dists
+            .missing_from_cache(&[request("aarch64-apple-darwin")], td.path())
+            .is_err());
+
+        Ok(())
     }
 
-    /// All target triples of distributions in this collection.
-    #[allow(unused)]
-    pub fn all_target_triples(&self) -> impl Iterator<Item = &str> {
-        self.dists
-            .iter()
-            .map(|dist| dist.target_triple.as_str())
-            .sorted()
-            .dedup()
+    #[test]
+    fn test_selection_snapshot() {
+        // Regenerate the stored copy after an intended selection change by
+        // writing out `PYTHON_DISTRIBUTIONS.selection_snapshot()`.
+        assert_eq!(
+            PYTHON_DISTRIBUTIONS.selection_snapshot(),
+            include_str!("python-distributions-selection.txt")
+        );
     }
-}
 
-pub static PYTHON_DISTRIBUTIONS: Lazy<PythonDistributionCollection> = Lazy::new(|| {
-    let dists = vec![
-        // Linux glibc linked.
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "e72b8f82d6e56f7fff9563b1d65aca24efe8237fa690b3f328e578ff9f8587fb".to_string(),
-            },
-            target_triple: "x86_64-unknown-linux-gnu".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
+    #[test]
+    fn test_flavor_for_goal() {
+        let single = PackagingGoal::SingleFileExecutable;
+        let extensions = PackagingGoal::LoadsThirdPartyExtensions;
+
+        for (triple, goal, expected) in [
+            (
+                "x86_64-unknown-linux-musl",
+                single,
+                DistributionFlavor::StandaloneStatic,
+            ),
+            (
+                "x86_64-unknown-linux-gnu",
+                single,
+                DistributionFlavor::Standalone,
+            ),
+            (
+                "x86_64-pc-windows-msvc",
+                single,
+                DistributionFlavor::StandaloneStatic,
+            ),
+            (
+                "x86_64-pc-windows-msvc",
+                extensions,
+                DistributionFlavor::StandaloneDynamic,
+            ),
+            (
+                "aarch64-apple-darwin",
+                extensions,
+                DistributionFlavor::StandaloneDynamic,
+            ),
+        ] {
+            let (flavor, rationale) = flavor_for_goal(triple, goal);
+            assert_eq!(flavor, expected, "{} {:?}", triple, goal);
+            assert!(!rationale.is_empty());
+
+            // Every recommendation is satisfiable by the built-in distributions.
+            assert!(PYTHON_DISTRIBUTIONS
+                .find_distribution(triple, &flavor, None)
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_can_build_flavor() {
+        let flavors = [
+            DistributionFlavor::Standalone,
+            DistributionFlavor::StandaloneStatic,
+            DistributionFlavor::StandaloneDynamic,
+        ];
+
+        for (triple, expected) in [
+            ("aarch64-apple-darwin", [true, false, true]),
+            ("i686-pc-windows-msvc", [true, true, true]),
+            ("x86_64-apple-darwin", [true, false, true]),
+            ("x86_64-pc-windows-msvc", [true, true, true]),
+            ("x86_64-unknown-linux-gnu", [true, false, true]),
+            ("x86_64-unknown-linux-musl", [true, true, false]),
+        ] {
+            for (flavor, expected) in flavors.iter().zip(expected.iter()) {
+                assert_eq!(
+                    can_build_flavor(triple, flavor),
+                    *expected,
+                    "{} {}",
+                    triple,
+                    flavor
+                );
+            }
+        }
+
+        // The rules agree with the built-in distributions.
+        let triples = PYTHON_DISTRIBUTIONS
+            .all_target_triples()
+            .collect::<Vec<_>>();
+        assert_eq!(triples.len(), 6);
+        for triple in triples {
+            for flavor in &flavors {
+                assert_eq!(
+                    can_build_flavor(triple, flavor),
+                    PYTHON_DISTRIBUTIONS
+                        .find_distribution(triple, flavor, None)
+                        .is_some(),
+                    "{} {}",
+                    triple,
+                    flavor
+                );
+            }
+        }
+
+        assert!(!can_build_flavor(
+            "riscv64gc-unknown-none-elf",
+            &DistributionFlavor::Standalone
+        ));
+    }
+
+    #[test]
+    fn test_resolved_version_differs() {
+        let resolved = |requested: Option<&str>| ResolvedDistribution {
+            record: PYTHON_DISTRIBUTIONS.iter().next().unwrap().clone(),
+            path: PathBuf::from("python"),
+            requested_version: requested.map(|v| v.to_string()),
+            selected_version: PythonVersion::new(3, 9, 6),
+        };
+
+        assert!(!resolved(None).version_differs());
+        assert!(!resolved(Some("3.9")).version_differs());
+        assert!(!resolved(Some("3.9.6")).version_differs());
+        assert!(!resolved(Some(">=3.8,<3.10")).version_differs());
+        assert!(resolved(Some("3.8")).version_differs());
+        assert!(resolved(Some("3.9.5")).version_differs());
+        assert!(resolved(Some("3.9.6rc1")).version_differs());
+        assert!(resolved(Some(">=3.10")).version_differs());
+
+        let selected = |version: &str| ResolvedDistribution {
+            selected_version: version.parse().unwrap(),
+            ..resolved(Some("3.9"))
+        };
+        assert!(selected("1!3.9.6").version_differs());
+        assert!(selected("3.9.6.post1").version_differs());
+        assert!(selected("3.9.6rc1").version_differs());
+        assert!(!selected("3.9.7").version_differs());
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let csv = PYTHON_DISTRIBUTIONS.to_csv();
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[0],
+            "target_triple,python_version,flavor,optimizations,url,sha256"
+        );
+        assert_eq!(lines.len(), PYTHON_DISTRIBUTIONS.iter().count() + 1);
+        assert!(lines.contains(&"aarch64-apple-darwin,3.9.6,standalone_dynamic,pgo,https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-aarch64-apple-darwin-pgo-20210724T1424.tar.zst,f5f9b84302aafdb0792d17c9301581fbce32a0eb9bc0838b2dffa023b1522a5e"));
+
+        // Built-in versions sort the same lexically and numerically.
+        let mut sorted = lines[1..].to_vec();
+        sorted.sort_unstable();
+        assert_eq!(lines[1..], sorted[..]);
+
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+        dists.register(PythonDistributionRecord {
             python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-unknown-linux-gnu-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "343e2d349779efb7d46f7eb01ec6a202bff14293616ce8103bc8060c777bb231".to_string(),
+            python_version: PythonVersion::new(3, 9, 6),
+            location: PythonDistributionLocation::Local {
+                local_path: "dists/cpython, \"custom\".tar.zst".to_string(),
+                sha256: "00".repeat(32),
             },
             target_triple: "x86_64-unknown-linux-gnu".to_string(),
             supports_prebuilt_extension_modules: true,
-        },
+            optimizations: None,
+            min_os_version: None,
+            strip_prefix: None,
+            seed_packages: None,
+            labels: BTreeMap::new(),
+            layout: DistributionLayout::Install,
+            archive_size: None,
+            experimental: false,
+            library_build_modes: BTreeMap::new(),
+            extension_modules: None,
+        });
+        assert_eq!(
+            dists.to_csv().lines().nth(1).unwrap(),
+            format!(
+                "x86_64-unknown-linux-gnu,3.9.6,standalone_dynamic,,\"dists/cpython, \"\"custom\"\".tar.zst\",{}",
+                "00".repeat(32)
+            )
+        );
+    }
 
-        // Linux musl.
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-unknown-linux-musl-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "29652e8dec55f36b11e009df675dd404fe2345c3a7cb1c1bcc5cedca0c9cf635".to_string(),
-            },
-            target_triple: "x86_64-unknown-linux-musl".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-unknown-linux-musl-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "70974f0c687442aacf3b821379ee9423cdcf02809b39bd61f9d959f79b0d1630".to_string(),
-            },
-            target_triple: "x86_64-unknown-linux-musl".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
+    #[test]
+    fn test_find_for_cargo_target() {
+        let flavor = DistributionFlavor::Standalone;
+        let find = |target: Option<&str>, version: Option<&str>| {
+            PYTHON_DISTRIBUTIONS.find_for_target_env(
+                target.map(|t| t.to_string()),
+                &flavor,
+                version,
+            )
+        };
 
-        // The order here is important because we will choose the
-        // first one. We prefer shared distributions on Windows because
-        // they are more versatile: statically linked Windows distributions
-        // don't declspec(dllexport) Python symbols and can't load shared
-        // shared library Python extensions, making them a pain to work
-        // with.
+        assert_eq!(
+            find(Some("x86_64-unknown-linux-gnu"), None)
+                .unwrap()
+                .target_triple,
+            "x86_64-unknown-linux-gnu"
+        );
+        assert!(find(None, None).unwrap_err().to_string().contains("TARGET"));
+        assert!(find(Some("riscv64gc-unknown-linux-gnu"), None)
+            .unwrap_err()
+            .to_string()
+            .contains("no Python distributions are known for target riscv64gc-unknown-linux-gnu"));
 
-        // Windows shared.
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-i686-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "af5eeaccfb6ab9f1f894d82100c542db29324d703d7dd5d2c433c45b369e3da2".to_string(),
-            },
-            target_triple: "i686-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-i686-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "0cc12b92d6fa427bbb5e14d2a9f05d90d651558887566dab9391adddcadd9e15".to_string(),
-            },
-            target_triple: "i686-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "ad7a3f64383ff92bbf56478521bae602e6e4a6be6936b0f50e42e83575b4e527".to_string(),
-            },
-            target_triple: "x86_64-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-pc-windows-msvc-shared-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "6525f62ce45d1629b68b08836d0ab681cf3c6d2cf9c3fd63c8fd6b36a90fb9db".to_string(),
-            },
-            target_triple: "x86_64-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
+        let err = find(Some("x86_64-unknown-linux-gnu"), Some("2.7")).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DistributionError>(),
+            Some(DistributionError::NotFound { .. })
+        ));
+    }
 
-        // Windows static.
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-i686-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "b8b64491f52fdfd701d049f9edfd762e26b284b429cfd13c4d6ee630233d81c2".to_string(),
-            },
-            target_triple: "i686-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-i686-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "82235f8de14ec3123c20406fb9732766b6c0141e266824864e3cce43117b4330".to_string(),
-            },
-            target_triple: "i686-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "5271f109a142374f4ccfd4061190fcbb9deec5604bcd99a03e3bc8cdfe9e354b".to_string(),
-            },
-            target_triple: "x86_64-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-pc-windows-msvc-static-noopt-20210724T1424.tar.zst".to_string(),
-                sha256: "0bd9ac2d3221e8588b9e728b7b774807724b845c299682ae9a990c406ff5f83b".to_string(),
-            },
-            target_triple: "x86_64-pc-windows-msvc".to_string(),
-            supports_prebuilt_extension_modules: false,
-        },
+    #[test]
+    fn test_newest_overall() {
+        let newest = PYTHON_DISTRIBUTIONS
+            .newest_overall(&DistributionFlavor::Standalone)
+            .unwrap();
+        assert_eq!(newest.python_version, PythonVersion::new(3, 9, 6));
+        assert_eq!(newest.target_triple, "aarch64-apple-darwin");
 
-        // macOS.
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-aarch64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "f5f9b84302aafdb0792d17c9301581fbce32a0eb9bc0838b2dffa023b1522a5e".to_string(),
-            },
-            target_triple: "aarch64-apple-darwin".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.8".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.8.11-x86_64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "291bb8ca565338959dbb93908557d52ce4de65e4d8bacfd0e3dc1c69917627e3".to_string(),
-            },
-            target_triple: "x86_64-apple-darwin".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-        PythonDistributionRecord {
-            python_major_minor_version: "3.9".to_string(),
-            location: PythonDistributionLocation::Url {
-                url: "https://github.com/indygreg/python-build-standalone/releases/download/20210724/cpython-3.9.6-x86_64-apple-darwin-pgo-20210724T1424.tar.zst".to_string(),
-                sha256: "9e11a09bf1c2e4c1e2c4d0f403e7199186a6f993ecc135e31aa57294b5634cc7".to_string(),
-            },
-            target_triple: "x86_64-apple-darwin".to_string(),
-            supports_prebuilt_extension_modules: true,
-        },
-    ];
+        let newest = PYTHON_DISTRIBUTIONS
+            .newest_overall(&DistributionFlavor::StandaloneStatic)
+            .unwrap();
+        assert_eq!(newest.target_triple, "i686-pc-windows-msvc");
 
-    PythonDistributionCollection { dists }
-});
+        let mut dists = PythonDistributionCollection { dists: vec![] };
+        for version in ["3.9.6", "3.10.0", "3.11.0rc1", "3.10.0"] {
+            let mut record = PYTHON_DISTRIBUTIONS.iter().next().unwrap().clone();
+            record.python_version = version.parse().unwrap();
+            record.target_triple = format!("{}-triple", version);
+            dists.register(record);
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // 3.10 is newer than 3.9 and the 3.11 pre-release is ignored.
+        let newest = dists
+            .newest_overall(&DistributionFlavor::Standalone)
+            .unwrap();
+        assert_eq!(newest.python_version.to_string(), "3.10.0");
+        assert!(std::ptr::eq(newest, &dists.dists[1]));
+    }
 
     #[test]
-    fn test_all_target_triples() {
-        assert_eq!(
-            PYTHON_DISTRIBUTIONS
-                .all_target_triples()
-                .collect::<Vec<_>>(),
-            vec![
-                "aarch64-apple-darwin",
-                "i686-pc-windows-msvc",
-                "x86_64-apple-darwin",
-                "x86_64-pc-windows-msvc",
+    fn test_wheels_compatible() {
+        let expected = [
+            ("aarch64-apple-darwin", "macosx_11_0_arm64"),
+            ("i686-pc-windows-msvc", "win32"),
+            ("x86_64-apple-darwin", "macosx_10_9_x86_64"),
+            ("x86_64-pc-windows-msvc", "win_amd64"),
+            ("x86_64-unknown-linux-gnu", "manylinux2014_x86_64"),
+            ("x86_64-unknown-linux-musl", "musllinux_1_1_x86_64"),
+        ];
+
+        // Every built-in triple has a compatible tag and is compatible with no
+        // other triple's tag.
+        for triple in PYTHON_DISTRIBUTIONS.all_target_triples() {
+            assert!(
+                expected.iter().any(|(t, _)| *t == triple),
+                "no expected wheel tag for {}",
+                triple
+            );
+
+            for (other, tag) in &expected {
+                assert_eq!(
+                    wheels_compatible(triple, tag),
+                    *other == triple,
+                    "{} {}",
+                    triple,
+                    tag
+                );
+            }
+            assert!(wheels_compatible(triple, "any"));
+        }
+
+        let gnu = "x86_64-unknown-linux-gnu";
+        assert!(wheels_compatible(gnu, "manylinux1_x86_64"));
+        assert!(wheels_compatible(gnu, "manylinux2010_x86_64"));
+        assert!(wheels_compatible(gnu, "manylinux_2_17_x86_64"));
+        assert!(!wheels_compatible(gnu, "manylinux_2_28_x86_64"));
+        assert!(wheels_compatible(
+            gnu,
+            "manylinux_2_17_x86_64.manylinux2014_x86_64"
+        ));
+        assert!(!wheels_compatible(gnu, "manylinux2014_aarch64"));
+        assert!(!wheels_compatible(gnu, "musllinux_1_1_x86_64"));
+        assert!(!wheels_compatible(gnu, "linux_x86_64"));
+        assert!(wheels_compatible(
+            "aarch64-unknown-linux-gnu",
+            "manylinux2014_aarch64"
+        ));
+        assert!(wheels_compatible(
+            "i686-unknown-linux-gnu",
+            "manylinux2010_i686"
+        ));
+
+        let musl = "x86_64-unknown-linux-musl";
+        assert!(wheels_compatible(musl, "musllinux_1_2_x86_64"));
+        assert!(!wheels_compatible(musl, "musllinux_1_3_x86_64"));
+        assert!(!wheels_compatible(musl, "manylinux2014_x86_64"));
+
+        let x86_mac = "x86_64-apple-darwin";
+        assert!(wheels_compatible(x86_mac, "macosx_10_6_intel"));
+        assert!(wheels_compatible(x86_mac, "macosx_10_9_universal2"));
+        assert!(!wheels_compatible(x86_mac, "macosx_10_15_x86_64"));
+        assert!(!wheels_compatible(x86_mac, "macosx_10_9_arm64"));
+        assert!(wheels_compatible(
+            "aarch64-apple-darwin",
+            "macosx_10_9_universal2"
+        ));
+        assert!(!wheels_compatible(
+            "aarch64-apple-darwin",
+            "macosx_12_0_arm64"
+        ));
+
+        assert!(wheels_compatible("aarch64-pc-windows-msvc", "win_arm64"));
+        assert!(!wheels_compatible("x86_64-pc-windows-msvc", "win32"));
+
+        assert!(!wheels_compatible(
+            "riscv64gc-unknown-linux-gnu",
+            "manylinux2014_riscv64"
+        ));
+        assert!(!wheels_compatible(gnu, "bogus"));
+    }
+
+    #[test]
+    fn test_find_by_os_arch() {
+        for (os, arch, libc, expected) in [
+            (
+                TargetOs::Linux,
+                TargetArch::X86_64,
+                None,
                 "x86_64-unknown-linux-gnu",
+            ),
+            (
+                TargetOs::Linux,
+                TargetArch::X86_64,
+                Some(TargetLibc::Musl),
                 "x86_64-unknown-linux-musl",
-            ]
-        );
+            ),
+            (
+                TargetOs::MacOs,
+                TargetArch::Aarch64,
+                None,
+                "aarch64-apple-darwin",
+            ),
+            (
+                TargetOs::Windows,
+                TargetArch::X86,
+                Some(TargetLibc::Musl),
+                "i686-pc-windows-msvc",
+            ),
+        ] {
+            assert_eq!(target_triple_for(os, arch, libc), expected);
+
+            let record = PYTHON_DISTRIBUTIONS
+                .find_by_os_arch(os, arch, libc, &DistributionFlavor::Standalone, None)
+                .unwrap();
+            assert_eq!(record.target_triple, expected);
+        }
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_by_os_arch(
+                TargetOs::Linux,
+                TargetArch::Aarch64,
+                None,
+                &DistributionFlavor::Standalone,
+                None
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_by_url() {
+        for record in PYTHON_DISTRIBUTIONS.iter() {
+            if let PythonDistributionLocation::Url { url, .. } = &record.location {
+                assert_eq!(PYTHON_DISTRIBUTIONS.find_by_url(url), Some(record));
+            }
+        }
+
+        assert!(PYTHON_DISTRIBUTIONS
+            .find_by_url("https://example.com/missing.tar.zst")
+            .is_none());
     }
 }
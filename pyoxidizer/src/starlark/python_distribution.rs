@@ -139,14 +139,19 @@ impl PythonDistributionValue {
 
         let python_version_str = python_version.as_deref();
 
-        let location = default_distribution_location(&flavor, &build_target, python_version_str)
-            .map_err(|e| {
-                ValueError::from(RuntimeError {
-                    code: "PYOXIDIZER_BUILD",
-                    message: format!("{:?}", e),
-                    label: "default_python_distribution()".to_string(),
-                })
-            })?;
+        let location = default_distribution_location(
+            pyoxidizer_context.logger(),
+            &flavor,
+            &build_target,
+            python_version_str,
+        )
+        .map_err(|e| {
+            ValueError::from(RuntimeError {
+                code: "PYOXIDIZER_BUILD",
+                message: format!("{:?}", e),
+                label: "default_python_distribution()".to_string(),
+            })
+        })?;
 
         Ok(Value::new(PythonDistributionValue::from_location(location)))
     }
@@ -296,6 +301,7 @@ impl PythonDistributionValue {
         } else {
             let flavor = DistributionFlavor::Standalone;
             let location = default_distribution_location(
+                pyoxidizer_context.logger(),
                 &flavor,
                 &pyoxidizer_context.build_host_triple,
                 Some(dist.python_major_minor_version().as_str()),
@@ -604,6 +610,32 @@ mod tests {
         assert_eq!(x.source, wanted);
     }
 
+    #[test]
+    fn test_python_distribution_to_starlark() -> Result<()> {
+        let mut records = PYTHON_DISTRIBUTIONS.iter().cloned().collect::<Vec<_>>();
+        let mut local = records[0].clone();
+        local.location = PythonDistributionLocation::Local {
+            local_path: "C:\\dists\\\"quoted\"\n.tar.zst".to_string(),
+            sha256: "00".repeat(32),
+        };
+        records.push(local);
+
+        for record in records {
+            let dist = starlark_ok(&record.to_starlark()?);
+            let x = dist.downcast_ref::<PythonDistributionValue>().unwrap();
+            assert_eq!(x.source, record.location);
+        }
+
+        let mut oci = PYTHON_DISTRIBUTIONS.iter().next().unwrap().clone();
+        oci.location = PythonDistributionLocation::Oci {
+            reference: "registry.example.com/python:3.9".to_string(),
+            digest: "00".repeat(32),
+        };
+        assert!(oci.to_starlark().is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_make_python_packaging_policy() {
         let policy = starlark_ok("default_python_distribution().make_python_packaging_policy()");
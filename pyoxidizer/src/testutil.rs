@@ -15,7 +15,7 @@ use {
     anyhow::{anyhow, Result},
     once_cell::sync::Lazy,
     slog::{Drain, Logger},
-    std::sync::Arc,
+    std::sync::{Arc, Mutex, MutexGuard, PoisonError},
 };
 
 static ENVIRONMENT: Lazy<Environment> =
@@ -35,6 +35,17 @@ pub fn get_logger() -> Result<slog::Logger> {
     ))
 }
 
+static GLOBAL_SETTINGS: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Serialize tests that change process global distribution settings.
+///
+/// Hold the returned guard for as long as the test relies on its settings.
+pub fn lock_global_settings() -> MutexGuard<'static, ()> {
+    GLOBAL_SETTINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
 pub static DISTRIBUTION_CACHE: Lazy<Arc<DistributionCache>> = Lazy::new(|| {
     Arc::new(DistributionCache::new(Some(
         &get_env()
@@ -49,7 +60,7 @@ pub fn get_distribution(
     let env = get_env()?;
     let logger = get_logger()?;
 
-    let dest_path = env.cache_dir().join("python_distributions");
+    let dest_path = env.python_distributions_dir();
 
     DISTRIBUTION_CACHE.resolve_distribution(&logger, location, Some(&dest_path))
 }
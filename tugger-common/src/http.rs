@@ -35,8 +35,10 @@ fn sha256_path<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
     Ok(hasher.finalize().to_vec())
 }
 
-/// Obtain an HTTP client, taking proxy environment variables into account.
-pub fn get_http_client() -> reqwest::Result<reqwest::blocking::Client> {
+/// Obtain an HTTP client builder, taking proxy environment variables into account.
+///
+/// This allows callers to further customize the client before building it.
+pub fn get_http_client_builder() -> reqwest::blocking::ClientBuilder {
     let mut builder = reqwest::blocking::ClientBuilder::new();
 
     for (key, value) in std::env::vars() {
@@ -49,6 +51,9 @@ pub fn get_http_client() -> reqwest::Result<reqwest::blocking::Client> {
                 if let Some(Ok(proxy)) = match schema {
                     "http" => Some(reqwest::Proxy::http(url.as_str())),
                     "https" => Some(reqwest::Proxy::https(url.as_str())),
+                    // Proxies for all schemes. This is how SOCKS proxies (e.g.
+                    // `socks5://host:port`) are typically defined.
+                    "all" => Some(reqwest::Proxy::all(url.as_str())),
                     _ => None,
                 } {
                     builder = builder.proxy(proxy);
@@ -57,7 +62,12 @@ pub fn get_http_client() -> reqwest::Result<reqwest::blocking::Client> {
         }
     }
 
-    builder.build()
+    builder
+}
+
+/// Obtain an HTTP client, taking proxy environment variables into account.
+pub fn get_http_client() -> reqwest::Result<reqwest::blocking::Client> {
+    get_http_client_builder().build()
 }
 
 /// Fetch a URL and verify its SHA-256 matches expectations.